@@ -0,0 +1,47 @@
+//! Compare COPY-based message insertion against the row-by-row loop
+//! on 50k rows. Requires a live database (DATABASE_URL).
+//!
+//! Run with: cargo bench --bench copy_messages
+
+use rust_ingester::db::{self, message_ops, models::TurnEmbedding};
+use std::time::Instant;
+use uuid::Uuid;
+
+const ROWS: usize = 50_000;
+
+fn make_turns(conversation_id: Uuid) -> Vec<TurnEmbedding> {
+    (0..ROWS)
+        .map(|i| TurnEmbedding {
+            message_id: Uuid::new_v4(),
+            conversation_id,
+            actual_text: format!("benchmark message number {}", i),
+            embedding: (0..768).map(|d| ((i + d) % 100) as f32 / 100.0).collect(),
+            embedding_model: None,
+        })
+        .collect()
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let client = db::connect::get_client().await?;
+
+    let conversation_id = Uuid::new_v4();
+    message_ops::insert_conversation(&client, conversation_id).await?;
+
+    let turns = make_turns(conversation_id);
+    let start = Instant::now();
+    let copied = message_ops::copy_insert_messages(&client, &turns).await?;
+    let copy_elapsed = start.elapsed();
+
+    let turns = make_turns(conversation_id);
+    let start = Instant::now();
+    for turn in &turns {
+        message_ops::insert_message_with_embedding(&client, turn).await?;
+    }
+    let loop_elapsed = start.elapsed();
+
+    println!("Inserting {} messages with embeddings:", ROWS);
+    println!("  COPY:     {:?} ({} rows)", copy_elapsed, copied);
+    println!("  row loop: {:?}", loop_elapsed);
+    Ok(())
+}
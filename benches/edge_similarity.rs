@@ -0,0 +1,75 @@
+//! Compare ranking edge embeddings in Rust (fetch every bucket candidate, score
+//! client-side) against ranking them in SQL with pgvector on a 100k-row bucket.
+//! Requires a live database (DATABASE_URL).
+//!
+//! Run with: cargo bench --bench edge_similarity
+
+use rand::{Rng, SeedableRng};
+use rust_ingester::db;
+use rust_ingester::etl::similarity::{DimensionMismatchMode, SimilarityMetric};
+use rust_ingester::retrieve::{score_candidates, sort_by_distance};
+use std::time::Instant;
+use uuid::Uuid;
+
+const ROWS: i64 = 100_000;
+const DIM: usize = 768;
+const TOP_K: usize = 10;
+const ROUNDS: u32 = 5;
+/// Every row shares this bucket, the worst case for an LSH lookup
+const BUCKET: i32 = -4242;
+/// Ids well away from real triplet ids
+const BASE_ID: i64 = -9_000_000_000;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let client = db::connect::get_client().await?;
+    let model = format!("bench-{}", Uuid::new_v4().simple());
+
+    // Random vectors generated server-side, so seeding doesn't dominate the run
+    let start = Instant::now();
+    client
+        .execute(
+            "INSERT INTO ag_catalog.embeddings(triplet_id, vec, lsh_bucket, lsh_buckets, embedding_model, session_id, edge_text)
+             SELECT $1 - g,
+                    ARRAY(SELECT random() * 2 - 1 FROM generate_series(1, $3::int) WHERE g > 0)::real[]::vector,
+                    $4, ARRAY[$4::int], $5, 'bench', 'a RELATES_TO b'
+             FROM generate_series(1, $2::bigint) AS g",
+            &[&BASE_ID, &ROWS, &(DIM as i32), &BUCKET, &model],
+        )
+        .await?;
+    println!("Seeded {} rows x {} dims in {:?}", ROWS, DIM, start.elapsed());
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+    let query: Vec<f32> = (0..DIM).map(|_| rng.gen_range(-1.0..1.0)).collect();
+    let fallback_limit = ROWS as usize;
+
+    let start = Instant::now();
+    let mut client_top = Vec::new();
+    for _ in 0..ROUNDS {
+        let candidates = db::vector::fetch_lsh_candidates(&client, &[BUCKET], Some(&model), fallback_limit).await?;
+        let mut scored = score_candidates(&query, &candidates.candidates, SimilarityMetric::Cosine, DimensionMismatchMode::Skip)?;
+        sort_by_distance(&mut scored);
+        scored.truncate(TOP_K);
+        client_top = scored;
+    }
+    println!("  fetch + score in Rust: {:?}", start.elapsed() / ROUNDS);
+
+    let start = Instant::now();
+    let mut sql_top = Vec::new();
+    for _ in 0..ROUNDS {
+        let nearest = db::vector::nearest_lsh_embeddings(
+            &client, &query, &[BUCKET], SimilarityMetric::Cosine, DimensionMismatchMode::Skip,
+            Some(&model), fallback_limit, TOP_K,
+        ).await?;
+        sql_top = nearest.neighbors.iter().map(|n| n.triplet_id).collect();
+    }
+    println!("  pgvector ORDER BY:     {:?}", start.elapsed() / ROUNDS);
+
+    let client_ids: Vec<i64> = client_top.iter().map(|(id, _)| *id).collect();
+    println!("  same top {}: {}", TOP_K, client_ids == sql_top);
+
+    client
+        .execute("DELETE FROM ag_catalog.embeddings WHERE embedding_model = $1", &[&model])
+        .await?;
+    Ok(())
+}
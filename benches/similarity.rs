@@ -0,0 +1,59 @@
+//! Compare the single-pass cosine implementations against the old three-pass version.
+//!
+//! Run with: cargo bench --bench similarity
+
+use rand::{Rng, SeedableRng};
+use rust_ingester::etl::similarity::{cosine_similarity, CosineQuery};
+use std::hint::black_box;
+use std::time::Instant;
+
+const DIM: usize = 768;
+const ROWS: usize = 10_000;
+const ROUNDS: usize = 20;
+
+/// Previous implementation: three separate passes per pair
+fn cosine_three_pass(a: &[f32], b: &[f32]) -> f32 {
+    let dot_product: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot_product / (norm_a * norm_b)
+    }
+}
+
+fn main() {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+    let query: Vec<f32> = (0..DIM).map(|_| rng.gen_range(-1.0..1.0)).collect();
+    let rows: Vec<Vec<f32>> = (0..ROWS)
+        .map(|_| (0..DIM).map(|_| rng.gen_range(-1.0..1.0)).collect())
+        .collect();
+
+    println!("Scoring {} rows x {} dims, {} rounds", ROWS, DIM, ROUNDS);
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        for row in &rows {
+            black_box(cosine_three_pass(black_box(&query), row));
+        }
+    }
+    println!("  three-pass:          {:?}", start.elapsed() / ROUNDS as u32);
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        for row in &rows {
+            black_box(cosine_similarity(black_box(&query), row));
+        }
+    }
+    println!("  single-pass:         {:?}", start.elapsed() / ROUNDS as u32);
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        let q = CosineQuery::new(black_box(&query));
+        for row in &rows {
+            black_box(q.similarity(row));
+        }
+    }
+    println!("  precomputed query:   {:?}", start.elapsed() / ROUNDS as u32);
+}
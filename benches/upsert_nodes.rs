@@ -0,0 +1,37 @@
+//! Compare per-node `upsert_node` round trips against `upsert_nodes_batch`
+//! on a 1000-node graph. Requires a live database (DATABASE_URL).
+//!
+//! Run with: cargo bench --bench upsert_nodes
+
+use rust_ingester::{config::Config, db};
+use serde_json::Value;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+const NODES: usize = 1000;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let graph = Config::from_env().graph_name;
+    let client = db::connect::get_client().await?;
+    let run = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+    let props = Value::Null;
+
+    let loop_pks: Vec<String> = (0..NODES).map(|i| format!("bench_loop_{run}_{i}")).collect();
+    let start = Instant::now();
+    for pk in &loop_pks {
+        db::graph::upsert_node(&client, &graph, "Node", pk, &props).await?;
+    }
+    let loop_elapsed = start.elapsed();
+
+    let batch_pks: Vec<String> = (0..NODES).map(|i| format!("bench_batch_{run}_{i}")).collect();
+    let batch: Vec<(&str, &str, &Value)> = batch_pks.iter().map(|pk| ("Node", pk.as_str(), &props)).collect();
+    let start = Instant::now();
+    let ids = db::graph::upsert_nodes_batch(&client, &graph, &batch).await?;
+    let batch_elapsed = start.elapsed();
+    assert_eq!(ids.len(), NODES);
+
+    println!("Upserting {} nodes:", NODES);
+    println!("  per-node loop: {:?}", loop_elapsed);
+    println!("  batch UNWIND:  {:?}", batch_elapsed);
+    Ok(())
+}
@@ -0,0 +1,125 @@
+//! Compare `VECTOR_STORAGE=f32` (pgvector `vector`) against `f16` (`halfvec`): table
+//! and ivfflat index size, and recall@10 of the f16 cosine ranking against the f32 one.
+//! The edges of a fixture file are embedded (hashed placeholders when no embedding
+//! server is reachable) and mixed with random distractor vectors; each edge is then
+//! used as a query. Requires a live database (DATABASE_URL).
+//!
+//! Run with: cargo bench --bench vector_storage [-- Data/ok.json]
+
+use rust_ingester::config::Config;
+use rust_ingester::db::{self, vector_index::VectorStorage};
+use rust_ingester::etl::embed::{self, EmbeddingProvider};
+use rust_ingester::etl::{parser::KnowledgeGraphData, payload::parse_payload};
+use pgvector::Vector;
+use std::collections::HashSet;
+use std::time::Instant;
+
+const DISTRACTORS: i64 = 50_000;
+const DIM: usize = 768;
+const TOP_K: i64 = 10;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let fixture = std::env::args().skip(1).find(|a| !a.starts_with("--")).unwrap_or_else(|| "Data/ok.json".to_string());
+    let data: KnowledgeGraphData = parse_payload(&std::fs::read_to_string(&fixture)?)?;
+    let texts: Vec<String> = data
+        .values()
+        .flat_map(|g| g.edges.iter().map(|e| format!("{} {} {}", e.source, e.relation, e.target)))
+        .collect();
+
+    let cfg = Config::global();
+    let mut queries = Vec::with_capacity(texts.len());
+    let mut placeholders = 0;
+    for text in &texts {
+        let (vec, provider) = embed::embed_text_with_provider(cfg, text).await?;
+        if provider == EmbeddingProvider::Placeholder || vec.len() != DIM {
+            placeholders += 1;
+            queries.push(embed::text_placeholder_embedding(text, DIM));
+        } else {
+            queries.push(vec);
+        }
+    }
+
+    let client = db::connect::get_client().await?;
+    let tables = [(VectorStorage::F32, "bench_vectors_f32"), (VectorStorage::F16, "bench_vectors_f16")];
+    for (storage, table) in tables {
+        client
+            .batch_execute(&format!(
+                "CREATE TEMP TABLE {} (id BIGINT PRIMARY KEY, embedding {} NOT NULL)",
+                table,
+                storage.column_type(DIM)
+            ))
+            .await?;
+    }
+
+    // Fixture edges get ids 0..n; distractors are generated server-side and copied
+    // into the f16 table, so both hold the same vectors
+    let start = Instant::now();
+    for (id, vec) in queries.iter().enumerate() {
+        client
+            .execute(
+                "INSERT INTO bench_vectors_f32 (id, embedding) VALUES ($1, $2::vector)",
+                &[&(id as i64), &Vector::from(vec.clone())],
+            )
+            .await?;
+    }
+    client
+        .execute(
+            "INSERT INTO bench_vectors_f32 (id, embedding)
+             SELECT 1000000 + g,
+                    ARRAY(SELECT random() * 2 - 1 FROM generate_series(1, $2::int) WHERE g > 0)::real[]::vector
+             FROM generate_series(1, $1::bigint) AS g",
+            &[&DISTRACTORS, &(DIM as i32)],
+        )
+        .await?;
+    client
+        .batch_execute("INSERT INTO bench_vectors_f16 SELECT id, embedding FROM bench_vectors_f32; ANALYZE bench_vectors_f32; ANALYZE bench_vectors_f16;")
+        .await?;
+    println!(
+        "Seeded {} fixture edges ({} placeholder vectors) + {} distractors x {} dims in {:?}",
+        queries.len(), placeholders, DISTRACTORS, DIM, start.elapsed()
+    );
+
+    // Exact (sequential scan) top 10 per query from each table
+    let mut results: Vec<Vec<HashSet<i64>>> = Vec::new();
+    for (storage, table) in tables {
+        let sql = format!(
+            "SELECT id FROM {} ORDER BY embedding <=> {} LIMIT $2",
+            table,
+            storage.query_param("$1")
+        );
+        let start = Instant::now();
+        let mut top = Vec::with_capacity(queries.len());
+        for vec in &queries {
+            let rows = client.query(&sql, &[&Vector::from(vec.clone()), &TOP_K]).await?;
+            top.push(rows.iter().map(|r| r.get::<_, i64>(0)).collect::<HashSet<i64>>());
+        }
+        println!("  {} exact top {}: {:?} per query", storage.as_str(), TOP_K, start.elapsed() / queries.len().max(1) as u32);
+        results.push(top);
+    }
+    let overlap: usize = results[0].iter().zip(&results[1]).map(|(a, b)| a.intersection(b).count()).sum();
+    let recall = overlap as f64 / (queries.len() as f64 * TOP_K as f64);
+
+    println!("  {:<6} {:>12} {:>12}", "", "table", "ivfflat");
+    let mut sizes = Vec::new();
+    for (storage, table) in tables {
+        client
+            .batch_execute(&format!(
+                "CREATE INDEX {}_ivfflat ON {} USING ivfflat (embedding {}) WITH (lists = 100)",
+                table, table, storage.cosine_ops()
+            ))
+            .await?;
+        let row = client
+            .query_one(
+                &format!("SELECT pg_table_size('{0}'), pg_relation_size('{0}_ivfflat')", table),
+                &[],
+            )
+            .await?;
+        let (table_bytes, index_bytes): (i64, i64) = (row.get(0), row.get(1));
+        println!("  {:<6} {:>9.1} MB {:>9.1} MB", storage.as_str(), table_bytes as f64 / 1e6, index_bytes as f64 / 1e6);
+        sizes.push(table_bytes);
+    }
+    println!("  f16/f32 table size: {:.2}", sizes[1] as f64 / sizes[0] as f64);
+    println!("  recall@{} of f16 vs f32: {:.4}", TOP_K, recall);
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use axum::{
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use subtle::ConstantTimeEq;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::api::models::ErrorResponse;
+
+/// Header carrying the API key on authenticated requests
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Middleware rejecting requests without a matching `X-API-Key` header.
+/// When no key is configured every request passes through (local dev).
+pub async fn require_api_key(
+    State(api_key): State<Option<String>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(expected) = api_key else {
+        return next.run(request).await;
+    };
+
+    let provided = request
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok());
+
+    match provided {
+        // Constant-time, so response timing doesn't reveal how much of the key matched
+        Some(key) if bool::from(key.as_bytes().ct_eq(expected.as_bytes())) => next.run(request).await,
+        Some(_) => (
+            StatusCode::UNAUTHORIZED,
+            Json(ErrorResponse::new("unauthorized", "Invalid API key")),
+        )
+            .into_response(),
+        None => (
+            StatusCode::UNAUTHORIZED,
+            Json(ErrorResponse::new("unauthorized", "Missing X-API-Key header")),
+        )
+            .into_response(),
+    }
+}
+
+/// Build the CORS layer from the configured origin allowlist.
+/// An empty allowlist allows no cross-origin requests; `*` allows any origin
+/// (the permissive policy, for local dev) and must be configured explicitly.
+pub fn cors_layer(allowed_origins: &[String]) -> CorsLayer {
+    if allowed_origins.is_empty() {
+        return CorsLayer::new();
+    }
+    if allowed_origins.iter().any(|o| o == "*") {
+        return CorsLayer::permissive();
+    }
+
+    let origins: Vec<HeaderValue> = allowed_origins
+        .iter()
+        .filter_map(|o| match HeaderValue::from_str(o) {
+            Ok(v) => Some(v),
+            Err(_) => {
+                tracing::warn!("⚠️  Ignoring invalid CORS origin: {}", o);
+                None
+            }
+        })
+        .collect();
+
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods(tower_http::cors::Any)
+        .allow_headers(tower_http::cors::Any)
+}
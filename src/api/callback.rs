@@ -0,0 +1,64 @@
+//! Completion callbacks: POST an ingest result to a caller-supplied URL
+
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::etl::embed::http_client;
+
+/// Attempts per callback (the first try plus retries) before giving up
+pub const CALLBACK_MAX_ATTEMPTS: u32 = 3;
+
+/// Per-attempt request timeout
+pub const CALLBACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Delay before the first retry; doubled for each later one
+pub const CALLBACK_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Check a `callback_url` before accepting the request: it must be an absolute http(s) URL
+pub fn validate_callback_url(url: &str) -> Result<(), String> {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => Ok(()),
+        Ok(parsed) => Err(format!("callback_url must use http or https, got '{}'", parsed.scheme())),
+        Err(e) => Err(format!("Invalid callback_url '{}': {}", url, e)),
+    }
+}
+
+/// POST `payload` as JSON to `url`, retrying connection errors, timeouts, 429 and 5xx
+/// responses with exponential backoff. Returns the number of attempts made.
+pub async fn post_callback<T: Serialize>(url: &str, payload: &T, retry_delay: Duration) -> Result<u32, String> {
+    let mut delay = retry_delay;
+    let mut attempt = 1;
+    loop {
+        let error = match http_client().post(url).timeout(CALLBACK_TIMEOUT).json(payload).send().await {
+            Ok(response) if response.status().is_success() => return Ok(attempt),
+            Ok(response) => {
+                let status = response.status();
+                let error = format!("callback returned {}", status);
+                // Other client errors won't change on retry
+                if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+                    return Err(error);
+                }
+                error
+            }
+            Err(e) => format!("callback request failed: {}", e),
+        };
+        if attempt >= CALLBACK_MAX_ATTEMPTS {
+            return Err(error);
+        }
+        tracing::debug!(url, attempt, error = %error, "Retrying ingest callback");
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+        attempt += 1;
+    }
+}
+
+/// Deliver a callback on a spawned task so the caller isn't blocked; failures are logged
+pub fn spawn_callback<T: Serialize + Send + Sync + 'static>(url: String, payload: T) {
+    tokio::spawn(async move {
+        match post_callback(&url, &payload, CALLBACK_RETRY_DELAY).await {
+            Ok(attempts) => tracing::info!(url = %url, attempts, "📣 Ingest callback delivered"),
+            Err(e) => tracing::error!(url = %url, error = %e, "❌ Ingest callback failed"),
+        }
+    });
+}
@@ -1,37 +1,136 @@
-use axum::{http::StatusCode, Json};
+use axum::{extract::Path, http::StatusCode, Json};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Display;
+use tokio_postgres::Client;
+use chrono::NaiveDateTime;
 use uuid::Uuid;
+use crate::api::models::ErrorResponse;
+use crate::api::query_cache::{self, QueryCache};
+use crate::config::Config;
+use crate::telemetry;
+use crate::etl::similarity::cosine_similarity;
 use crate::db::{models::*, message_ops::*, kg_ops::*, connect::get_client};
 
 // ============================================================================
 // Request/Response Types
 // ============================================================================
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct ContextQueryRequest {
     pub query: String,
     pub top_k: Option<usize>,
     pub max_tokens: Option<usize>, // e.g., 4000 for context window
     pub include_kg_edges: Option<bool>,
     pub retrieval_mode: Option<String>, // "hybrid" (default), "kg_only", "direct_only"
+    pub fusion: Option<String>, // "weighted" (default), "rrf"
+    pub kg_min_similarity: Option<f32>, // drop KG edges less similar to the query than this
+    pub include_evidence_content: Option<bool>, // resolve evidence messages inline on each KG edge
+    pub max_evidence_per_edge: Option<usize>, // default DEFAULT_MAX_EVIDENCE_PER_EDGE
+    pub embedding_model: Option<String>, // only compare against vectors from this model
+    pub explain: Option<bool>, // attach a hybrid search scoring breakdown to each direct match
+    pub highlight: Option<bool>, // attach byte ranges of the query keywords to each direct match
+    pub render_template: Option<String>, // render the messages into `rendered_prompt`; "" uses DEFAULT_RENDER_TEMPLATE
+    pub min_content_length: Option<usize>, // drop shorter messages not found via KG edges; default MIN_CONTENT_LENGTH
+    pub dedup_threshold: Option<f32>, // collapse messages whose embeddings are more similar than this (0-1)
+    pub require_both: Option<bool>, // only keep direct matches found by both keyword and embedding search
+    pub min_relevance: Option<f32>, // drop messages whose combined relevance score is below this, before the token budget
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub queries: Vec<String>, // paraphrases of `query`, each retrieved for and fused into one evidence set
+    pub multi_query_fusion: Option<String>, // how per-query rankings are fused: "max" (default) or "rrf"
+    pub context_window: Option<usize>, // also return this many turns before and after each match, as source "context"
+    pub order_by: Option<String>, // order of the selected messages: "relevance" (default), "recency" or "mixed"
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ContextQueryResponse {
     pub formatted_context: FormattedLLMContext,
     pub knowledge_graph_edges: Vec<KGEdgeWithContext>,
     pub query_duration_ms: u128,
     pub total_evidence_messages: usize,
     pub retrieval_stats: RetrievalStats,
+    /// `formatted_context.messages` rendered with `render_template`, when one was given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rendered_prompt: Option<String>,
+    /// Served from the query cache (`QUERY_CACHE_SIZE`) rather than computed for this request
+    pub cached: bool,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct RetrievalStats {
     pub kg_edge_matches: usize,
     pub direct_message_matches: usize,
     pub total_unique_messages: usize,
     pub retrieval_mode: String,
+    pub fusion: String,
+    pub messages_by_source: BTreeMap<RetrievalSource, usize>,
+    /// Messages dropped for being shorter than `min_content_length`
+    pub short_messages_filtered: usize,
+    /// Messages dropped for scoring below `min_relevance`
+    pub below_min_relevance: usize,
+    /// Messages dropped as near-duplicates of a higher-ranked one (`dedup_threshold`)
+    pub near_duplicates_collapsed: usize,
+    /// Nodes not expanded by graph traversal for having more edges than `HUB_DEGREE_THRESHOLD`
+    pub hubs_skipped: Vec<String>,
+    /// Direct matches were limited to messages both keyword and embedding search found
+    pub require_both: bool,
+    /// Phrasings retrieved for: `query` plus the distinct `queries`. The match counts
+    /// above are summed over them.
+    pub queries: usize,
+    /// Surrounding turns added around the matches (`context_window`), before the token budget
+    pub context_messages: usize,
+    /// Order of `formatted_context.messages` (`order_by`)
+    pub order_by: String,
+}
+
+// ============================================================================
+// Errors
+// ============================================================================
+
+/// Error returned by the context handlers (same shape as `api::handlers`)
+pub type ContextError = (StatusCode, Json<ErrorResponse>);
+
+/// Reject an empty or whitespace-only query before any embedding or database work
+pub fn require_query(query: &str) -> Result<(), ContextError> {
+    if query.trim().is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new("invalid_request", "query must not be empty")),
+        ));
+    }
+    Ok(())
+}
+
+/// The database could not be reached
+pub fn db_connect_failed(e: impl Display) -> ContextError {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse::new("db_connect_failed", format!("Failed to connect to database: {}", e))),
+    )
+}
+
+/// The embedding server failed to embed the query
+pub fn embedding_failed(e: impl Display) -> ContextError {
+    (
+        StatusCode::BAD_GATEWAY,
+        Json(ErrorResponse::new("embedding_failed", format!("Failed to generate query embedding: {}", e))),
+    )
+}
+
+/// Too many embedding calls were already queued (`EMBED_QUEUE_LIMIT`)
+pub fn embedding_overloaded(e: impl Display) -> ContextError {
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        Json(ErrorResponse::new("embedding_overloaded", e.to_string())),
+    )
+}
+
+/// A retrieval step (KG search, message search, message fetch) failed
+pub fn retrieval_failed(step: &str, e: impl Display) -> ContextError {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse::new("retrieval_failed", format!("{} failed: {}", step, e))),
+    )
 }
 
 // ============================================================================
@@ -42,69 +141,411 @@ pub struct RetrievalStats {
 /// This retrieves relevant knowledge graph edges and their associated message content
 pub async fn query_llm_context(
     Json(payload): Json<ContextQueryRequest>,
-) -> Result<Json<ContextQueryResponse>, StatusCode> {
+) -> Result<Json<ContextQueryResponse>, ContextError> {
+    query_llm_context_with_config(Config::global(), query_cache::global(), payload).await
+}
+
+/// `query_llm_context` embedding with `cfg` and caching responses in `cache`
+pub async fn query_llm_context_with_config(
+    cfg: &Config,
+    cache: &QueryCache,
+    payload: ContextQueryRequest,
+) -> Result<Json<ContextQueryResponse>, ContextError> {
     let start = std::time::Instant::now();
+    let _timer = telemetry::time_query("llm_context");
+    require_query(&payload.query)?;
 
     let top_k = payload.top_k.unwrap_or(10);
     let max_tokens = payload.max_tokens.unwrap_or(4000);
     let include_kg_edges = payload.include_kg_edges.unwrap_or(false);
     let retrieval_mode = payload.retrieval_mode.as_deref().unwrap_or("hybrid");
+    let fusion = match payload.fusion.as_deref() {
+        None => FusionStrategy::default(),
+        Some(f) => match FusionStrategy::parse(f) {
+            Some(strategy) => strategy,
+            None => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse::new(
+                        "invalid_fusion",
+                        format!("Unknown fusion strategy '{}' (expected \"weighted\" or \"rrf\")", f),
+                    )),
+                ));
+            }
+        },
+    };
+
+    if let Some(threshold) = payload.dedup_threshold {
+        if !(threshold > 0.0 && threshold <= 1.0) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse::new("invalid_request", "dedup_threshold must be greater than 0 and at most 1")),
+            ));
+        }
+    }
 
-    println!("Retrieval mode: {}", retrieval_mode);
+    if payload.queries.len() > MAX_QUERIES - 1 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new("invalid_request", format!("at most {} queries in addition to query", MAX_QUERIES - 1))),
+        ));
+    }
+    for query in &payload.queries {
+        require_query(query)?;
+    }
+    let multi_query_fusion = match payload.multi_query_fusion.as_deref() {
+        None => MultiQueryFusion::default(),
+        Some(f) => MultiQueryFusion::parse(f).ok_or_else(|| (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new(
+                "invalid_fusion",
+                format!("Unknown multi_query_fusion '{}' (expected \"max\" or \"rrf\")", f),
+            )),
+        ))?,
+    };
 
-    println!("Querying LLM context for: '{}' (top_k={}, max_tokens={})",
-        payload.query, top_k, max_tokens);
+    if let Some(floor) = payload.min_relevance {
+        if !(floor.is_finite() && floor >= 0.0) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse::new("invalid_request", "min_relevance must be a non-negative number")),
+            ));
+        }
+    }
+
+    let order_by = match payload.order_by.as_deref() {
+        None => ContextOrder::default(),
+        Some(o) => ContextOrder::parse(o).ok_or_else(|| (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new(
+                "invalid_order_by",
+                format!("Unknown order_by '{}' (expected \"relevance\", \"recency\" or \"mixed\")", o),
+            )),
+        ))?,
+    };
+
+    let context_window = payload.context_window.unwrap_or(0);
+    if context_window > MAX_CONTEXT_WINDOW {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new("invalid_request", format!("context_window must be at most {}", MAX_CONTEXT_WINDOW))),
+        ));
+    }
+
+    // Identical requests within QUERY_CACHE_TTL_SECS of each other, with no ingest in
+    // between, skip embedding and retrieval
+    let cache_key = cache.is_enabled().then(|| QueryCache::key(&payload));
+    let cache_generation = cache.generation();
+    if let Some(key) = &cache_key {
+        let cached = cache.get(key);
+        telemetry::record_query_cache(cached.is_some());
+        if let Some(mut response) = cached {
+            tracing::debug!(query = %payload.query, "Serving LLM context from the query cache");
+            response.cached = true;
+            response.query_duration_ms = start.elapsed().as_millis();
+            telemetry::record_query_results("llm_context", response.retrieval_stats.total_unique_messages);
+            return Ok(Json(response));
+        }
+    }
+
+    tracing::info!(query = %payload.query, top_k, max_tokens, retrieval_mode, "Querying LLM context");
 
     let client = match get_client().await {
         Ok(c) => c,
         Err(e) => {
-            eprintln!("Failed to connect to database: {}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            tracing::error!(error = %e, "Failed to connect to database");
+            return Err(db_connect_failed(e));
         }
     };
 
+    let embedding_model = payload.embedding_model.as_deref();
+    if embedding_model.is_none() {
+        crate::db::vector::warn_on_mixed_embedding_models(&client, &["message_embeddings", "kg_edge_embeddings"]).await;
+    }
+
+    // Steps 1-2: embed and search for the query and each paraphrase in `queries`
+    let texts = query_texts(&payload);
+    let per_query = futures::future::try_join_all(
+        texts.iter().map(|query| retrieve_for_query(cfg, &client, query, &payload, top_k, retrieval_mode, fusion)),
+    )
+    .await?;
+
+    // One evidence set: messages and edges found by several queries appear once
+    let mut evidence = EvidenceAggregate::default();
+    let mut rankings = Vec::with_capacity(per_query.len());
+    let mut kg_edge_count = 0;
+    let mut direct_message_count = 0;
+    let mut kg_edges_for_response: Vec<KGEdgeWithContext> = Vec::new();
+    let mut explanations = HashMap::new();
+    let mut highlights = HashMap::new();
+    let mut hubs_skipped: Vec<String> = Vec::new();
+    for found in per_query {
+        rankings.push(found.evidence.ranked());
+        evidence.absorb(found.evidence);
+        kg_edge_count += found.kg_edge_count;
+        direct_message_count += found.direct_message_count;
+        for edge in found.kg_edges {
+            let seen = kg_edges_for_response.iter_mut().find(|e| {
+                e.source == edge.source && e.relation == edge.relation && e.target == edge.target && e.conversation_id == edge.conversation_id
+            });
+            match seen {
+                Some(seen) if edge.similarity > seen.similarity => seen.similarity = edge.similarity,
+                Some(_) => {}
+                None => kg_edges_for_response.push(edge),
+            }
+        }
+        // Explanations and highlights of the first query that found a message
+        for (id, explanation) in found.explanations {
+            explanations.entry(id).or_insert(explanation);
+        }
+        for (id, ranges) in found.highlights {
+            highlights.entry(id).or_insert(ranges);
+        }
+        for hub in found.hubs_skipped {
+            if !hubs_skipped.contains(&hub) {
+                hubs_skipped.push(hub);
+            }
+        }
+    }
+    if texts.len() > 1 {
+        tracing::debug!(queries = texts.len(), message_count = evidence.len(), fusion = multi_query_fusion.as_str(), "Fused multi-query evidence");
+    }
+
+    // Step 3: Fetch the actual messages, best combined score first, so messages both
+    // strategies (or several queries) found come ahead of those only one did
+    let ranked = fuse_query_rankings(rankings, multi_query_fusion);
+    let evidence_message_vec: Vec<Uuid> = ranked.iter().map(|(id, _)| *id).collect();
+    let relevance: HashMap<Uuid, f32> = ranked.into_iter().collect();
+    let message_sources = evidence.sources();
+    let mut messages = match get_messages_by_ids_chunked(&client, &evidence_message_vec, cfg.message_fetch_chunk_size).await {
+        Ok(msgs) => msgs,
+        Err(e) => {
+            tracing::error!(error = %e, "Error fetching messages");
+            return Err(retrieval_failed("Message fetch", e));
+        }
+    };
+
+    tracing::info!(
+        result_count = messages.len(),
+        kg_edges = kg_edge_count,
+        direct_messages = direct_message_count,
+        retrieval_mode,
+        "Retrieved messages"
+    );
+
+    // Strongest evidence first, so the token budget (and the near-duplicate collapse)
+    // favors it, without the weak matches
+    let below_min_relevance = rank_by_relevance(&mut messages, &relevance, payload.min_relevance);
+    if below_min_relevance > 0 {
+        tracing::debug!(filtered = below_min_relevance, min_relevance = payload.min_relevance, "Dropped weak messages");
+    }
+
+    // Drop chit-chat ("ok", "thanks") before it takes up the token budget
+    let min_content_length = payload.min_content_length.unwrap_or(cfg.min_content_length);
+    let retrieved_count = messages.len();
+    retain_substantive_messages(&mut messages, min_content_length, &evidence);
+    let short_messages_filtered = retrieved_count - messages.len();
+    if short_messages_filtered > 0 {
+        tracing::debug!(filtered = short_messages_filtered, min_content_length, "Dropped short messages");
+    }
+
+    // Paraphrased repeats would spend the token budget on the same content
+    let mut near_duplicates_collapsed = 0;
+    if let Some(threshold) = payload.dedup_threshold {
+        let ids: Vec<Uuid> = messages.iter().map(|m| m.message_id).collect();
+        match get_message_embeddings(&client, &ids).await {
+            Ok(embeddings) => {
+                near_duplicates_collapsed = collapse_near_duplicates(&mut messages, &embeddings, threshold);
+                tracing::debug!(collapsed = near_duplicates_collapsed, threshold, "Collapsed near-duplicate messages");
+            }
+            Err(e) => tracing::warn!(error = %e, "Could not fetch message embeddings; skipping near-duplicate collapse"),
+        }
+    }
+
+    let total_evidence_messages = messages.len();
+
+    let mut messages_by_source = BTreeMap::new();
+    for msg in &messages {
+        if let Some(source) = message_sources.get(&msg.message_id) {
+            *messages_by_source.entry(*source).or_insert(0) += 1;
+        }
+    }
+
+    // Each match is followed by the turns around it, so the token budget covers the
+    // thread of the strongest matches first
+    let mut context_for = HashMap::new();
+    if context_window > 0 {
+        let ids: Vec<Uuid> = messages.iter().map(|m| m.message_id).collect();
+        match get_conversation_neighbors(&client, &ids, context_window).await {
+            Ok(neighbors) => {
+                (messages, context_for) = expand_with_context(messages, neighbors);
+                tracing::debug!(added = context_for.len(), context_window, "Added surrounding turns");
+            }
+            Err(e) => tracing::warn!(error = %e, "Could not fetch surrounding turns; returning the matches only"),
+        }
+    }
+
+    // Step 4: Format messages for LLM context with token management. The budget is
+    // always filled by relevance; `order_by` only reorders what fits.
+    let mut formatted = format_messages_for_llm_simple(
+        messages, &relevance, &message_sources, &explanations, &highlights, &context_for, max_tokens,
+    );
+    order_context_messages(&mut formatted.messages, order_by, cfg.recency_half_life_hours);
+
+    tracing::debug!(
+        message_count = formatted.messages.len(),
+        tokens_estimate = formatted.total_tokens_estimate,
+        context_window_used = formatted.context_window_used,
+        "Formatted messages for LLM"
+    );
+
+    // Step 5: Resolve evidence content for the returned edges in one query
+    if include_kg_edges && payload.include_evidence_content.unwrap_or(false) {
+        let cap = payload.max_evidence_per_edge.unwrap_or(DEFAULT_MAX_EVIDENCE_PER_EDGE);
+        let evidence: Vec<Vec<Uuid>> = kg_edges_for_response
+            .iter()
+            .map(|e| e.evidence_message_ids.clone())
+            .collect();
+        let resolved = resolve_evidence_messages(&client, &evidence, cap)
+            .await
+            .map_err(|e| retrieval_failed("Evidence fetch", e))?;
+        for (edge, messages) in kg_edges_for_response.iter_mut().zip(resolved) {
+            edge.evidence = Some(messages);
+        }
+    }
+
+    let rendered_prompt = payload.render_template.as_deref().map(|template| {
+        let template = if template.is_empty() { DEFAULT_RENDER_TEMPLATE } else { template };
+        render_context(&formatted.messages, template, max_tokens)
+    });
+
+    let response = ContextQueryResponse {
+        formatted_context: formatted,
+        knowledge_graph_edges: if include_kg_edges { kg_edges_for_response } else { Vec::new() },
+        query_duration_ms: start.elapsed().as_millis(),
+        total_evidence_messages,
+        retrieval_stats: RetrievalStats {
+            kg_edge_matches: kg_edge_count,
+            direct_message_matches: direct_message_count,
+            total_unique_messages: total_evidence_messages,
+            retrieval_mode: retrieval_mode.to_string(),
+            fusion: fusion.as_str().to_string(),
+            messages_by_source,
+            short_messages_filtered,
+            below_min_relevance,
+            near_duplicates_collapsed,
+            hubs_skipped,
+            require_both: payload.require_both.unwrap_or(false),
+            queries: texts.len(),
+            context_messages: context_for.len(),
+            order_by: order_by.as_str().to_string(),
+        },
+        rendered_prompt,
+        cached: false,
+    };
+
+    if let Some(key) = cache_key {
+        cache.insert(key, response.clone(), cache_generation);
+    }
+    telemetry::record_query_results("llm_context", response.retrieval_stats.total_unique_messages);
+    Ok(Json(response))
+}
+
+// ============================================================================
+// Helper Functions
+// ============================================================================
+
+/// Most phrasings of a question `/query/llm-context` retrieves for: `query` plus `queries`
+pub const MAX_QUERIES: usize = 8;
+
+/// What the KG and direct searches found for one phrasing of the question
+#[derive(Debug, Default)]
+struct QueryEvidence {
+    evidence: EvidenceAggregate,
+    /// Edges kept after the similarity floor and the keyword relevance check
+    kg_edges: Vec<KGEdgeWithContext>,
+    kg_edge_count: usize,
+    direct_message_count: usize,
+    explanations: HashMap<Uuid, ScoreExplanation>,
+    highlights: HashMap<Uuid, Vec<(usize, usize)>>,
+    hubs_skipped: Vec<String>,
+}
+
+/// `query` followed by the paraphrases in `queries`, without repeats
+fn query_texts(payload: &ContextQueryRequest) -> Vec<&str> {
+    let mut texts = vec![payload.query.as_str()];
+    for query in &payload.queries {
+        if !texts.contains(&query.as_str()) {
+            texts.push(query);
+        }
+    }
+    texts
+}
+
+/// Embed `query` and run the KG and direct message searches of `retrieval_mode` for it
+async fn retrieve_for_query(
+    cfg: &Config,
+    client: &Client,
+    query: &str,
+    payload: &ContextQueryRequest,
+    top_k: usize,
+    retrieval_mode: &str,
+    fusion: FusionStrategy,
+) -> Result<QueryEvidence, ContextError> {
+    let mut found = QueryEvidence::default();
+    let embedding_model = payload.embedding_model.as_deref();
+
     // Step 1: Generate embedding for the query using llama.cpp server
     use crate::etl::embed;
-    let query_embedding = match embed::embed_text(&payload.query).await {
+    let query_embedding = match embed::embed_text(cfg, query).await {
         Ok(emb) => emb,
+        Err(e) if embed::is_overloaded(&e) => return Err(embedding_overloaded(e)),
         Err(e) => {
-            eprintln!("Error generating query embedding: {}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            tracing::error!(error = %e, "Error generating query embedding");
+            return Err(embedding_failed(e));
         }
     };
 
-    println!("Generated query embedding with {} dimensions", query_embedding.len());
+    tracing::debug!(dim = query_embedding.len(), "Generated query embedding");
 
     // Step 2A: Search KG edges with graph traversal (if enabled)
-    let mut kg_edge_count = 0;
-    let mut evidence_message_ids = HashSet::new();
-    let mut kg_edges_for_response = Vec::new();
-
     if retrieval_mode == "hybrid" || retrieval_mode == "kg_only" {
         // Use hybrid KG retrieval with graph traversal
         let enable_traversal = true; // Enable multi-hop traversal
-        let kg_edges = match hybrid_kg_retrieval(&client, &query_embedding, top_k as i64, enable_traversal).await {
-            Ok(edges) => edges,
+        let limits = TraversalLimits {
+            max_fanout_per_node: cfg.max_fanout_per_node,
+            hub_degree_threshold: cfg.hub_degree_threshold,
+        };
+        let kg_edges = match hybrid_kg_retrieval(client, &query_embedding, top_k as i64, enable_traversal, embedding_model, limits).await {
+            Ok(retrieval) => {
+                found.hubs_skipped = retrieval.hubs_skipped;
+                retrieval.edges
+            }
             Err(e) => {
-                eprintln!("Error in hybrid KG retrieval: {}", e);
+                tracing::error!(error = %e, "Error in hybrid KG retrieval");
                 if retrieval_mode == "kg_only" {
-                    return Err(StatusCode::INTERNAL_SERVER_ERROR);
+                    return Err(retrieval_failed("KG retrieval", e));
                 }
                 Vec::new() // Continue with direct search in hybrid mode
             }
         };
 
-        kg_edge_count = kg_edges.len();
-        println!("Found {} edges via KG search + graph traversal", kg_edge_count);
+        let retrieved = kg_edges.len();
+        let kg_edges = filter_edges_by_similarity(kg_edges, payload.kg_min_similarity);
+        found.kg_edge_count = kg_edges.len();
+        tracing::debug!(
+            result_count = found.kg_edge_count,
+            below_threshold = retrieved - found.kg_edge_count,
+            "Found edges via KG search + graph traversal"
+        );
 
         // Extract evidence_message_ids from matched edges with relevance filtering
         for edge in kg_edges {
-            println!("  KG Edge: {} {} {}", 
-                edge.source, edge.relation, edge.target);
+            tracing::trace!(source = %edge.source, relation = %edge.relation, target = %edge.target, "KG edge");
             
             // Check if edge is relevant to query keywords
             let edge_text = format!("{} {} {}", edge.source, edge.relation, edge.target).to_lowercase();
-            let query_lower = payload.query.to_lowercase();
+            let query_lower = query.to_lowercase();
             
             // Simple relevance check: edge contains at least one query word (>3 chars)
             let query_words: Vec<&str> = query_lower.split_whitespace()
@@ -115,35 +556,41 @@ pub async fn query_llm_context(
             
             if is_relevant || retrieval_mode == "kg_only" {
                 for msg_id in &edge.evidence_message_ids {
-                    evidence_message_ids.insert(*msg_id);
+                    found.evidence.add(*msg_id, RetrievalSource::KgEdge, edge.similarity.unwrap_or(0.0));
                 }
-                kg_edges_for_response.push(edge);
+                found.kg_edges.push(edge);
             } else {
-                println!("    ⚠️  Filtered out (not relevant to query)");
+                tracing::trace!(source = %edge.source, relation = %edge.relation, target = %edge.target, "KG edge filtered out (not relevant to query)");
             }
         }
 
-        println!("Collected {} unique message IDs from KG (with traversal)", evidence_message_ids.len());
+        tracing::debug!(message_count = found.evidence.len(), "Collected unique message IDs from KG (with traversal)");
     }
 
     // Step 2B: HYBRID/DIRECT - Search messages with keyword + embedding hybrid
-    let mut direct_message_count = 0;
     if retrieval_mode == "hybrid" || retrieval_mode == "direct_only" {
-        println!("Using hybrid keyword + embedding search for direct messages");
-        
-        let similar_messages = match hybrid_search_messages(&client, &payload.query, &query_embedding, top_k as i64).await {
-            Ok(msgs) => msgs,
+        let extras = HybridSearchExtras {
+            explain: payload.explain.unwrap_or(false),
+            highlight: payload.highlight.unwrap_or(false),
+            require_both: payload.require_both.unwrap_or(false),
+        };
+        let similar_messages = match hybrid_search_messages_with_explanations(client, query, &query_embedding, top_k as i64, fusion, embedding_model, extras).await {
+            Ok(results) => {
+                found.explanations = results.explanations;
+                found.highlights = results.highlights;
+                results.messages
+            }
             Err(e) => {
-                eprintln!("Error in hybrid message search: {}", e);
+                tracing::error!(error = %e, "Error in hybrid message search");
                 if retrieval_mode == "direct_only" {
-                    return Err(StatusCode::INTERNAL_SERVER_ERROR);
+                    return Err(retrieval_failed("Message search", e));
                 }
                 Vec::new() // Continue with KG results in hybrid mode
             }
         };
 
-        direct_message_count = similar_messages.len();
-        println!("Found {} messages via hybrid search (keyword + embedding)", direct_message_count);
+        found.direct_message_count = similar_messages.len();
+        tracing::debug!(result_count = found.direct_message_count, "Found messages via hybrid search (keyword + embedding)");
 
         // Add directly matched messages to the evidence set
         for msg_with_rel in &similar_messages {
@@ -152,58 +599,296 @@ pub async fn query_llm_context(
             } else {
                 &msg_with_rel.content
             };
-            println!("  Match: {}... (score: {:.3})", 
-                preview, msg_with_rel.relevance_score);
-            evidence_message_ids.insert(msg_with_rel.message_id);
+            tracing::trace!(preview, score = msg_with_rel.relevance_score, "Direct message match");
+            found.evidence.add(msg_with_rel.message_id, msg_with_rel.source, msg_with_rel.relevance_score);
         }
 
-        println!("Total unique message IDs after hybrid search: {}", evidence_message_ids.len());
+        tracing::debug!(message_count = found.evidence.len(), "Total unique message IDs after hybrid search");
     }
 
-    // Step 3: Fetch the actual messages using the combined evidence_message_ids
-    let evidence_message_vec: Vec<Uuid> = evidence_message_ids.into_iter().collect();
-    let messages = match get_messages_by_ids_ordered(&client, &evidence_message_vec).await {
-        Ok(msgs) => msgs,
-        Err(e) => {
-            eprintln!("Error fetching messages: {}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    Ok(found)
+}
+
+/// Part of a strategy's score every message it found gets, however low it ranked there
+pub const STRATEGY_BASE_SCORE: f32 = 0.5;
+
+/// Messages found by the KG and direct strategies, with the best score each strategy
+/// gave them, so a message both found can be ranked above one only a single strategy did
+#[derive(Debug, Default)]
+pub struct EvidenceAggregate {
+    /// Message -> (sources, best KG edge similarity, best direct search score)
+    found: HashMap<Uuid, (RetrievalSource, Option<f32>, Option<f32>)>,
+}
+
+impl EvidenceAggregate {
+    /// Record that `source` found `message_id` with `score`. `KgEdge` scores are the
+    /// edge's similarity; any other source is the direct search's fused score.
+    pub fn add(&mut self, message_id: Uuid, source: RetrievalSource, score: f32) {
+        let entry = self.found.entry(message_id).or_insert((source, None, None));
+        entry.0 = entry.0.merge(source);
+        let slot = if source == RetrievalSource::KgEdge { &mut entry.1 } else { &mut entry.2 };
+        *slot = Some(slot.map_or(score, |best| best.max(score)));
+    }
+
+    pub fn len(&self) -> usize {
+        self.found.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.found.is_empty()
+    }
+
+    /// Add what another query found: sources are merged and each strategy keeps its
+    /// best score. Scores from different queries aren't comparable, so rank each
+    /// query's aggregate first and fuse with `fuse_query_rankings`.
+    pub fn absorb(&mut self, other: EvidenceAggregate) {
+        for (id, (source, kg, direct)) in other.found {
+            let entry = self.found.entry(id).or_insert((source, None, None));
+            entry.0 = entry.0.merge(source);
+            for (slot, score) in [(&mut entry.1, kg), (&mut entry.2, direct)] {
+                if let Some(score) = score {
+                    *slot = Some(slot.map_or(score, |best| best.max(score)));
+                }
+            }
         }
-    };
+    }
 
-    println!("Retrieved {} messages (KG: {}, Direct: {}, Mode: {})", 
-        messages.len(), kg_edge_count, direct_message_count, retrieval_mode);
+    /// Whether a KG edge cites `message_id` as evidence
+    pub fn found_by_kg(&self, message_id: &Uuid) -> bool {
+        self.found.get(message_id).is_some_and(|(_, kg, _)| kg.is_some())
+    }
 
-    let total_evidence_messages = messages.len();
+    /// Which strategies found each message
+    pub fn sources(&self) -> HashMap<Uuid, RetrievalSource> {
+        self.found.iter().map(|(id, (source, _, _))| (*id, *source)).collect()
+    }
 
-    // Step 4: Format messages for LLM context with token management
-    let formatted = format_messages_for_llm_simple(messages, max_tokens);
+    /// Messages with their combined score, best first. Each strategy's scores are scaled
+    /// by its best score, since edge similarities and fused search scores aren't on the
+    /// same scale, and a strategy contributes `STRATEGY_BASE_SCORE` plus the rest in
+    /// proportion to that scaled score. Scores from both strategies are summed, so a
+    /// message found by both (up to 2.0) outranks any found by one (up to 1.0).
+    pub fn ranked(&self) -> Vec<(Uuid, f32)> {
+        let (best_kg, best_direct) = self.found.values().fold((0.0f32, 0.0f32), |(k, d), (_, kg, direct)| {
+            (kg.map_or(k, |s| k.max(s)), direct.map_or(d, |s| d.max(s)))
+        });
+        let contribution = |score: Option<f32>, best: f32| {
+            score.map_or(0.0, |s| {
+                let scaled = if best > 0.0 { (s / best).clamp(0.0, 1.0) } else { 1.0 };
+                STRATEGY_BASE_SCORE + (1.0 - STRATEGY_BASE_SCORE) * scaled
+            })
+        };
 
-    println!("Formatted {} messages for LLM (estimated {} tokens, {:.1}% of context window)",
-        formatted.messages.len(),
-        formatted.total_tokens_estimate,
-        formatted.context_window_used);
+        let mut ranked: Vec<(Uuid, f32)> = self
+            .found
+            .iter()
+            .map(|(id, (_, kg, direct))| (*id, contribution(*kg, best_kg) + contribution(*direct, best_direct)))
+            .collect();
+        // Ties keep a stable order by id
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        ranked
+    }
+}
 
-    let response = ContextQueryResponse {
-        formatted_context: formatted,
-        knowledge_graph_edges: if include_kg_edges { kg_edges_for_response } else { Vec::new() },
-        query_duration_ms: start.elapsed().as_millis(),
-        total_evidence_messages,
-        retrieval_stats: RetrievalStats {
-            kg_edge_matches: kg_edge_count,
-            direct_message_matches: direct_message_count,
-            total_unique_messages: total_evidence_messages,
-            retrieval_mode: retrieval_mode.to_string(),
-        },
+/// Sort `messages` by their combined score from `EvidenceAggregate::ranked`, best first
+/// (ties keep their order), and drop those scoring below `min_relevance`. A message
+/// without a score counts as 0. Returns how many were dropped.
+pub fn rank_by_relevance(messages: &mut Vec<Message>, relevance: &HashMap<Uuid, f32>, min_relevance: Option<f32>) -> usize {
+    let score = |msg: &Message| relevance.get(&msg.message_id).copied().unwrap_or(0.0);
+    let before = messages.len();
+    if let Some(floor) = min_relevance {
+        messages.retain(|msg| score(msg) >= floor);
+    }
+    messages.sort_by(|a, b| score(b).total_cmp(&score(a)));
+    before - messages.len()
+}
+
+/// Largest `context_window` `/query/llm-context` accepts
+pub const MAX_CONTEXT_WINDOW: usize = 10;
+
+/// Place the turns around each of the ranked `messages` (from `get_conversation_neighbors`)
+/// next to it in conversation order: earlier turns before it, later ones after. A turn
+/// that is itself a match, or already placed next to a stronger one, is not repeated.
+/// Returns the messages and, for each added turn, the match it was added for.
+pub fn expand_with_context(
+    messages: Vec<Message>,
+    mut neighbors: HashMap<Uuid, Vec<(i64, Message)>>,
+) -> (Vec<Message>, HashMap<Uuid, Uuid>) {
+    let mut placed: HashSet<Uuid> = messages.iter().map(|m| m.message_id).collect();
+    let mut context_for = HashMap::new();
+    let mut expanded = Vec::with_capacity(messages.len());
+
+    for msg in messages {
+        let anchor = msg.message_id;
+        let mut after = Vec::new();
+        for (offset, neighbor) in neighbors.remove(&anchor).unwrap_or_default() {
+            if !placed.insert(neighbor.message_id) {
+                continue;
+            }
+            context_for.insert(neighbor.message_id, anchor);
+            if offset < 0 {
+                expanded.push(neighbor);
+            } else {
+                after.push(neighbor);
+            }
+        }
+        expanded.push(msg);
+        expanded.extend(after);
+    }
+    (expanded, context_for)
+}
+
+/// Order of the messages `/query/llm-context` returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContextOrder {
+    /// Strongest evidence first
+    #[default]
+    Relevance,
+    /// Newest first
+    Recency,
+    /// By relevance halved for every `RECENCY_HALF_LIFE_HOURS` a message is older
+    /// than the newest one
+    Mixed,
+}
+
+impl ContextOrder {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "relevance" => Some(ContextOrder::Relevance),
+            "recency" => Some(ContextOrder::Recency),
+            "mixed" => Some(ContextOrder::Mixed),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContextOrder::Relevance => "relevance",
+            ContextOrder::Recency => "recency",
+            ContextOrder::Mixed => "mixed",
+        }
+    }
+}
+
+/// Reorder the messages that fit the token budget (which arrive relevance first).
+/// Surrounding turns (`context_for`) stay with their match in conversation order, and
+/// each group is placed by its match. Messages without a timestamp count as oldest.
+pub fn order_context_messages(messages: &mut [LLMContextMessage], order: ContextOrder, half_life_hours: f64) {
+    let matches: HashMap<Uuid, (f32, Option<NaiveDateTime>)> = messages
+        .iter()
+        .filter(|m| m.context_for.is_none())
+        .map(|m| (m.message_id, (m.relevance_score, m.created_at)))
+        .collect();
+    // A turn whose match didn't fit is placed by its own score and timestamp
+    let group = |m: &LLMContextMessage| {
+        matches.get(&m.context_for.unwrap_or(m.message_id)).copied().unwrap_or((m.relevance_score, m.created_at))
     };
 
-    Ok(Json(response))
+    match order {
+        ContextOrder::Relevance => {}
+        ContextOrder::Recency => messages.sort_by_key(|m| std::cmp::Reverse(group(m).1)),
+        ContextOrder::Mixed => {
+            let newest = matches.values().filter_map(|(_, created_at)| *created_at).max();
+            let decayed = |m: &LLMContextMessage| {
+                let (score, created_at) = group(m);
+                match (newest, created_at) {
+                    (Some(newest), Some(created_at)) => {
+                        let age_hours = (newest - created_at).num_milliseconds() as f64 / 3_600_000.0;
+                        score as f64 * 0.5f64.powf(age_hours / half_life_hours)
+                    }
+                    _ => 0.0,
+                }
+            };
+            messages.sort_by(|a, b| decayed(b).total_cmp(&decayed(a)));
+        }
+    }
 }
 
-// ============================================================================
-// Helper Functions
-// ============================================================================
+/// How the per-query rankings of a multi-query request are merged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MultiQueryFusion {
+    /// A message's best combined score over the queries
+    #[default]
+    Max,
+    /// Reciprocal Rank Fusion over the per-query rankings (k = `RRF_K`)
+    Rrf,
+}
+
+impl MultiQueryFusion {
+    /// Parse the `multi_query_fusion` request parameter ("max" or "rrf")
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "max" => Some(MultiQueryFusion::Max),
+            "rrf" => Some(MultiQueryFusion::Rrf),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MultiQueryFusion::Max => "max",
+            MultiQueryFusion::Rrf => "rrf",
+        }
+    }
+}
+
+/// Merge the `EvidenceAggregate::ranked` lists of several queries into one ranking,
+/// each message once, best first (ties by id). A single ranking is returned as is.
+pub fn fuse_query_rankings(mut rankings: Vec<Vec<(Uuid, f32)>>, fusion: MultiQueryFusion) -> Vec<(Uuid, f32)> {
+    if rankings.len() == 1 {
+        return rankings.pop().unwrap_or_default();
+    }
+    let mut fused: HashMap<Uuid, f32> = HashMap::new();
+    for ranking in &rankings {
+        for (rank, (id, score)) in ranking.iter().enumerate() {
+            match fusion {
+                MultiQueryFusion::Max => {
+                    let best = fused.entry(*id).or_insert(*score);
+                    *best = best.max(*score);
+                }
+                MultiQueryFusion::Rrf => *fused.entry(*id).or_insert(0.0) += 1.0 / (RRF_K + (rank + 1) as f32),
+            }
+        }
+    }
+    let mut ranked: Vec<(Uuid, f32)> = fused.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    ranked
+}
+
+/// Drop messages whose text, without its role prefix, is shorter than `min_content_length`
+/// characters. Messages cited by a KG edge are kept whatever their length, since a short
+/// reply ("Yes, use Postgres 16") may be the evidence the edge was extracted from.
+pub fn retain_substantive_messages(messages: &mut Vec<Message>, min_content_length: usize, evidence: &EvidenceAggregate) {
+    if min_content_length == 0 {
+        return;
+    }
+    messages.retain(|msg| {
+        evidence.found_by_kg(&msg.message_id)
+            || parse_message_role(&msg.content).1.trim().chars().count() >= min_content_length
+    });
+}
+
+/// Drop each message whose embedding has a cosine similarity above `threshold` with a
+/// message before it that was kept, so with `messages` in rank order the better-scored
+/// copy survives. Messages without a stored embedding are kept. Returns how many were dropped.
+pub fn collapse_near_duplicates(messages: &mut Vec<Message>, embeddings: &HashMap<Uuid, Vec<f32>>, threshold: f32) -> usize {
+    let before = messages.len();
+    let mut kept: Vec<&[f32]> = Vec::new();
+    messages.retain(|msg| {
+        let Some(embedding) = embeddings.get(&msg.message_id) else {
+            return true;
+        };
+        if kept.iter().any(|other| cosine_similarity(embedding, other) > threshold) {
+            return false;
+        }
+        kept.push(embedding);
+        true
+    });
+    before - messages.len()
+}
 
 /// Format messages with actual relevance scores from embedding similarity
+#[allow(dead_code)]
 fn format_messages_with_scores(
     messages: Vec<MessageWithRelevance>,
     max_tokens: usize,
@@ -220,7 +905,7 @@ fn format_messages_with_scores(
 
         // Stop if we exceed token budget
         if total_tokens + estimated_tokens > max_tokens {
-            println!("Reached token limit, stopping at {} messages", llm_messages.len());
+            tracing::debug!(message_count = llm_messages.len(), max_tokens, "Reached token limit");
             break;
         }
 
@@ -234,6 +919,11 @@ fn format_messages_with_scores(
             content,
             message_id: msg.message_id,
             relevance_score: msg.relevance_score,
+            source: msg.source,
+            explanation: None,
+            highlights: None,
+            context_for: None,
+            created_at: None,
         });
 
         total_tokens += estimated_tokens;
@@ -265,7 +955,7 @@ fn format_messages_for_llm(
 
         // Stop if we exceed token budget
         if total_tokens + estimated_tokens > max_tokens {
-            println!("Reached token limit at message {} of {}", idx + 1, messages.len());
+            tracing::debug!(message = idx + 1, total_messages = messages.len(), max_tokens, "Reached token limit");
             break;
         }
 
@@ -282,6 +972,11 @@ fn format_messages_for_llm(
             content,
             message_id: msg.message_id,
             relevance_score,
+            source: RetrievalSource::KgEdge,
+            explanation: None,
+            highlights: None,
+            context_for: None,
+            created_at: msg.created_at,
         });
 
         total_tokens += estimated_tokens;
@@ -295,10 +990,16 @@ fn format_messages_for_llm(
     }
 }
 
-/// Format messages for LLM (simple version without relevance scores)
-/// Used when messages come from evidence_message_ids (already relevant by definition)
+/// Format messages for LLM, in the given order (best first, from `rank_by_relevance`),
+/// with their combined relevance from `EvidenceAggregate::ranked`. Surrounding turns
+/// (`context_for`) are marked as context and score 0.
 fn format_messages_for_llm_simple(
     messages: Vec<Message>,
+    relevance: &HashMap<Uuid, f32>,
+    sources: &HashMap<Uuid, RetrievalSource>,
+    explanations: &HashMap<Uuid, ScoreExplanation>,
+    highlights: &HashMap<Uuid, Vec<(usize, usize)>>,
+    context_for: &HashMap<Uuid, Uuid>,
     max_tokens: usize,
 ) -> FormattedLLMContext {
     let mut llm_messages = Vec::new();
@@ -313,7 +1014,7 @@ fn format_messages_for_llm_simple(
 
         // Stop if we exceed token budget
         if total_tokens + estimated_tokens > max_tokens {
-            println!("Reached token limit, stopping at {} messages", llm_messages.len());
+            tracing::debug!(message_count = llm_messages.len(), max_tokens, "Reached token limit");
             break;
         }
 
@@ -321,12 +1022,23 @@ fn format_messages_for_llm_simple(
 
         // Parse role from message content if possible
         let (role, content) = parse_message_role(&msg.content);
+        let highlights = highlights.get(&msg.message_id).map(|ranges| shift_highlights(ranges, &msg.content, &content));
+        let context_for = context_for.get(&msg.message_id).copied();
+        let source = match context_for {
+            Some(_) => RetrievalSource::Context,
+            None => sources.get(&msg.message_id).copied().unwrap_or(RetrievalSource::KgEdge),
+        };
 
         llm_messages.push(LLMContextMessage {
             role,
             content,
             message_id: msg.message_id,
-            relevance_score: 1.0, // All evidence messages are equally relevant
+            relevance_score: relevance.get(&msg.message_id).copied().unwrap_or(0.0),
+            source,
+            explanation: explanations.get(&msg.message_id).cloned(),
+            highlights,
+            context_for,
+            created_at: msg.created_at,
         });
 
         total_tokens += estimated_tokens;
@@ -340,20 +1052,99 @@ fn format_messages_for_llm_simple(
     }
 }
 
-/// Parse message role from content (e.g., "user: hello" -> ("user", "hello"))
-fn parse_message_role(content: &str) -> (String, String) {
-    // Check if content starts with a role prefix like "user:" or "assistant:"
-    let parts: Vec<&str> = content.splitn(2, ':').collect();
+/// Template used when `render_template` is given as an empty string
+pub const DEFAULT_RENDER_TEMPLATE: &str = "{role}: {content}\n\n";
 
-    if parts.len() == 2 {
-        let potential_role = parts[0].trim().to_lowercase();
-        if matches!(potential_role.as_str(), "user" | "assistant" | "system") {
-            return (potential_role, parts[1].trim().to_string());
+/// Render `messages` into one prompt string by filling `template` once per message.
+/// `{role}`, `{content}` and `{relevance_score}` (three decimals) are replaced in a
+/// single pass, so placeholders inside message content are left alone, as are unknown
+/// placeholders. Messages are added in order until the next would push the estimated
+/// token count past `max_tokens`.
+pub fn render_context(messages: &[LLMContextMessage], template: &str, max_tokens: usize) -> String {
+    let tokens_per_char = 0.25; // rough estimate: 1 token ≈ 4 chars
+    let mut rendered = String::new();
+    let mut total_tokens = 0;
+
+    for msg in messages {
+        let mut entry = String::with_capacity(template.len() + msg.content.len());
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            entry.push_str(&rest[..start]);
+            let tail = &rest[start..];
+            let placeholder = tail.find('}').map_or("", |end| &tail[..=end]);
+            let value = match placeholder {
+                "{role}" => Some(msg.role.clone()),
+                "{content}" => Some(msg.content.clone()),
+                "{relevance_score}" => Some(format!("{:.3}", msg.relevance_score)),
+                _ => None,
+            };
+            match value {
+                Some(value) => {
+                    entry.push_str(&value);
+                    rest = &tail[placeholder.len()..];
+                }
+                None => {
+                    entry.push('{');
+                    rest = &tail[1..];
+                }
+            }
         }
+        entry.push_str(rest);
+
+        let estimated_tokens = (entry.len() as f32 * tokens_per_char) as usize;
+        if total_tokens + estimated_tokens > max_tokens {
+            tracing::debug!(max_tokens, "Reached token limit while rendering prompt");
+            break;
+        }
+        rendered.push_str(&entry);
+        total_tokens += estimated_tokens;
     }
 
-    // Default to "user" if no role prefix found
-    ("user".to_string(), content.to_string())
+    rendered
+}
+
+/// Re-base highlight ranges computed on a message's stored content onto `content`,
+/// the part returned after `parse_message_role` strips a role prefix. Ranges in the
+/// stripped prefix are dropped and ranges are clipped to `content`.
+pub fn shift_highlights(ranges: &[(usize, usize)], original: &str, content: &str) -> Vec<(usize, usize)> {
+    let shift = original.find(content).unwrap_or(0);
+    ranges
+        .iter()
+        .map(|&(start, end)| (start.max(shift) - shift, end.min(shift + content.len()).saturating_sub(shift)))
+        .filter(|(start, end)| start < end)
+        .collect()
+}
+
+/// Parse message role from content (e.g., "user: hello" -> ("user", "hello")) using
+/// the configured `ROLE_PREFIXES`, `ROLE_SEPARATORS` and `DEFAULT_ROLE`
+fn parse_message_role(content: &str) -> (String, String) {
+    let (role, rest) = Config::global().role_scheme.parse(content);
+    (role, rest.to_string())
+}
+
+// ============================================================================
+// Conversation Handler
+// ============================================================================
+
+/// A conversation and its metadata, including the `pipeline_metadata` recorded
+/// when its knowledge graph was ingested
+pub async fn get_conversation_metadata(
+    Path(conversation_id): Path<String>,
+) -> Result<Json<Conversation>, ContextError> {
+    let conversation_id = Uuid::parse_str(&conversation_id).map_err(|_| (
+        StatusCode::BAD_REQUEST,
+        Json(ErrorResponse::new("invalid_conversation_id", format!("Not a valid conversation id: {}", conversation_id))),
+    ))?;
+
+    let client = get_client().await.map_err(db_connect_failed)?;
+    match get_conversation(&client, conversation_id).await {
+        Ok(Some(conversation)) => Ok(Json(conversation)),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse::new("conversation_not_found", format!("No conversation with id {}", conversation_id))),
+        )),
+        Err(e) => Err(retrieval_failed("Conversation fetch", e)),
+    }
 }
 
 // ============================================================================
@@ -374,21 +1165,23 @@ pub struct MessageQueryResponse {
 /// Get full messages by their IDs
 pub async fn query_messages_by_ids(
     Json(payload): Json<MessageQueryRequest>,
-) -> Result<Json<MessageQueryResponse>, StatusCode> {
-    println!("Querying {} message IDs", payload.message_ids.len());
+) -> Result<Json<MessageQueryResponse>, ContextError> {
+    let _timer = telemetry::time_query("messages");
+    tracing::debug!(id_count = payload.message_ids.len(), "Querying messages by ID");
 
     let client = match get_client().await {
         Ok(c) => c,
         Err(e) => {
-            eprintln!("Failed to connect to database: {}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            tracing::error!(error = %e, "Failed to connect to database");
+            return Err(db_connect_failed(e));
         }
     };
 
-    match get_messages_by_ids_ordered(&client, &payload.message_ids).await {
+    match get_messages_by_ids_chunked(&client, &payload.message_ids, Config::global().message_fetch_chunk_size).await {
         Ok(messages) => {
             let total_found = messages.len();
-            println!("Found {} messages", total_found);
+            tracing::debug!(result_count = total_found, "Found messages");
+            telemetry::record_query_results("messages", total_found);
 
             Ok(Json(MessageQueryResponse {
                 messages,
@@ -396,9 +1189,85 @@ pub async fn query_messages_by_ids(
             }))
         }
         Err(e) => {
-            eprintln!("Error querying messages: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            tracing::error!(error = %e, "Error querying messages");
+            Err(retrieval_failed("Message fetch", e))
         }
     }
 }
 
+
+// ============================================================================
+// Single-Backend Message Search Handler
+// ============================================================================
+
+#[derive(Debug, Default, Deserialize)]
+pub struct MessageSearchRequest {
+    pub query: String,
+    pub mode: Option<String>, // "keyword", "embedding" or "hybrid" (default)
+    pub top_k: Option<usize>,
+    pub embedding_model: Option<String>, // only compare against vectors from this model
+}
+
+#[derive(Debug, Serialize)]
+pub struct MessageSearchResponse {
+    pub mode: String,
+    /// Search terms after stop-word removal and synonym expansion (keyword mode only)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    pub results: Vec<MessageWithRelevance>,
+    pub count: usize,
+    pub query_duration_ms: u128,
+}
+
+/// Run one retrieval backend on its own and return its raw scored results, to compare
+/// what keyword and embedding search each find before hybrid fusion
+pub async fn search_messages(
+    Json(payload): Json<MessageSearchRequest>,
+) -> Result<Json<MessageSearchResponse>, ContextError> {
+    let start = std::time::Instant::now();
+    let _timer = telemetry::time_query("message_search");
+    require_query(&payload.query)?;
+
+    let mode = match payload.mode.as_deref() {
+        None => MessageSearchMode::default(),
+        Some(m) => MessageSearchMode::parse(m).ok_or_else(|| (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new(
+                "invalid_mode",
+                format!("Unknown search mode '{}' (expected \"keyword\", \"embedding\" or \"hybrid\")", m),
+            )),
+        ))?,
+    };
+    let top_k = payload.top_k.unwrap_or(10);
+    tracing::info!(query = %payload.query, mode = mode.as_str(), top_k, "Searching messages");
+
+    let client = get_client().await.map_err(db_connect_failed)?;
+
+    let query_embedding = if mode.needs_embedding() {
+        use crate::etl::embed;
+        match embed::embed_text(Config::global(), &payload.query).await {
+            Ok(emb) => emb,
+            Err(e) if embed::is_overloaded(&e) => return Err(embedding_overloaded(e)),
+            Err(e) => return Err(embedding_failed(e)),
+        }
+    } else {
+        Vec::new()
+    };
+
+    let results = search_messages_with_mode(&client, mode, &payload.query, &query_embedding, top_k as i64, payload.embedding_model.as_deref())
+        .await
+        .map_err(|e| retrieval_failed("Message search", e))?;
+    telemetry::record_query_results("message_search", results.len());
+
+    let keywords = match mode {
+        MessageSearchMode::Keyword => expand_query_keywords(&query_search_keywords(&payload.query)),
+        _ => Vec::new(),
+    };
+    Ok(Json(MessageSearchResponse {
+        mode: mode.as_str().to_string(),
+        keywords,
+        count: results.len(),
+        results,
+        query_duration_ms: start.elapsed().as_millis(),
+    }))
+}
@@ -1,11 +1,27 @@
 use axum::{
-    extract::Path,
-    http::StatusCode,
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
     Json,
 };
+use metrics_exporter_prometheus::PrometheusHandle;
+use crate::api::callback;
+use crate::api::ingest_handlers::IngestJson;
+use crate::api::jobs::JobStore;
+use crate::api::query_cache;
 use crate::api::models::*;
+use crate::config::Config;
 use crate::db;
+use crate::db::export::ExportFormat;
+use crate::db::models::GraphSchema;
+use crate::etl::similarity::{distance_order, DimensionMismatch, SimilarityMetric};
 use crate::ingest;
+use crate::telemetry;
+use futures::TryStreamExt;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Instant;
 
 /// Health check endpoint
 pub async fn health_check() -> Result<Json<StatusResponse>, (StatusCode, Json<ErrorResponse>)> {
@@ -45,7 +61,7 @@ async fn get_system_status() -> anyhow::Result<StatusResponse> {
     
     // Get knowledge graph nodes count
     let node_count = match client
-        .query_one("SELECT COUNT(*) FROM kg_nodes", &[])
+        .query_one("SELECT COUNT(*) FROM ag_catalog.kg_nodes", &[])
         .await {
             Ok(row) => {
                 let count = row.get::<_, i64>(0);
@@ -60,7 +76,7 @@ async fn get_system_status() -> anyhow::Result<StatusResponse> {
     
     // Get knowledge graph edges count
     let edge_count = match client
-        .query_one("SELECT COUNT(*) FROM kg_edges", &[])
+        .query_one("SELECT COUNT(*) FROM ag_catalog.kg_edges", &[])
         .await {
             Ok(row) => {
                 let count = row.get::<_, i64>(0);
@@ -79,19 +95,140 @@ async fn get_system_status() -> anyhow::Result<StatusResponse> {
         status: "healthy".to_string(),
         database: "connected".to_string(),
         age_extension: "loaded".to_string(),
-        graph_name: "sem_graph".to_string(),
+        graph_name: Config::global().graph_name.clone(),
         total_sessions: session_count,
         total_nodes: node_count,
         total_edges: edge_count,
     })
 }
 
+/// Liveness probe: succeeds as long as the process is serving requests
+pub async fn health_live() -> Json<LivenessResponse> {
+    Json(LivenessResponse {
+        status: "alive".to_string(),
+    })
+}
+
+/// Readiness probe: checks the database, AGE extension and embedding server.
+/// Returns 503 when a required dependency (the database) is down.
+pub async fn health_ready() -> (StatusCode, Json<ReadinessResponse>) {
+    let cfg = Config::global();
+    let mut checks = BTreeMap::new();
+
+    // Database (required) and AGE extension (optional, KG features only)
+    let start = std::time::Instant::now();
+    match db::connect::get_client().await {
+        Ok(client) => {
+            checks.insert("database".to_string(), DependencyStatus {
+                status: "up".to_string(),
+                required: true,
+                latency_ms: start.elapsed().as_millis(),
+                error: None,
+            });
+
+            let start = std::time::Instant::now();
+            let age = client
+                .query_opt("SELECT 1 FROM pg_extension WHERE extname = 'age'", &[])
+                .await;
+            let (status, error) = match age {
+                Ok(Some(_)) => ("up", None),
+                Ok(None) => ("down", Some("age extension not installed".to_string())),
+                Err(e) => ("down", Some(e.to_string())),
+            };
+            checks.insert("age_extension".to_string(), DependencyStatus {
+                status: status.to_string(),
+                required: false,
+                latency_ms: start.elapsed().as_millis(),
+                error,
+            });
+        }
+        Err(e) => {
+            tracing::error!("❌ Readiness: database unavailable: {}", e);
+            checks.insert("database".to_string(), DependencyStatus {
+                status: "down".to_string(),
+                required: true,
+                latency_ms: start.elapsed().as_millis(),
+                error: Some(e.to_string()),
+            });
+            checks.insert("age_extension".to_string(), DependencyStatus {
+                status: "down".to_string(),
+                required: false,
+                latency_ms: 0,
+                error: Some("database unavailable".to_string()),
+            });
+        }
+    }
+
+    // Embedding server (optional: embed_text falls back to placeholder vectors)
+    let embed_status = match cfg.embed_server_url.as_deref() {
+        Some(url) => {
+            use crate::etl::embed::{ping_embed_server, HEALTH_PING_TIMEOUT};
+            let start = std::time::Instant::now();
+            let result = ping_embed_server(url, HEALTH_PING_TIMEOUT).await;
+            DependencyStatus {
+                status: if result.is_ok() { "up" } else { "down" }.to_string(),
+                required: false,
+                latency_ms: start.elapsed().as_millis(),
+                error: result.err().map(|e| e.to_string()),
+            }
+        }
+        None => DependencyStatus {
+            status: "not_configured".to_string(),
+            required: false,
+            latency_ms: 0,
+            error: None,
+        },
+    };
+    checks.insert("embedding_server".to_string(), embed_status);
+
+    let required_down = checks.values().any(|c| c.required && c.status == "down");
+    let optional_down = checks.values().any(|c| !c.required && c.status == "down");
+    let (code, status) = if required_down {
+        (StatusCode::SERVICE_UNAVAILABLE, "not_ready")
+    } else if optional_down {
+        (StatusCode::OK, "degraded")
+    } else {
+        (StatusCode::OK, "ready")
+    };
+
+    (code, Json(ReadinessResponse {
+        status: status.to_string(),
+        checks,
+    }))
+}
+
+/// Prometheus scrape endpoint (text exposition format)
+pub async fn metrics(State(handle): State<PrometheusHandle>) -> impl IntoResponse {
+    handle.run_upkeep();
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], handle.render())
+}
+
+/// Reject a malformed `callback_url` before doing any work
+fn check_callback_url(url: Option<&str>) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    match url.map(callback::validate_callback_url) {
+        Some(Err(message)) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new("invalid_callback_url", message)),
+        )),
+        _ => Ok(()),
+    }
+}
+
 /// Ingest a single session graph
 pub async fn ingest_session(
-    Json(payload): Json<IngestSessionRequest>,
+    IngestJson(payload): IngestJson<IngestSessionRequest>,
 ) -> Result<Json<IngestSessionResponse>, (StatusCode, Json<ErrorResponse>)> {
-    match ingest::ingest_session_graph(&payload.session_id, &payload.graph).await {
-        Ok(stats) => Ok(Json(stats.into())),
+    check_callback_url(payload.callback_url.as_deref())?;
+    let result = ingest::ingest_session_graph(Config::global(), &payload.session_id, &payload.graph, &payload.ingest_options()).await;
+    query_cache::invalidate();
+    match result {
+        Ok(stats) => {
+            let response: IngestSessionResponse = stats.into();
+            if let Some(url) = payload.callback_url {
+                callback::spawn_callback(url, response.clone());
+            }
+            Ok(Json(response))
+        }
         Err(e) => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse::new("ingestion_failed", e.to_string())),
@@ -99,98 +236,260 @@ pub async fn ingest_session(
     }
 }
 
-/// Ingest batch of sessions
+/// Ingest batch of sessions as a background job (poll `GET /ingest/jobs/:job_id`,
+/// or pass `callback_url` to be notified when it ends)
 pub async fn ingest_batch(
-    Json(payload): Json<IngestBatchRequest>,
-) -> Result<Json<IngestBatchResponse>, (StatusCode, Json<ErrorResponse>)> {
-    match ingest::ingest_knowledge_graph_data(&payload.sessions).await {
+    State(jobs): State<JobStore>,
+    IngestJson(payload): IngestJson<IngestBatchRequest>,
+) -> Result<(StatusCode, Json<IngestJobAccepted>), (StatusCode, Json<ErrorResponse>)> {
+    check_callback_url(payload.callback_url.as_deref())?;
+    let cfg = Config::global();
+    let opts = payload.ingest_options();
+    let callback_url = payload.callback_url.clone();
+    let job_id = jobs.spawn_with_callback("batch", payload.sessions.len(), callback_url, move |jobs, job_id| async move {
+        let result = ingest::ingest_knowledge_graph_data_with_progress(cfg, &payload.sessions, &opts, |done| {
+            jobs.set_progress(&job_id, done)
+        })
+        .await;
+        query_cache::invalidate();
+        result
+            .map(|stats| IngestJobResult::Batch(stats.into()))
+            .map_err(|e| format!("batch_ingestion_failed: {}", e))
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(IngestJobAccepted::new(job_id))))
+}
+
+/// How stored embeddings are spread over the LSH buckets, to catch them collapsing
+/// into a few buckets before recall suffers
+pub async fn lsh_distribution() -> Result<Json<LshDistributionResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let query_failed = |e: anyhow::Error| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse::new("query_failed", e.to_string())),
+    );
+    let client = db::connect::get_client().await.map_err(query_failed)?;
+    let counts = db::vector::get_lsh_bucket_counts(&client).await.map_err(query_failed)?;
+    Ok(Json(LshDistributionResponse::new(counts, Config::global().lsh_buckets)))
+}
+
+/// Regenerate edge embeddings that are missing or placeholders (stored while the
+/// embedding server was down), optionally for one session or conversation
+pub async fn reembed(
+    Json(payload): Json<ReembedRequest>,
+) -> Result<Json<ReembedResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let result = ingest::reembed_missing(Config::global(), &payload.scope()).await;
+    query_cache::invalidate();
+    match result {
         Ok(stats) => Ok(Json(stats.into())),
         Err(e) => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse::new("batch_ingestion_failed", e.to_string())),
+            Json(ErrorResponse::new("reembed_failed", e.to_string())),
+        )),
+    }
+}
+
+/// Status, progress and (when finished) result of a background ingest job
+pub async fn get_ingest_job(
+    State(jobs): State<JobStore>,
+    Path(job_id): Path<String>,
+) -> Result<Json<IngestJob>, (StatusCode, Json<ErrorResponse>)> {
+    let job_id = uuid::Uuid::parse_str(&job_id).map_err(|_| (
+        StatusCode::BAD_REQUEST,
+        Json(ErrorResponse::new("invalid_job_id", format!("Not a valid job id: {}", job_id))),
+    ))?;
+
+    match jobs.get(&job_id) {
+        Some(job) => Ok(Json(job)),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse::new("job_not_found", format!("No ingest job with id {}", job_id))),
         )),
     }
 }
 
+/// Embed text and report the vector, its LSH bucket and the provider used (debugging aid)
+pub async fn query_embed(
+    Query(params): Query<EmbedQueryParams>,
+    Json(payload): Json<EmbedRequest>,
+) -> Result<Json<EmbedResponse>, (StatusCode, Json<ErrorResponse>)> {
+    use crate::etl::{embed, lsh::Lsh};
+
+    let _timer = telemetry::time_query("embed");
+    if payload.text.trim().is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new("invalid_request", "text must not be empty")),
+        ));
+    }
+
+    let cfg = Config::global();
+    let start = std::time::Instant::now();
+    let (mut embedding, provider) = embed::embed_text_with_provider(cfg, &payload.text).await.map_err(|e| {
+        if embed::is_overloaded(&e) {
+            return crate::api::context_handlers::embedding_overloaded(e);
+        }
+        (StatusCode::BAD_GATEWAY, Json(ErrorResponse::new("embedding_failed", e.to_string())))
+    })?;
+    let duration_ms = start.elapsed().as_millis() as u64;
+
+    let dim = embedding.len();
+    let lsh_bucket = Lsh::new(dim, cfg.lsh_buckets).hash(&embedding) as i32;
+
+    let truncated = !params.full && dim > EMBED_PREVIEW_LEN;
+    if truncated {
+        embedding.truncate(EMBED_PREVIEW_LEN);
+    }
+
+    Ok(Json(EmbedResponse {
+        embedding,
+        dim,
+        truncated,
+        lsh_bucket,
+        provider: provider.as_str().to_string(),
+        duration_ms,
+    }))
+}
+
 /// Query similar edges
 pub async fn query_similar(
     Json(payload): Json<QuerySimilarRequest>,
 ) -> Result<Json<QuerySimilarResponse>, (StatusCode, Json<ErrorResponse>)> {
-    match query_similar_edges(&payload.query, payload.top_k, payload.threshold).await {
-        Ok(results) => Ok(Json(QuerySimilarResponse {
-            count: results.len(),
-            results,
-        })),
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse::new("query_failed", e.to_string())),
-        )),
+    let _timer = telemetry::time_query("similar");
+    crate::api::context_handlers::require_query(&payload.query)?;
+    let cfg = Config::global();
+    let metric = match payload.metric.as_deref() {
+        Some(m) => SimilarityMetric::parse(m).ok_or_else(|| (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new(
+                "invalid_metric",
+                format!("Unknown metric '{}' (expected cosine, dot or euclidean)", m),
+            )),
+        ))?,
+        None => cfg.similarity_metric,
+    };
+
+    // Resolve evidence content only when asked for, capped per edge
+    let evidence_cap = payload.include_evidence_content.unwrap_or(false).then(|| {
+        payload.max_evidence_per_edge.unwrap_or(db::message_ops::DEFAULT_MAX_EVIDENCE_PER_EDGE)
+    });
+
+    // Graph neighbors of the matches, when asked for
+    let expand_hops = payload.expand_neighbors.unwrap_or(false).then(|| payload.hops.unwrap_or(1).clamp(1, MAX_NEIGHBOR_HOPS));
+
+    // Same bounds as the GET variant, so a huge top_k can't overflow the candidate count
+    let top_k = payload.top_k.clamp(1, MAX_SIMILAR_TOP_K);
+
+    match query_similar_edges(cfg, &payload.query, top_k, payload.threshold, metric, evidence_cap, payload.embedding_model.as_deref(), expand_hops, &payload.filters()).await {
+        Ok((results, degraded)) => {
+            telemetry::record_query_results("similar", results.len());
+            Ok(Json(QuerySimilarResponse {
+                count: results.len(),
+                results,
+                degraded,
+            }))
+        }
+        Err(e) if crate::etl::embed::is_overloaded(&e) => Err(crate::api::context_handlers::embedding_overloaded(e)),
+        Err(e) => {
+            // Stored vectors from another model can't be scored against this query
+            let code = if e.is::<DimensionMismatch>() { "dimension_mismatch" } else { "query_failed" };
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse::new(code, e.to_string())),
+            ))
+        }
+    }
+}
+
+/// Query similar edges from query parameters, for browsers and curl one-liners.
+/// `POST /query/similar` is the canonical variant.
+pub async fn query_similar_get(
+    Query(params): Query<QuerySimilarParams>,
+) -> Result<Json<QuerySimilarResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let query = params.q.map(|q| q.trim().to_string()).unwrap_or_default();
+    if query.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new("invalid_request", "Query parameter 'q' is required and must not be empty")),
+        ));
     }
+
+    query_similar(Json(QuerySimilarRequest {
+        query,
+        top_k: params.top_k.unwrap_or(DEFAULT_SIMILAR_TOP_K).clamp(1, MAX_SIMILAR_TOP_K),
+        threshold: params.threshold,
+        metric: params.metric,
+        include_evidence_content: None,
+        max_evidence_per_edge: None,
+        embedding_model: params.embedding_model,
+        expand_neighbors: None,
+        hops: None,
+        relations: split_list(params.relations.as_deref()),
+        node_types: split_list(params.node_types.as_deref()),
+    }))
+    .await
 }
 
-async fn query_similar_edges(
+/// Comma-separated query parameter values, trimmed, empties dropped
+fn split_list(s: Option<&str>) -> Vec<String> {
+    s.map(|s| s.split(',').map(str::trim).filter(|v| !v.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn query_similar_edges(
+    cfg: &Config,
     query: &str,
     top_k: i64,
     threshold: Option<f32>,
-) -> anyhow::Result<Vec<SimilarityResult>> {
-    use crate::{config::Config, etl::{embed, lsh::Lsh}};
+    metric: SimilarityMetric,
+    evidence_cap: Option<usize>,
+    embedding_model: Option<&str>,
+    expand_hops: Option<usize>,
+    filters: &EdgeFilters,
+) -> anyhow::Result<(Vec<SimilarityResult>, bool)> {
+    use crate::etl::{embed, lsh::LshTables};
     
-    let cfg = Config::from_env();
     let client = db::connect::get_client().await?;
     
     // Generate query embedding
-    let query_vec = embed::embed_text(query).await?;
-    let lsh = Lsh::new(query_vec.len(), cfg.lsh_buckets);
-    let bucket = lsh.hash(&query_vec) as i32;
+    let query_vec = embed::embed_text(cfg, query).await?;
+    let lsh = LshTables::new(query_vec.len(), cfg.lsh_buckets, cfg.lsh_tables);
+    let buckets = lsh.hash_all(&query_vec);
     
-    eprintln!("🔍 Query similarity search (API handler):");
-    eprintln!("   Query text: {}", query);
-    eprintln!("   Query bucket: {}", bucket);
-    eprintln!("   LSH buckets config: {}", cfg.lsh_buckets);
-    
-    // Get all vectors in the same LSH bucket with session info
-    let sql = "SELECT triplet_id, vec, session_id, edge_text FROM embeddings WHERE lsh_bucket = $1";
-    let rows = client.query(sql, &[&bucket]).await?;
+    tracing::debug!(
+        query,
+        buckets = ?buckets,
+        lsh_buckets = cfg.lsh_buckets,
+        lsh_tables = cfg.lsh_tables,
+        metric = metric.as_str(),
+        "🔍 Query similarity search (API handler)"
+    );
     
-    eprintln!("   Found {} embeddings in bucket {}", rows.len(), bucket);
+    // Get all vectors sharing a bucket with the query in any LSH table, with session info
+    if embedding_model.is_none() {
+        db::vector::warn_on_mixed_embedding_models(&client, &["embeddings"]).await;
+    }
     
-    // If no results in the specific bucket, fall back to searching all embeddings
-    let rows = if rows.is_empty() {
-        eprintln!("   ⚠️  Bucket {} is empty, searching ALL embeddings as fallback", bucket);
-        let sql_all = "SELECT triplet_id, vec, session_id, edge_text FROM embeddings LIMIT 1000";
-        let all_rows = client.query(sql_all, &[]).await?;
-        eprintln!("   Found {} total embeddings in database", all_rows.len());
-        
-        // Show bucket distribution
-        let bucket_count_sql = "SELECT lsh_bucket, COUNT(*) FROM embeddings GROUP BY lsh_bucket ORDER BY lsh_bucket";
-        let bucket_rows = client.query(bucket_count_sql, &[]).await?;
-        eprintln!("   Bucket distribution:");
-        for br in bucket_rows.iter().take(10) {
-            let b: i32 = br.get(0);
-            let count: i64 = br.get(1);
-            eprintln!("      Bucket {}: {} embeddings", b, count);
-        }
-        if bucket_rows.len() > 10 {
-            eprintln!("      ... and {} more buckets", bucket_rows.len() - 10);
-        }
-        
-        all_rows
-    } else {
-        rows
-    };
+    // pgvector ranks the bucket candidates; only the top k rows come back
+    let db::vector::NearestEmbeddings { neighbors, degraded } = db::vector::nearest_lsh_embeddings(
+        &client,
+        &query_vec,
+        &buckets,
+        metric,
+        cfg.on_dimension_mismatch,
+        embedding_model,
+        cfg.fallback_scan_limit,
+        // Filters drop ranked candidates, so rank enough that top_k usually survive
+        if filters.is_empty() { top_k.max(0) } else { top_k.max(0).saturating_mul(FILTERED_CANDIDATE_FACTOR) } as usize,
+    ).await?;
+    if degraded {
+        telemetry::record_lsh_fallback("similar");
+    }
     
     let mut results = Vec::new();
-    for row in rows {
-        let triplet_id: i64 = row.get(0);
-        let vec_json: String = row.get(1);
-        let session_id: Option<String> = row.get(2);
-        let edge_text: Option<String> = row.get(3);
-        
-        let stored_vec: Vec<f32> = serde_json::from_str(&vec_json)?;
-        
-        // Calculate similarity
-        let similarity = cosine_similarity(&query_vec, &stored_vec);
-        let distance = 1.0 - similarity;
+    for neighbor in neighbors {
+        let distance = neighbor.distance;
+        let similarity = metric.similarity_from_distance(distance);
         
         // Apply threshold if specified
         if let Some(thresh) = threshold {
@@ -202,8 +501,8 @@ async fn query_similar_edges(
         // Get evidence for this edge
         let evidence_rows = client
             .query(
-                "SELECT evidence_message_id FROM edge_evidence WHERE edge_id = $1",
-                &[&triplet_id],
+                "SELECT evidence_message_id FROM ag_catalog.edge_evidence WHERE edge_id = $1",
+                &[&neighbor.triplet_id],
             )
             .await?;
         
@@ -213,7 +512,7 @@ async fn query_similar_edges(
             .collect();
         
         // Parse edge text (format: "source relation target")
-        let edge = if let Some(text) = edge_text {
+        let edge = if let Some(text) = neighbor.edge_text {
             parse_edge_text(&text)
         } else {
             EdgeResult {
@@ -223,40 +522,111 @@ async fn query_similar_edges(
             }
         };
         
+        if !filters.matches_relation(&edge.relation) {
+            continue;
+        }
+        
         results.push(SimilarityResult {
-            session_id: session_id.unwrap_or_else(|| "unknown".to_string()),
+            session_id: neighbor.session_id.unwrap_or_else(|| "unknown".to_string()),
             edge,
             similarity,
             distance,
             evidence_message_ids,
+            evidence: None,
+            neighbor_of: None,
+        });
+    }
+    
+    if !filters.node_types.is_empty() {
+        let pks: Vec<&str> = results.iter().flat_map(|r| [r.edge.source.as_str(), r.edge.target.as_str()]).collect();
+        let labels = db::graph::get_node_labels(&client, &cfg.graph_name, &pks).await?;
+        results.retain(|r| {
+            filters.matches_node_type(labels.get(&r.edge.source).map(String::as_str))
+                || filters.matches_node_type(labels.get(&r.edge.target).map(String::as_str))
         });
     }
     
-    // Sort by similarity (descending) and take top k
-    results.sort_by(|a, b| b.similarity.partial_cmp(&a.similarity).unwrap());
+    // Sort by distance (ascending, NaN last, ties by session) and take top k
+    results.sort_by(|a, b| distance_order(a.distance, b.distance).then_with(|| a.session_id.cmp(&b.session_id)));
     results.truncate(top_k as usize);
+
+    if let Some(hops) = expand_hops {
+        let neighbors = expand_neighbor_edges(&client, &cfg.graph_name, &results, hops, metric, threshold).await?;
+        tracing::debug!(neighbor_count = neighbors.len(), hops, "Expanded matches to graph neighbors");
+        results.extend(neighbors);
+    }
+    
+    // Evidence ids in edge_evidence are free-form strings; map them like KG edge evidence
+    if let Some(cap) = evidence_cap {
+        let evidence: Vec<Vec<uuid::Uuid>> = results
+            .iter()
+            .map(|r| r.evidence_message_ids.iter().map(|id| db::models::evidence_uuid(id)).collect())
+            .collect();
+        let resolved = db::message_ops::resolve_evidence_messages(&client, &evidence, cap).await?;
+        for (result, messages) in results.iter_mut().zip(resolved) {
+            result.evidence = Some(messages);
+        }
+    }
     
-    eprintln!("   Returning {} results", results.len());
-    if !results.is_empty() {
-        eprintln!("   Top result: similarity={:.4}, edge={} {} {}", 
-            results[0].similarity,
-            results[0].edge.source,
-            results[0].edge.relation,
-            results[0].edge.target
+    if let Some(top) = results.first() {
+        tracing::debug!(
+            result_count = results.len(),
+            top_similarity = top.similarity,
+            top_edge = %format!("{} {} {}", top.edge.source, top.edge.relation, top.edge.target),
+            "Returning similarity results"
         );
+    } else {
+        tracing::debug!(result_count = 0, "Returning similarity results");
     }
     
-    Ok(results)
+    Ok((results, degraded))
 }
 
-fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
-    let dot_product: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
-    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
-    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
-    if norm_a == 0.0 || norm_b == 0.0 {
-        0.0
-    } else {
-        dot_product / (norm_a * norm_b)
+/// Graph edges within `hops` of each match's endpoints that aren't matches themselves.
+/// Each gets the similarity of the best match it was reached from, scaled by
+/// `NEIGHBOR_SIMILARITY_DECAY` per hop, and the matching distance; neighbors falling
+/// below `threshold` are dropped. Sorted by similarity, best first.
+pub async fn expand_neighbor_edges(
+    client: &tokio_postgres::Client,
+    graph: &str,
+    matches: &[SimilarityResult],
+    hops: usize,
+    metric: SimilarityMetric,
+    threshold: Option<f32>,
+) -> anyhow::Result<Vec<SimilarityResult>> {
+    let mut neighbors: Vec<SimilarityResult> = Vec::new();
+    for seed in matches {
+        let pks = [seed.edge.source.as_str(), seed.edge.target.as_str()];
+        for nearby in db::graph::get_edges_near(client, graph, &pks, hops).await? {
+            let edge = EdgeResult { source: nearby.source, relation: nearby.relation, target: nearby.target };
+            let similarity = seed.similarity * NEIGHBOR_SIMILARITY_DECAY.powi(nearby.hops as i32);
+            if matches.iter().any(|m| m.edge == edge) || threshold.is_some_and(|t| similarity < t) {
+                continue;
+            }
+            // Reached from several matches: keep the best-scored path
+            if let Some(existing) = neighbors.iter_mut().find(|n| n.edge == edge) {
+                if similarity <= existing.similarity {
+                    continue;
+                }
+                *existing = neighbor_result(seed, edge, similarity, nearby.hops, metric);
+            } else {
+                neighbors.push(neighbor_result(seed, edge, similarity, nearby.hops, metric));
+            }
+        }
+    }
+    neighbors.sort_by(|a, b| distance_order(a.distance, b.distance));
+    Ok(neighbors)
+}
+
+fn neighbor_result(seed: &SimilarityResult, edge: EdgeResult, similarity: f32, hops: usize, metric: SimilarityMetric) -> SimilarityResult {
+    SimilarityResult {
+        session_id: seed.session_id.clone(),
+        edge,
+        similarity,
+        distance: metric.distance_from_similarity(similarity),
+        evidence_message_ids: Vec::new(),
+        evidence: None,
+        neighbor_of: Some(NeighborOf { edge: seed.edge.clone(), hops }),
     }
 }
 
@@ -278,6 +648,48 @@ fn parse_edge_text(text: &str) -> EdgeResult {
 }
 
 /// Get session graph by ID
+/// Ingested sessions, most recent first, with node, edge and embedding counts
+pub async fn list_sessions(
+    Query(params): Query<SessionListParams>,
+) -> Result<Json<SessionListResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let limit = params.limit.unwrap_or(DEFAULT_SESSION_LIST_LIMIT).clamp(1, MAX_SESSION_LIST_LIMIT);
+    let offset = params.offset.unwrap_or(0).max(0);
+
+    let query_failed = |e: anyhow::Error| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse::new("session_query_failed", e.to_string())),
+    );
+    let client = db::connect::get_client().await.map_err(query_failed)?;
+    let sessions = db::sessions::list_sessions(&client, limit, offset).await.map_err(query_failed)?;
+    let total = db::sessions::count_sessions(&client).await.map_err(query_failed)?;
+
+    Ok(Json(SessionListResponse {
+        count: sessions.len(),
+        sessions,
+        total,
+        limit,
+        offset,
+    }))
+}
+
+/// Stored stats for one ingested session
+pub async fn get_session_stats(
+    Path(session_id): Path<String>,
+) -> Result<Json<db::sessions::SessionSummary>, (StatusCode, Json<ErrorResponse>)> {
+    let query_failed = |e: anyhow::Error| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse::new("session_query_failed", e.to_string())),
+    );
+    let client = db::connect::get_client().await.map_err(query_failed)?;
+    match db::sessions::get_session_summary(&client, &session_id).await.map_err(query_failed)? {
+        Some(summary) => Ok(Json(summary)),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse::new("session_not_found", format!("No ingested session '{}'", session_id))),
+        )),
+    }
+}
+
 pub async fn get_session(
     Path(session_id): Path<String>,
 ) -> Result<Json<SessionGraphResponse>, (StatusCode, Json<ErrorResponse>)> {
@@ -299,11 +711,225 @@ async fn retrieve_session_graph(_session_id: &str) -> anyhow::Result<crate::etl:
     })
 }
 
+/// Reject a pk from the request path or query that contains `$$`. Pks are bound as
+/// Cypher parameters, but a `$$` pk is still refused so one can never end the
+/// dollar-quoted cypher() argument should it reach the SQL text.
+fn check_pk(pk: &str) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    if pk.contains("$$") {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new("invalid_pk", "pk must not contain '$$'")),
+        ));
+    }
+    Ok(())
+}
+
+/// Fetch a graph node by pk with its neighbors up to `depth` hops
+pub async fn get_graph_node(
+    Path(pk): Path<String>,
+    Query(params): Query<GraphNodeQueryParams>,
+) -> Result<Json<GraphNodeResponse>, (StatusCode, Json<ErrorResponse>)> {
+    check_pk(&pk)?;
+    if params.depth > MAX_GRAPH_NODE_DEPTH {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new(
+                "invalid_depth",
+                format!("depth must be at most {}", MAX_GRAPH_NODE_DEPTH),
+            )),
+        ));
+    }
+
+    let query_failed = |e: anyhow::Error| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse::new("graph_query_failed", e.to_string())),
+    );
+
+    let cfg = Config::global();
+    let client = db::connect::get_client().await.map_err(query_failed)?;
+    let node = db::graph::get_node_by_pk(&client, &cfg.graph_name, &pk)
+        .await
+        .map_err(query_failed)?
+        .ok_or_else(|| (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse::new("node_not_found", format!("No node with pk '{}'", pk))),
+        ))?;
+    let neighbors = db::graph::get_node_neighbors(&client, &cfg.graph_name, &pk, params.depth)
+        .await
+        .map_err(query_failed)?;
+
+    Ok(Json(GraphNodeResponse {
+        node,
+        depth: params.depth,
+        neighbor_count: neighbors.len(),
+        neighbors,
+    }))
+}
+
+/// Set properties on an existing node without re-ingesting it. The body is a JSON
+/// object merged into the node's properties; a null value removes a property.
+pub async fn patch_graph_node(
+    Path(pk): Path<String>,
+    Json(props): Json<serde_json::Value>,
+) -> Result<Json<GraphNodeUpdateResponse>, (StatusCode, Json<ErrorResponse>)> {
+    check_pk(&pk)?;
+    let invalid = |message: &str| (
+        StatusCode::BAD_REQUEST,
+        Json(ErrorResponse::new("invalid_request", message)),
+    );
+    let Some(map) = props.as_object() else {
+        return Err(invalid("body must be a JSON object of properties"));
+    };
+    if map.contains_key("pk") {
+        return Err(invalid("the pk property can't be changed"));
+    }
+
+    let query_failed = |e: anyhow::Error| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse::new("graph_query_failed", e.to_string())),
+    );
+
+    let cfg = Config::global();
+    let client = db::connect::get_client().await.map_err(query_failed)?;
+    let node = db::graph::update_node_properties(&client, &cfg.graph_name, &pk, map)
+        .await
+        .map_err(query_failed)?
+        .ok_or_else(|| (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse::new("node_not_found", format!("No node with pk '{}'", pk))),
+        ))?;
+    let kg_nodes_updated = db::kg_ops::update_kg_node_properties(&client, &pk, &props)
+        .await
+        .map_err(|e| query_failed(e.into()))?;
+    query_cache::invalidate();
+    tracing::info!(pk = %pk, properties = map.len(), kg_nodes_updated, "Updated node properties");
+
+    Ok(Json(GraphNodeUpdateResponse { node, kg_nodes_updated }))
+}
+
+/// Shortest path between two nodes, for explaining how two entities are connected
+pub async fn get_graph_path(
+    Query(params): Query<GraphPathQueryParams>,
+) -> Result<Json<GraphPathResponse>, (StatusCode, Json<ErrorResponse>)> {
+    check_pk(&params.from)?;
+    check_pk(&params.to)?;
+    let query_failed = |e: anyhow::Error| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse::new("graph_query_failed", e.to_string())),
+    );
+
+    let cfg = Config::global();
+    let max_depth = params.max_depth.unwrap_or(cfg.max_path_depth).min(cfg.max_path_depth);
+    let client = db::connect::get_client().await.map_err(query_failed)?;
+    for pk in [&params.from, &params.to] {
+        if db::graph::get_node_by_pk(&client, &cfg.graph_name, pk).await.map_err(query_failed)?.is_none() {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse::new("node_not_found", format!("No node with pk '{}'", pk))),
+            ));
+        }
+    }
+    let path = db::graph::get_shortest_path(&client, &cfg.graph_name, &params.from, &params.to, max_depth)
+        .await
+        .map_err(query_failed)?;
+
+    let found = path.is_some();
+    let path = path.unwrap_or_default();
+    Ok(Json(GraphPathResponse {
+        from: params.from,
+        to: params.to,
+        max_depth,
+        found,
+        length: path.relationships.len(),
+        nodes: path.nodes,
+        relationships: path.relationships,
+    }))
+}
+
+/// Last `/graph/schema` result and when it was read; autocomplete calls this on every keystroke
+static GRAPH_SCHEMA_CACHE: Mutex<Option<(Instant, GraphSchema)>> = Mutex::new(None);
+
+/// Relation types, node types and AGE labels present in the graph, cached for
+/// `GRAPH_SCHEMA_CACHE_TTL`
+pub async fn get_graph_schema() -> Result<Json<GraphSchema>, (StatusCode, Json<ErrorResponse>)> {
+    let cfg = Config::global();
+    if let Some((read_at, schema)) = GRAPH_SCHEMA_CACHE.lock().unwrap().as_ref() {
+        if read_at.elapsed() < GRAPH_SCHEMA_CACHE_TTL && schema.graph == cfg.graph_name {
+            return Ok(Json(schema.clone()));
+        }
+    }
+
+    let query_failed = |e: anyhow::Error| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse::new("graph_query_failed", e.to_string())),
+    );
+    let client = db::connect::get_client().await.map_err(query_failed)?;
+    let schema = db::graph::get_graph_schema(&client, &cfg.graph_name)
+        .await
+        .map_err(query_failed)?;
+
+    *GRAPH_SCHEMA_CACHE.lock().unwrap() = Some((Instant::now(), schema.clone()));
+    Ok(Json(schema))
+}
+
+/// Stream every node and edge in the graph as node-link JSON or GraphML
+pub async fn export_graph(
+    Query(params): Query<GraphExportParams>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let format = match params.format.as_deref() {
+        None => ExportFormat::default(),
+        Some(name) => ExportFormat::parse(name).ok_or_else(|| (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new(
+                "invalid_format",
+                format!("Unknown export format '{}' (expected json or graphml)", name),
+            )),
+        ))?,
+    };
+
+    let client = db::connect::get_client().await.map_err(|e| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse::new("graph_query_failed", e.to_string())),
+    ))?;
+    let graph = Config::global().graph_name.clone();
+    tracing::info!(graph = %graph, format = format.as_str(), "📤 Exporting graph");
+
+    // Headers are already sent once streaming starts, so a failing row aborts the body
+    let chunks = db::export::export_graph(client, graph, format).map_err(|e| {
+        tracing::error!("❌ Graph export failed: {}", e);
+        std::io::Error::other(e.to_string())
+    });
+    Ok(([(header::CONTENT_TYPE, format.content_type())], Body::from_stream(chunks)))
+}
+
 /// Execute custom Cypher query
 pub async fn execute_cypher(
     Json(payload): Json<CypherQueryRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<ErrorResponse>)> {
-    match run_cypher_query(&payload.query).await {
+    // `$$` would terminate the dollar-quoted cypher() argument and allow raw SQL
+    if payload.query.contains("$$") {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new("invalid_cypher", "Query must not contain '$$'")),
+        ));
+    }
+
+    if !Config::global().cypher_allow_writes {
+        if let Some(clause) = find_cypher_write_clause(&payload.query) {
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(ErrorResponse::new(
+                    "cypher_write_forbidden",
+                    format!(
+                        "{} clauses are not allowed in read-only mode (set CYPHER_ALLOW_WRITES=true to enable)",
+                        clause
+                    ),
+                )),
+            ));
+        }
+    }
+
+    match run_cypher_query(&payload.query, !Config::global().cypher_allow_writes).await {
         Ok(results) => Ok(Json(serde_json::json!({
             "results": results,
             "count": results.as_array().map(|a| a.len()).unwrap_or(0)
@@ -315,18 +941,100 @@ pub async fn execute_cypher(
     }
 }
 
-async fn run_cypher_query(query: &str) -> anyhow::Result<serde_json::Value> {
-    let client = db::connect::get_client().await?;
+/// Run `query` in its own transaction; a `read_only` one makes Postgres refuse any
+/// write the clause check missed
+async fn run_cypher_query(query: &str, read_only: bool) -> anyhow::Result<serde_json::Value> {
+    let cfg = Config::global();
+    let mut client = db::connect::get_client().await?;
     
     let cypher = format!(
-        "SELECT * FROM ag_catalog.cypher('sem_graph'::name, $$
+        "SELECT * FROM ag_catalog.cypher('{}'::name, $$
          {}
          $$::cstring) AS (result ag_catalog.agtype);",
-        query
+        cfg.graph_name, query
     );
     
-    let rows = client.query(&cypher, &[]).await?;
+    let transaction = client.build_transaction().read_only(read_only).start().await?;
+    let rows = transaction.query(&cypher, &[]).await?;
+    transaction.commit().await?;
     let results: Vec<String> = rows.iter().map(|r| r.get::<_, String>(0)).collect();
     
     Ok(serde_json::json!(results))
 }
+
+/// Cypher clauses that modify the graph
+const CYPHER_WRITE_CLAUSES: &[&str] = &["CREATE", "DELETE", "SET", "MERGE", "REMOVE"];
+
+/// Find the first write clause in a Cypher query, if any.
+///
+/// Tokenizes on identifier boundaries so names like `CREATED_BY` don't match,
+/// and skips comments, string literals, backtick-quoted names, labels (`:SET`),
+/// property keys (`{set: 1}`) and property access (`n.set`).
+pub fn find_cypher_write_clause(query: &str) -> Option<&'static str> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        // Skip comments, so a quote inside one doesn't open a literal
+        if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+        if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+            continue;
+        }
+
+        // Skip quoted literals and escaped identifiers. Strings escape with a
+        // backslash; a backtick-quoted name only with a doubled backtick.
+        if c == '\'' || c == '"' || c == '`' {
+            i += 1;
+            while i < chars.len() {
+                if chars[i] == c {
+                    if c == '`' && chars.get(i + 1) == Some(&'`') {
+                        i += 2;
+                        continue;
+                    }
+                    break;
+                }
+                if chars[i] == '\\' && c != '`' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            i += 1;
+            continue;
+        }
+
+        if c.is_alphanumeric() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let token: String = chars[start..i].iter().collect::<String>().to_uppercase();
+
+            let prev = chars[..start].iter().rev().find(|c| !c.is_whitespace());
+            let next = chars[i..].iter().find(|c| !c.is_whitespace());
+            let is_name = matches!(prev, Some(':') | Some('.')) || next == Some(&':');
+
+            if !is_name {
+                if let Some(clause) = CYPHER_WRITE_CLAUSES.iter().find(|w| **w == token) {
+                    return Some(clause);
+                }
+            }
+            continue;
+        }
+
+        i += 1;
+    }
+
+    None
+}
@@ -1,5 +1,17 @@
-use axum::{http::StatusCode, Json};
+use axum::{
+    async_trait,
+    extract::{FromRequest, Query, Request, State},
+    http::{header, StatusCode},
+    Json,
+};
+use serde::de::{value::{MapAccessDeserializer, SeqAccessDeserializer}, DeserializeOwned, Deserializer, MapAccess, SeqAccess, Visitor};
 use serde::Deserialize;
+use crate::api::context_handlers::{db_connect_failed, ContextError};
+use crate::api::jobs::JobStore;
+use crate::api::query_cache;
+use crate::api::models::{ErrorResponse, IngestJobAccepted, IngestJobResult, IngestQualityParams};
+use crate::config::Config;
+use crate::etl::payload::parse_payload;
 use crate::db::{models::*, message_ops::*, kg_ops::*, connect::get_client};
 
 // ============================================================================
@@ -8,8 +20,113 @@ use crate::db::{models::*, message_ops::*, kg_ops::*, connect::get_client};
 
 #[derive(Debug, Deserialize)]
 pub struct BatchMessageIngestRequest {
-    #[serde(flatten)]
     pub turns: Vec<TurnEmbedding>,
+    /// JSON object merged into the metadata of every conversation in `turns`
+    #[serde(default)]
+    pub metadata: Option<serde_json::Value>,
+}
+
+/// `/ingest/messages` accepts either a bare array of turns (turn_embeddings.json)
+/// or `{"turns": [...], "metadata": {...}}`
+#[derive(Debug)]
+pub enum MessageIngestPayload {
+    Turns(Vec<TurnEmbedding>),
+    WithMetadata(BatchMessageIngestRequest),
+}
+
+// Picked by the JSON type rather than `#[serde(untagged)]`, so a malformed turn reports
+// its own error instead of "data did not match any variant"
+impl<'de> Deserialize<'de> for MessageIngestPayload {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct PayloadVisitor;
+
+        impl<'de> Visitor<'de> for PayloadVisitor {
+            type Value = MessageIngestPayload;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("an array of turns or an object with `turns`")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+                Vec::deserialize(SeqAccessDeserializer::new(seq)).map(MessageIngestPayload::Turns)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                BatchMessageIngestRequest::deserialize(MapAccessDeserializer::new(map)).map(MessageIngestPayload::WithMetadata)
+            }
+        }
+
+        deserializer.deserialize_any(PayloadVisitor)
+    }
+}
+
+impl MessageIngestPayload {
+    pub fn into_parts(self) -> (Vec<TurnEmbedding>, Option<serde_json::Value>) {
+        match self {
+            MessageIngestPayload::Turns(turns) => (turns, None),
+            MessageIngestPayload::WithMetadata(req) => (req.turns, req.metadata),
+        }
+    }
+}
+
+// ============================================================================
+// Payload Parsing
+// ============================================================================
+
+/// JSON body extractor for ingest payloads. Unlike `Json`, a body that doesn't match
+/// `T` is rejected with an `invalid_payload` error naming the record, field and
+/// position that failed, e.g. `record 3: field `embedding`: invalid type: string`.
+#[derive(Debug)]
+pub struct IngestJson<T>(pub T);
+
+#[async_trait]
+impl<T: DeserializeOwned, S: Send + Sync> FromRequest<S> for IngestJson<T> {
+    type Rejection = ContextError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let is_json = req.headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|ct| ct.starts_with("application/") && ct.contains("json"));
+        if !is_json {
+            return Err((
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                Json(ErrorResponse::new("unsupported_media_type", "Expected request with `Content-Type: application/json`")),
+            ));
+        }
+
+        let body = String::from_request(req, state).await.map_err(|e| {
+            let code = if e.status() == StatusCode::PAYLOAD_TOO_LARGE { "payload_too_large" } else { "invalid_body" };
+            (e.status(), Json(ErrorResponse::new(code, e.body_text())))
+        })?;
+
+        parse_payload(&body).map(IngestJson).map_err(|e| {
+            tracing::warn!(error = %e, "Rejected malformed ingest payload");
+            (StatusCode::BAD_REQUEST, Json(ErrorResponse::invalid_payload(e)))
+        })
+    }
+}
+
+// ============================================================================
+// Limits
+// ============================================================================
+
+/// Reject a payload of `rows` rows when it exceeds `max_rows` (`MAX_INGEST_ROWS`),
+/// before anything is written
+pub fn check_row_limit(rows: usize, max_rows: usize, what: &str) -> Result<(), ContextError> {
+    if rows <= max_rows {
+        return Ok(());
+    }
+    Err((
+        StatusCode::PAYLOAD_TOO_LARGE,
+        Json(ErrorResponse::new(
+            "payload_too_large",
+            format!(
+                "Request contains {} {}, more than the limit of {} (MAX_INGEST_ROWS); split it into smaller requests",
+                rows, what, max_rows
+            ),
+        )),
+    ))
 }
 
 // ============================================================================
@@ -18,36 +135,66 @@ pub struct BatchMessageIngestRequest {
 
 /// Ingest messages with their full embeddings from turn_embeddings.json
 pub async fn ingest_turn_embeddings(
-    Json(payload): Json<Vec<TurnEmbedding>>,
-) -> Result<Json<IngestResponse>, StatusCode> {
+    IngestJson(payload): IngestJson<MessageIngestPayload>,
+) -> Result<Json<IngestResponse>, ContextError> {
     let start = std::time::Instant::now();
+    let cfg = Config::global();
+    let (mut payload, metadata) = payload.into_parts();
     let total_processed = payload.len();
 
-    println!("Starting ingestion of {} turn embeddings", total_processed);
+    check_row_limit(total_processed, cfg.max_ingest_rows, "turns")?;
+
+    // Turns that don't say which model embedded them are assumed to match the query model
+    for turn in payload.iter_mut().filter(|t| t.embedding_model.is_none()) {
+        turn.embedding_model = Some(cfg.embed_model_name.clone());
+    }
+
+    // Metadata is merged key-by-key, so it has to be an object
+    if metadata.as_ref().is_some_and(|m| !m.is_object()) {
+        tracing::warn!("Conversation metadata must be a JSON object");
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new("invalid_metadata", "Conversation metadata must be a JSON object")),
+        ));
+    }
+
+    tracing::info!(turn_count = total_processed, "Starting ingestion of turn embeddings");
 
     let client = match get_client().await {
         Ok(c) => c,
         Err(e) => {
-            eprintln!("Failed to connect to database: {}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            tracing::error!(error = %e, "Failed to connect to database");
+            return Err(db_connect_failed(e));
         }
     };
 
-    match batch_insert_messages(&client, &payload).await {
-        Ok((count, errors)) => {
-            println!("Successfully ingested {} messages", count);
+    let result = batch_insert_messages_with_metadata(&client, &payload, metadata.as_ref()).await;
+    // Even a failed batch may have written some turns
+    query_cache::invalidate();
+    match result {
+        Ok((count, skipped, errors)) => {
+            tracing::info!(
+                message_count = count,
+                skipped,
+                duration_ms = start.elapsed().as_millis() as u64,
+                "Successfully ingested messages"
+            );
             
             Ok(Json(IngestResponse {
                 success: errors.is_empty(),
                 total_processed,
                 total_inserted: count,
+                skipped,
                 duration_ms: start.elapsed().as_millis(),
                 errors,
             }))
         }
         Err(e) => {
-            eprintln!("Error during batch insert: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            tracing::error!(error = %e, "Error during batch insert");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse::new("ingestion_failed", e.to_string())),
+            ))
         }
     }
 }
@@ -57,43 +204,51 @@ pub async fn ingest_turn_embeddings(
 // ============================================================================
 
 /// Ingest knowledge graph data from enhanced_pipeline_full_results.json
+/// as a background job (poll `GET /ingest/jobs/:job_id`)
 pub async fn ingest_knowledge_graph(
-    Json(payload): Json<ConversationKnowledgeGraph>,
-) -> Result<Json<IngestResponse>, StatusCode> {
-    let start = std::time::Instant::now();
-    
+    State(jobs): State<JobStore>,
+    IngestJson(payload): IngestJson<ConversationKnowledgeGraph>,
+) -> Result<(StatusCode, Json<IngestJobAccepted>), ContextError> {
     let total_processed: usize = payload.conversations.values()
         .map(|kg| kg.nodes.len() + kg.edges.len())
         .sum();
+    let total_conversations = payload.conversations.len();
 
-    println!("Starting ingestion of knowledge graph with {} conversations", 
-        payload.conversations.len());
+    check_row_limit(total_processed, Config::global().max_ingest_rows, "nodes and edges")?;
 
-    let client = match get_client().await {
-        Ok(c) => c,
-        Err(e) => {
-            eprintln!("Failed to connect to database: {}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
-        }
-    };
+    tracing::info!(conversation_count = total_conversations, "Queueing knowledge graph ingestion");
 
-    match batch_insert_knowledge_graph(&client, payload).await {
-        Ok((nodes, edges, errors)) => {
-            println!("Successfully ingested {} nodes and {} edges", nodes, edges);
-            
-            Ok(Json(IngestResponse {
-                success: errors.is_empty(),
-                total_processed,
-                total_inserted: nodes + edges,
-                duration_ms: start.elapsed().as_millis(),
-                errors,
-            }))
-        }
-        Err(e) => {
-            eprintln!("Error during knowledge graph insert: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    let job_id = jobs.spawn("knowledge_graph", total_conversations, move |jobs, job_id| async move {
+        let start = std::time::Instant::now();
+
+        let client = get_client().await
+            .map_err(|e| format!("db_connect_failed: {}", e))?;
+
+        let result = batch_insert_knowledge_graph_with_progress(&client, payload, |done| {
+            jobs.set_progress(&job_id, done)
+        })
+        .await;
+        query_cache::invalidate();
+        let (nodes, edges, errors) = result.map_err(|e| format!("knowledge_graph_ingestion_failed: {}", e))?;
+
+        tracing::info!(
+            nodes,
+            edges,
+            duration_ms = start.elapsed().as_millis() as u64,
+            "Successfully ingested knowledge graph"
+        );
+
+        Ok(IngestJobResult::KnowledgeGraph(IngestResponse {
+            success: errors.is_empty(),
+            total_processed,
+            total_inserted: nodes + edges,
+            skipped: 0,
+            duration_ms: start.elapsed().as_millis(),
+            errors,
+        }))
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(IngestJobAccepted::new(job_id))))
 }
 
 // ============================================================================
@@ -105,7 +260,7 @@ pub async fn get_statistics() -> Result<Json<serde_json::Value>, StatusCode> {
     let client = match get_client().await {
         Ok(c) => c,
         Err(e) => {
-            eprintln!("Failed to connect to database: {}", e);
+            tracing::error!(error = %e, "Failed to connect to database");
             return Err(StatusCode::INTERNAL_SERVER_ERROR);
         }
     };
@@ -113,9 +268,29 @@ pub async fn get_statistics() -> Result<Json<serde_json::Value>, StatusCode> {
     match get_kg_statistics(&client).await {
         Ok(stats) => Ok(Json(stats)),
         Err(e) => {
-            eprintln!("Error fetching statistics: {}", e);
+            tracing::error!(error = %e, "Error fetching statistics");
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
 }
 
+/// Evidence and embedding coverage of the knowledge graph edges (all of them, or
+/// one conversation's with `?conversation_id=`), for data-quality audits
+pub async fn get_quality(
+    Query(params): Query<IngestQualityParams>,
+) -> Result<Json<EvidenceQualityReport>, ContextError> {
+    let conversation_id = params
+        .conversation_id
+        .map(|id| uuid::Uuid::parse_str(&id).map_err(|_| (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new("invalid_conversation_id", format!("Not a valid conversation id: {}", id))),
+        )))
+        .transpose()?;
+
+    let client = get_client().await.map_err(db_connect_failed)?;
+    get_evidence_quality(&client, conversation_id).await.map(Json).map_err(|e| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse::new("quality_query_failed", e.to_string())),
+    ))
+}
+
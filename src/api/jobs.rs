@@ -0,0 +1,174 @@
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use futures::FutureExt;
+use tokio_util::task::TaskTracker;
+use uuid::Uuid;
+
+use crate::api::callback;
+use crate::api::models::{IngestJob, IngestJobResult, JobProgress, JobStatus};
+
+/// How long a completed or failed job stays queryable before it's evicted
+pub const FINISHED_JOB_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// In-memory registry of background ingest jobs, shared by the ingest handlers.
+/// Job state is lost on restart; finished jobs are evicted after a TTL.
+#[derive(Debug, Clone)]
+pub struct JobStore {
+    jobs: Arc<DashMap<Uuid, IngestJob>>,
+    /// Tasks running jobs, so shutdown can wait for them (`drain`)
+    tasks: TaskTracker,
+    ttl: Duration,
+}
+
+impl Default for JobStore {
+    fn default() -> Self {
+        Self::with_ttl(FINISHED_JOB_TTL)
+    }
+}
+
+impl JobStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A store that evicts finished jobs `ttl` after they finish
+    pub fn with_ttl(ttl: Duration) -> Self {
+        Self { jobs: Arc::default(), tasks: TaskTracker::new(), ttl }
+    }
+
+    /// Register a queued job and return its id
+    pub fn create(&self, kind: &str, sessions_total: usize) -> Uuid {
+        self.evict_expired();
+        let job_id = Uuid::new_v4();
+        self.jobs.insert(job_id, IngestJob {
+            job_id,
+            kind: kind.to_string(),
+            status: JobStatus::Queued,
+            progress: JobProgress { sessions_done: 0, sessions_total },
+            created_at: Utc::now().to_rfc3339(),
+            started_at: None,
+            finished_at: None,
+            result: None,
+            error: None,
+        });
+        job_id
+    }
+
+    /// Drop jobs that finished more than the TTL ago; running and queued jobs are kept
+    pub fn evict_expired(&self) {
+        let Ok(ttl) = chrono::Duration::from_std(self.ttl) else {
+            return;
+        };
+        let now = Utc::now();
+        self.jobs.retain(|_, job| {
+            job.finished_at
+                .as_deref()
+                .and_then(|finished| DateTime::parse_from_rfc3339(finished).ok())
+                .is_none_or(|finished| now.signed_duration_since(finished) < ttl)
+        });
+    }
+
+    /// Number of jobs currently held, finished ones included until they're evicted
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    /// Stop accepting jobs and wait for the running ones to finish. Called after
+    /// the server stops, so a shutdown doesn't cut an ingest off half-written.
+    pub async fn drain(&self) {
+        self.tasks.close();
+        if !self.tasks.is_empty() {
+            tracing::info!(jobs = self.tasks.len(), "⏳ Waiting for background ingest jobs to finish");
+        }
+        self.tasks.wait().await;
+    }
+
+    /// Snapshot of a job's current state
+    pub fn get(&self, job_id: &Uuid) -> Option<IngestJob> {
+        self.jobs.get(job_id).map(|job| job.clone())
+    }
+
+    pub fn mark_running(&self, job_id: &Uuid) {
+        if let Some(mut job) = self.jobs.get_mut(job_id) {
+            job.status = JobStatus::Running;
+            job.started_at = Some(Utc::now().to_rfc3339());
+        }
+    }
+
+    pub fn set_progress(&self, job_id: &Uuid, sessions_done: usize) {
+        if let Some(mut job) = self.jobs.get_mut(job_id) {
+            job.progress.sessions_done = sessions_done;
+        }
+    }
+
+    pub fn complete(&self, job_id: &Uuid, result: IngestJobResult) {
+        if let Some(mut job) = self.jobs.get_mut(job_id) {
+            job.status = JobStatus::Completed;
+            job.progress.sessions_done = job.progress.sessions_total;
+            job.finished_at = Some(Utc::now().to_rfc3339());
+            job.result = Some(result);
+        }
+    }
+
+    pub fn fail(&self, job_id: &Uuid, error: String) {
+        if let Some(mut job) = self.jobs.get_mut(job_id) {
+            job.status = JobStatus::Failed;
+            job.finished_at = Some(Utc::now().to_rfc3339());
+            job.error = Some(error);
+        }
+    }
+
+    /// Enqueue `work` as a new job and run it on a spawned task.
+    /// `work` receives the store and its job id so it can report progress.
+    pub fn spawn<F, Fut>(&self, kind: &str, sessions_total: usize, work: F) -> Uuid
+    where
+        F: FnOnce(JobStore, Uuid) -> Fut,
+        Fut: Future<Output = Result<IngestJobResult, String>> + Send + 'static,
+    {
+        self.spawn_with_callback(kind, sessions_total, None, work)
+    }
+
+    /// Same as `spawn`, POSTing the finished job to `callback_url` (if set) once it
+    /// completes or fails
+    pub fn spawn_with_callback<F, Fut>(&self, kind: &str, sessions_total: usize, callback_url: Option<String>, work: F) -> Uuid
+    where
+        F: FnOnce(JobStore, Uuid) -> Fut,
+        Fut: Future<Output = Result<IngestJobResult, String>> + Send + 'static,
+    {
+        let job_id = self.create(kind, sessions_total);
+        let fut = work(self.clone(), job_id);
+        let store = self.clone();
+        self.tasks.spawn(async move {
+            store.mark_running(&job_id);
+            // A panicking job is reported as failed instead of staying `running`
+            let outcome = AssertUnwindSafe(fut).catch_unwind().await.unwrap_or_else(|panic| {
+                let message = panic
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| panic.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "unknown panic".to_string());
+                Err(format!("job panicked: {}", message))
+            });
+            match outcome {
+                Ok(result) => store.complete(&job_id, result),
+                Err(e) => {
+                    tracing::error!("Ingest job {} failed: {}", job_id, e);
+                    store.fail(&job_id, e);
+                }
+            }
+            if let (Some(url), Some(job)) = (callback_url, store.get(&job_id)) {
+                callback::spawn_callback(url, job);
+            }
+        });
+        job_id
+    }
+}
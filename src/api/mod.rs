@@ -1,5 +1,9 @@
+pub mod auth;
+pub mod callback;
 pub mod handlers;
+pub mod jobs;
 pub mod models;
 pub mod routes;
 pub mod ingest_handlers;
 pub mod context_handlers;
+pub mod query_cache;
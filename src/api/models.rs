@@ -1,6 +1,12 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+use crate::db::models::{AgEdge, AgVertex, GraphNeighbor, Message};
+use crate::db::sessions::SessionSummary;
 use crate::etl::parser::{SessionGraph, KnowledgeGraphData};
-use crate::ingest::{SessionIngestStats, BatchIngestStats};
+use crate::etl::payload::{PayloadError, PayloadLocation};
+use crate::config::Config;
+use crate::ingest::{SessionIngestStats, BatchIngestStats, EmbedErrorMode, MissingNodeMode, ReembedCounts, ReembedScope, ReembedStats, SessionIngestOptions};
 
 // ============================================================================
 // Request Models
@@ -10,11 +16,59 @@ use crate::ingest::{SessionIngestStats, BatchIngestStats};
 pub struct IngestSessionRequest {
     pub session_id: String,
     pub graph: SessionGraph,
+    #[serde(default)]
+    pub on_embed_error: EmbedErrorMode, // "abort" (default), "skip", "placeholder"
+    pub on_missing_node: Option<MissingNodeMode>, // "strict" or "lenient"; default MISSING_NODE_MODE
+    /// POSTed the `IngestSessionResponse` once ingestion succeeds
+    pub callback_url: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct IngestBatchRequest {
     pub sessions: KnowledgeGraphData,
+    #[serde(default)]
+    pub on_embed_error: EmbedErrorMode,
+    pub on_missing_node: Option<MissingNodeMode>,
+    /// POSTed the finished `IngestJob` (with its result or error) when the job ends
+    pub callback_url: Option<String>,
+}
+
+impl IngestSessionRequest {
+    pub fn ingest_options(&self) -> SessionIngestOptions {
+        SessionIngestOptions {
+            on_embed_error: self.on_embed_error,
+            on_missing_node: self.on_missing_node.unwrap_or(Config::global().missing_node_mode),
+            ..Default::default()
+        }
+    }
+}
+
+impl IngestBatchRequest {
+    pub fn ingest_options(&self) -> SessionIngestOptions {
+        SessionIngestOptions {
+            on_embed_error: self.on_embed_error,
+            on_missing_node: self.on_missing_node.unwrap_or(Config::global().missing_node_mode),
+            ..Default::default()
+        }
+    }
+}
+
+/// Body of `POST /maintenance/reembed`; `{}` re-embeds every edge lacking a real embedding
+#[derive(Debug, Default, Deserialize)]
+pub struct ReembedRequest {
+    /// Only this session's edges (the `embeddings` table)
+    pub session_id: Option<String>,
+    /// Only this conversation's KG edges (`kg_edge_embeddings`)
+    pub conversation_id: Option<Uuid>,
+}
+
+impl ReembedRequest {
+    pub fn scope(&self) -> ReembedScope {
+        ReembedScope {
+            session_id: self.session_id.clone(),
+            conversation_id: self.conversation_id,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -24,10 +78,92 @@ pub struct QuerySimilarRequest {
     pub top_k: i64,
     #[serde(default)]
     pub threshold: Option<f32>,
+    /// `cosine`, `dot` or `euclidean`; defaults to `SIMILARITY_METRIC`
+    #[serde(default)]
+    pub metric: Option<String>,
+    /// Return the evidence messages inline with each result
+    #[serde(default)]
+    pub include_evidence_content: Option<bool>,
+    /// Cap on resolved evidence messages per result (default `DEFAULT_MAX_EVIDENCE_PER_EDGE`)
+    #[serde(default)]
+    pub max_evidence_per_edge: Option<usize>,
+    /// Only compare against vectors recorded with this embedding model
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+    /// Also return the graph edges around each match, ranked below the matches
+    #[serde(default)]
+    pub expand_neighbors: Option<bool>,
+    /// How far to expand from each match with `expand_neighbors` (default 1, max `MAX_NEIGHBOR_HOPS`)
+    #[serde(default)]
+    pub hops: Option<usize>,
+    /// Only return edges with one of these relations (case-insensitive)
+    #[serde(default)]
+    pub relations: Vec<String>,
+    /// Only return edges with an endpoint of one of these node types (case-insensitive)
+    #[serde(default)]
+    pub node_types: Vec<String>,
+}
+
+impl QuerySimilarRequest {
+    pub fn filters(&self) -> EdgeFilters {
+        EdgeFilters { relations: self.relations.clone(), node_types: self.node_types.clone() }
+    }
+}
+
+/// Relation and node type facets applied to `/query/similar` matches
+#[derive(Debug, Clone, Default)]
+pub struct EdgeFilters {
+    pub relations: Vec<String>,
+    pub node_types: Vec<String>,
+}
+
+impl EdgeFilters {
+    pub fn is_empty(&self) -> bool {
+        self.relations.is_empty() && self.node_types.is_empty()
+    }
+
+    pub fn matches_relation(&self, relation: &str) -> bool {
+        self.relations.is_empty() || self.relations.iter().any(|r| r.eq_ignore_ascii_case(relation))
+    }
+
+    pub fn matches_node_type(&self, node_type: Option<&str>) -> bool {
+        self.node_types.is_empty()
+            || node_type.is_some_and(|t| self.node_types.iter().any(|n| n.eq_ignore_ascii_case(t)))
+    }
 }
 
+/// Candidates fetched per requested result when `relations` or `node_types` is set,
+/// since the filters are applied after the vector ranking
+pub const FILTERED_CANDIDATE_FACTOR: i64 = 10;
+
+/// Largest `hops` accepted for `expand_neighbors`; larger values are clamped
+pub const MAX_NEIGHBOR_HOPS: usize = 3;
+
+/// Factor a neighbor edge's similarity is scaled by per hop from the match it was found from
+pub const NEIGHBOR_SIMILARITY_DECAY: f32 = 0.5;
+
+/// `top_k` for `/query/similar` when none is given
+pub const DEFAULT_SIMILAR_TOP_K: i64 = 5;
+
 fn default_top_k() -> i64 {
-    5
+    DEFAULT_SIMILAR_TOP_K
+}
+
+/// Largest `top_k` accepted by `/query/similar`; larger values are clamped
+pub const MAX_SIMILAR_TOP_K: i64 = 100;
+
+/// Query parameters for `GET /query/similar` (mirrors `QuerySimilarRequest`)
+#[derive(Debug, Default, Deserialize)]
+pub struct QuerySimilarParams {
+    pub q: Option<String>,
+    pub top_k: Option<i64>,
+    pub threshold: Option<f32>,
+    pub metric: Option<String>,
+    pub embedding_model: Option<String>,
+    /// Comma-separated `relations` filter
+    pub relations: Option<String>,
+    /// Comma-separated `node_types` filter
+    pub node_types: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -35,6 +171,69 @@ pub struct CypherQueryRequest {
     pub query: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct EmbedRequest {
+    pub text: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct EmbedQueryParams {
+    /// Return the whole vector instead of a preview
+    #[serde(default)]
+    pub full: bool,
+}
+
+/// Deepest neighborhood `/graph/node/:pk` will expand
+pub const MAX_GRAPH_NODE_DEPTH: usize = 5;
+
+#[derive(Debug, Deserialize)]
+pub struct GraphNodeQueryParams {
+    #[serde(default = "default_graph_node_depth")]
+    pub depth: usize,
+}
+
+fn default_graph_node_depth() -> usize {
+    1
+}
+
+/// Query parameters for `GET /graph/path`
+#[derive(Debug, Deserialize)]
+pub struct GraphPathQueryParams {
+    pub from: String,
+    pub to: String,
+    /// Longest path searched, capped at `MAX_PATH_DEPTH` (the default)
+    pub max_depth: Option<usize>,
+}
+
+/// Query parameters for `GET /ingest/quality`
+#[derive(Debug, Default, Deserialize)]
+pub struct IngestQualityParams {
+    /// Only this conversation's edges
+    pub conversation_id: Option<String>,
+}
+
+/// Query parameters for `GET /graph/export`
+#[derive(Debug, Default, Deserialize)]
+pub struct GraphExportParams {
+    /// `json` (node-link, the default) or `graphml`
+    pub format: Option<String>,
+}
+
+/// How long `GET /graph/schema` serves a cached result before re-reading the database
+pub const GRAPH_SCHEMA_CACHE_TTL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Sessions returned by `GET /ingest/sessions` when no `limit` is given
+pub const DEFAULT_SESSION_LIST_LIMIT: i64 = 50;
+
+/// Largest `limit` accepted by `GET /ingest/sessions`; larger values are clamped
+pub const MAX_SESSION_LIST_LIMIT: i64 = 500;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct SessionListParams {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
 // ============================================================================
 // Response Models
 // ============================================================================
@@ -51,12 +250,35 @@ pub struct StatusResponse {
 }
 
 #[derive(Debug, Serialize)]
+pub struct LivenessResponse {
+    pub status: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DependencyStatus {
+    pub status: String, // "up", "down", "not_configured"
+    pub required: bool,
+    pub latency_ms: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReadinessResponse {
+    pub status: String, // "ready", "degraded", "not_ready"
+    pub checks: BTreeMap<String, DependencyStatus>,
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct IngestSessionResponse {
     pub session_id: String,
     pub nodes_created: usize,
     pub edges_created: usize,
     pub embeddings_created: usize,
+    pub edges_skipped: usize,
+    pub skipped_unchanged: usize,
     pub duration_ms: u64,
+    pub errors: Vec<String>,
 }
 
 impl From<SessionIngestStats> for IngestSessionResponse {
@@ -66,17 +288,43 @@ impl From<SessionIngestStats> for IngestSessionResponse {
             nodes_created: stats.nodes_created,
             edges_created: stats.edges_created,
             embeddings_created: stats.embeddings_created,
+            edges_skipped: stats.edges_skipped,
+            skipped_unchanged: stats.skipped_unchanged,
             duration_ms: stats.duration_ms,
+            errors: stats.errors,
         }
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
+pub struct ReembedResponse {
+    /// Session edges (`embeddings`)
+    pub embeddings: ReembedCounts,
+    /// Knowledge graph edges (`kg_edge_embeddings`)
+    pub kg_edge_embeddings: ReembedCounts,
+    pub duration_ms: u64,
+    pub errors: Vec<String>,
+}
+
+impl From<ReembedStats> for ReembedResponse {
+    fn from(stats: ReembedStats) -> Self {
+        Self {
+            embeddings: stats.embeddings,
+            kg_edge_embeddings: stats.kg_edge_embeddings,
+            duration_ms: stats.duration_ms,
+            errors: stats.errors,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct IngestBatchResponse {
     pub total_sessions: usize,
     pub total_nodes: usize,
     pub total_edges: usize,
     pub total_embeddings: usize,
+    pub total_edges_skipped: usize,
+    pub skipped_unchanged: usize,
     pub duration_ms: u64,
     pub errors: Vec<String>,
 }
@@ -88,13 +336,73 @@ impl From<BatchIngestStats> for IngestBatchResponse {
             total_nodes: stats.total_nodes,
             total_edges: stats.total_edges,
             total_embeddings: stats.total_embeddings,
+            total_edges_skipped: stats.total_edges_skipped,
+            skipped_unchanged: stats.skipped_unchanged,
             duration_ms: stats.duration_ms,
             errors: stats.errors,
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobProgress {
+    pub sessions_done: usize,
+    pub sessions_total: usize,
+}
+
+/// Final result of a background ingest job
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum IngestJobResult {
+    Batch(IngestBatchResponse),
+    KnowledgeGraph(crate::db::models::IngestResponse),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IngestJob {
+    pub job_id: Uuid,
+    pub kind: String, // "batch", "knowledge_graph"
+    pub status: JobStatus,
+    pub progress: JobProgress,
+    pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<IngestJobResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Returned with 202 Accepted when an ingest job is enqueued
 #[derive(Debug, Serialize)]
+pub struct IngestJobAccepted {
+    pub job_id: Uuid,
+    pub status: JobStatus,
+    pub status_url: String,
+}
+
+impl IngestJobAccepted {
+    pub fn new(job_id: Uuid) -> Self {
+        Self {
+            job_id,
+            status: JobStatus::Queued,
+            status_url: format!("/ingest/jobs/{}", job_id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct EdgeResult {
     pub source: String,
     pub relation: String,
@@ -108,12 +416,118 @@ pub struct SimilarityResult {
     pub similarity: f32,
     pub distance: f32,
     pub evidence_message_ids: Vec<String>,
+    /// Resolved evidence messages, when requested with `include_evidence_content`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub evidence: Option<Vec<Message>>,
+    /// Set on edges added by `expand_neighbors`: the match they were reached from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub neighbor_of: Option<NeighborOf>,
+}
+
+/// Where an `expand_neighbors` result came from
+#[derive(Debug, Clone, Serialize)]
+pub struct NeighborOf {
+    pub edge: EdgeResult,
+    pub hops: usize,
+}
+
+/// Number of leading values returned by `/query/embed` unless `full=true`
+pub const EMBED_PREVIEW_LEN: usize = 16;
+
+#[derive(Debug, Serialize)]
+pub struct EmbedResponse {
+    pub embedding: Vec<f32>,
+    pub dim: usize,
+    pub truncated: bool,
+    pub lsh_bucket: i32,
+    pub provider: String,
+    pub duration_ms: u64,
 }
 
 #[derive(Debug, Serialize)]
 pub struct QuerySimilarResponse {
     pub results: Vec<SimilarityResult>,
     pub count: usize,
+    /// The query's LSH buckets were empty, so results come from a scan of at most
+    /// `FALLBACK_SCAN_LIMIT` embeddings instead of an index hit
+    pub degraded: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphNodeResponse {
+    pub node: AgVertex,
+    pub depth: usize,
+    pub neighbors: Vec<GraphNeighbor>,
+    pub neighbor_count: usize,
+}
+
+/// Node after `PATCH /graph/node/:pk`
+#[derive(Debug, Serialize)]
+pub struct GraphNodeUpdateResponse {
+    pub node: AgVertex,
+    /// `kg_nodes` rows (one per conversation the node appears in) whose properties were updated
+    pub kg_nodes_updated: u64,
+}
+
+/// Shortest path between two nodes (`GET /graph/path`)
+#[derive(Debug, Serialize)]
+pub struct GraphPathResponse {
+    pub from: String,
+    pub to: String,
+    /// Depth searched, after capping at `MAX_PATH_DEPTH`
+    pub max_depth: usize,
+    /// Whether the nodes are connected within `max_depth`
+    pub found: bool,
+    /// Relationships along the path (0 when `from` and `to` are the same node)
+    pub length: usize,
+    pub nodes: Vec<AgVertex>,
+    pub relationships: Vec<AgEdge>,
+}
+
+/// Occupancy of the single-table LSH buckets (`GET /debug/lsh-distribution`)
+#[derive(Debug, Serialize)]
+pub struct LshDistributionResponse {
+    /// Embeddings per non-empty bucket
+    pub buckets: BTreeMap<i32, i64>,
+    /// `LSH_BUCKETS`
+    pub configured_buckets: usize,
+    pub non_empty_buckets: usize,
+    pub total_embeddings: i64,
+    pub max_occupancy: i64,
+    /// Mean embeddings per non-empty bucket
+    pub mean_occupancy: f64,
+    /// Over all configured buckets, empty ones included: 0.0 is an even spread,
+    /// values near 1.0 mean the embeddings have collapsed into a few buckets
+    pub gini: f64,
+}
+
+impl LshDistributionResponse {
+    pub fn new(buckets: BTreeMap<i32, i64>, configured_buckets: usize) -> Self {
+        let total_embeddings: i64 = buckets.values().sum();
+        let non_empty_buckets = buckets.len();
+        // Buckets past LSH_BUCKETS (stored before it was lowered) still count
+        let mut occupancy: Vec<i64> = buckets.values().copied().collect();
+        occupancy.resize(configured_buckets.max(non_empty_buckets), 0);
+        Self {
+            configured_buckets,
+            non_empty_buckets,
+            total_embeddings,
+            max_occupancy: buckets.values().copied().max().unwrap_or(0),
+            mean_occupancy: if non_empty_buckets == 0 { 0.0 } else { total_embeddings as f64 / non_empty_buckets as f64 },
+            gini: crate::etl::lsh::gini_coefficient(&occupancy),
+            buckets,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionListResponse {
+    pub sessions: Vec<SessionSummary>,
+    pub count: usize,
+    /// All ingested sessions, for paging
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
 }
 
 #[derive(Debug, Serialize)]
@@ -126,6 +540,9 @@ pub struct SessionGraphResponse {
 pub struct ErrorResponse {
     pub error: String,
     pub message: String,
+    /// Record and field of a malformed request body
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<Box<PayloadLocation>>,
 }
 
 impl ErrorResponse {
@@ -133,6 +550,16 @@ impl ErrorResponse {
         Self {
             error: error.into(),
             message: message.into(),
+            location: None,
+        }
+    }
+
+    /// `invalid_payload` error pointing at the record and field that failed to parse
+    pub fn invalid_payload(err: PayloadError) -> Self {
+        Self {
+            error: "invalid_payload".to_string(),
+            message: err.to_string(),
+            location: Some(Box::new(err.location)),
         }
     }
 }
@@ -0,0 +1,119 @@
+//! TTL cache of `/query/llm-context` responses, so dashboards and retries that repeat
+//! a request don't re-embed and re-search it. Any ingest through the API invalidates it.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::api::context_handlers::{ContextQueryRequest, ContextQueryResponse};
+use crate::config::Config;
+
+struct CacheEntry {
+    response: ContextQueryResponse,
+    inserted_at: Instant,
+    /// `QueryCache::generation` when the response was computed
+    generation: u64,
+}
+
+/// Bounded map from a request to its response. Entries expire after `ttl` and are
+/// dropped when the data changes (`invalidate`); when full, the oldest entry is evicted.
+pub struct QueryCache {
+    entries: Mutex<HashMap<String, CacheEntry>>,
+    capacity: usize,
+    ttl: Duration,
+    generation: AtomicU64,
+}
+
+impl QueryCache {
+    /// A cache of at most `capacity` responses (0 disables caching)
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        QueryCache {
+            entries: Mutex::new(HashMap::new()),
+            capacity,
+            ttl,
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0 && !self.ttl.is_zero()
+    }
+
+    /// Cache key of a request: every field, since each one can change the response
+    pub fn key(request: &ContextQueryRequest) -> String {
+        serde_json::to_string(request).expect("request serializes")
+    }
+
+    /// The cached response for `key`, unless it expired or predates the last ingest
+    pub fn get(&self, key: &str) -> Option<ContextQueryResponse> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let generation = self.generation.load(Ordering::Acquire);
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some(entry) if entry.generation == generation && entry.inserted_at.elapsed() < self.ttl => {
+                Some(entry.response.clone())
+            }
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Store `response` for `key`. `generation` is the value of `generation()` read
+    /// before the response was computed, so a response that raced an ingest isn't kept.
+    pub fn insert(&self, key: String, response: ContextQueryResponse, generation: u64) {
+        if !self.is_enabled() || generation != self.generation() {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            let ttl = self.ttl;
+            entries.retain(|_, e| e.generation == generation && e.inserted_at.elapsed() < ttl);
+            if entries.len() >= self.capacity {
+                let oldest = entries.iter().min_by_key(|(_, e)| e.inserted_at).map(|(k, _)| k.clone());
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+        entries.insert(key, CacheEntry { response, inserted_at: Instant::now(), generation });
+    }
+
+    /// Current data generation; bumped by every `invalidate`
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Drop every cached response, after data was ingested or changed
+    pub fn invalidate(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        self.entries.lock().unwrap().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The process-wide cache, sized from `QUERY_CACHE_SIZE` and `QUERY_CACHE_TTL_SECS`
+pub fn global() -> &'static QueryCache {
+    static CACHE: OnceLock<QueryCache> = OnceLock::new();
+    CACHE.get_or_init(|| {
+        let cfg = Config::global();
+        QueryCache::new(cfg.query_cache_size, Duration::from_secs(cfg.query_cache_ttl_secs))
+    })
+}
+
+/// Invalidate the process-wide cache (called by the ingest handlers)
+pub fn invalidate() {
+    global().invalidate();
+}
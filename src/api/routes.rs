@@ -2,19 +2,33 @@ use axum::{
     routing::{get, post},
     Router,
     extract::DefaultBodyLimit,
+    middleware,
 };
-use tower_http::cors::CorsLayer;
 use tower_http::trace::TraceLayer;
 
+use crate::config::Config;
+use crate::telemetry;
+use super::auth;
 use super::handlers;
+use super::jobs::JobStore;
 use super::ingest_handlers;
 use super::context_handlers;
 
-pub fn create_router() -> Router {
-    Router::new()
-        // Health check
-        .route("/status", get(handlers::health_check))
-        
+/// Build the service router. Background ingests run on `jobs`, which the caller
+/// drains on shutdown.
+pub fn create_router(jobs: JobStore) -> Router {
+    create_router_with_config(Config::global(), jobs)
+}
+
+/// `create_router` with the API key, CORS origins and body limit of `cfg`
+pub fn create_router_with_config(cfg: &Config, jobs: JobStore) -> Router {
+    // Everything except health checks requires the API key (when configured)
+    let require_key = middleware::from_fn_with_state(cfg.api_key.clone(), auth::require_api_key);
+    let metrics = Router::new()
+        .route("/metrics", get(handlers::metrics))
+        .route_layer(require_key.clone())
+        .with_state(telemetry::install());
+    let protected = Router::new()
         // Ingestion endpoints
         .route("/ingest/session", post(handlers::ingest_session))
         .route("/ingest/batch", post(handlers::ingest_batch))
@@ -23,20 +37,45 @@ pub fn create_router() -> Router {
         .route("/ingest/messages", post(ingest_handlers::ingest_turn_embeddings))
         .route("/ingest/knowledge-graph", post(ingest_handlers::ingest_knowledge_graph))
         .route("/ingest/statistics", get(ingest_handlers::get_statistics))
+        .route("/ingest/quality", get(ingest_handlers::get_quality))
+        .route("/ingest/sessions", get(handlers::list_sessions))
+        .route("/ingest/sessions/:session_id", get(handlers::get_session_stats))
+        .route("/ingest/jobs/:job_id", get(handlers::get_ingest_job))
         
         // Query endpoints
-        .route("/query/similar", post(handlers::query_similar))
+        .route("/query/similar", post(handlers::query_similar).get(handlers::query_similar_get))
+        .route("/query/embed", post(handlers::query_embed))
         .route("/query/session/:session_id", get(handlers::get_session))
         
         // New: LLM Context query endpoints
         .route("/query/llm-context", post(context_handlers::query_llm_context))
         .route("/query/messages", post(context_handlers::query_messages_by_ids))
+        .route("/query/messages/search", post(context_handlers::search_messages))
+        .route("/query/conversation/:conversation_id", get(context_handlers::get_conversation_metadata))
         
         // Graph query endpoint
         .route("/graph/cypher", post(handlers::execute_cypher))
+        .route("/graph/node/:pk", get(handlers::get_graph_node).patch(handlers::patch_graph_node))
+        .route("/graph/path", get(handlers::get_graph_path))
+        .route("/graph/schema", get(handlers::get_graph_schema))
+        .route("/graph/export", get(handlers::export_graph))
+        
+        // Maintenance
+        .route("/maintenance/reembed", post(handlers::reembed))
+        .route("/debug/lsh-distribution", get(handlers::lsh_distribution))
+        .route_layer(require_key)
+        .with_state(jobs);
+
+    Router::new()
+        // Health checks
+        .route("/status", get(handlers::health_check))
+        .route("/health/live", get(handlers::health_live))
+        .route("/health/ready", get(handlers::health_ready))
+        .merge(metrics)
+        .merge(protected)
         
         // Middleware
-        .layer(DefaultBodyLimit::max(500 * 1024 * 1024)) // 500MB limit for large ingestion
-        .layer(CorsLayer::permissive())
+        .layer(DefaultBodyLimit::max(cfg.max_body_bytes))
+        .layer(auth::cors_layer(&cfg.cors_allowed_origins))
         .layer(TraceLayer::new_for_http())
 }
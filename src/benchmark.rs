@@ -0,0 +1,116 @@
+//! Per-stage ingest timings (used by `bin/benchmark`)
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A timed step of the ingest pipeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IngestStage {
+    /// Reading and deserializing the input file
+    Parse,
+    /// Batch node upsert, one sample per session
+    NodeUpsert,
+    /// Edge upsert plus its evidence rows, one sample per edge
+    EdgeUpsert,
+    /// Embedding request, one sample per edge
+    Embedding,
+    /// LSH bucket hashing, one sample per edge
+    LshHash,
+    /// Embedding row insert, one sample per edge
+    VectorInsert,
+}
+
+impl IngestStage {
+    /// Every stage, in pipeline order
+    pub const ALL: [IngestStage; 6] = [
+        IngestStage::Parse,
+        IngestStage::NodeUpsert,
+        IngestStage::EdgeUpsert,
+        IngestStage::Embedding,
+        IngestStage::LshHash,
+        IngestStage::VectorInsert,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IngestStage::Parse => "parse",
+            IngestStage::NodeUpsert => "node_upsert",
+            IngestStage::EdgeUpsert => "edge_upsert",
+            IngestStage::Embedding => "embedding",
+            IngestStage::LshHash => "lsh_hash",
+            IngestStage::VectorInsert => "vector_insert",
+        }
+    }
+}
+
+/// Durations recorded per stage. Clones share the same samples, so one can be passed
+/// in `SessionIngestOptions` and read back after the ingest.
+#[derive(Debug, Clone, Default)]
+pub struct StageTimings {
+    samples: Arc<Mutex<HashMap<IngestStage, Vec<Duration>>>>,
+}
+
+impl StageTimings {
+    pub fn record(&self, stage: IngestStage, elapsed: Duration) {
+        self.samples.lock().unwrap().entry(stage).or_default().push(elapsed);
+    }
+
+    /// Summary of each stage with at least one sample, in pipeline order
+    pub fn summary(&self) -> Vec<StageSummary> {
+        let samples = self.samples.lock().unwrap();
+        let grand_total: Duration = samples.values().flatten().sum();
+        IngestStage::ALL
+            .iter()
+            .filter_map(|stage| {
+                let durations = samples.get(stage).filter(|d| !d.is_empty())?;
+                Some(StageSummary::new(*stage, durations, grand_total))
+            })
+            .collect()
+    }
+}
+
+/// Aggregate and percentile timings for one stage, in milliseconds
+#[derive(Debug, Clone, Serialize)]
+pub struct StageSummary {
+    pub stage: &'static str,
+    pub samples: usize,
+    pub total_ms: f64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+    /// Fraction of the summed time of all stages
+    pub share: f64,
+}
+
+impl StageSummary {
+    fn new(stage: IngestStage, durations: &[Duration], grand_total: Duration) -> Self {
+        let mut sorted = durations.to_vec();
+        sorted.sort();
+        let total: Duration = sorted.iter().sum();
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        StageSummary {
+            stage: stage.as_str(),
+            samples: sorted.len(),
+            total_ms: ms(total),
+            mean_ms: ms(total) / sorted.len() as f64,
+            p50_ms: ms(percentile(&sorted, 50.0)),
+            p95_ms: ms(percentile(&sorted, 95.0)),
+            p99_ms: ms(percentile(&sorted, 99.0)),
+            max_ms: ms(sorted[sorted.len() - 1]),
+            share: if grand_total.is_zero() { 0.0 } else { total.as_secs_f64() / grand_total.as_secs_f64() },
+        }
+    }
+}
+
+/// Nearest-rank percentile `p` (0-100) of ascending `sorted` durations; zero if empty
+pub fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
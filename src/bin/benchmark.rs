@@ -0,0 +1,92 @@
+use rust_ingester::{
+    benchmark::{IngestStage, StageTimings},
+    config::Config,
+    etl::{parser::KnowledgeGraphData, payload::parse_payload},
+    ingest::{self, SessionIngestOptions},
+};
+use anyhow::{Context, Result};
+
+/// Ingest a fixture dataset and report the time spent in each pipeline stage
+/// (parsing, node upsert, edge upsert, embedding, LSH hashing, vector insert)
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Load environment variables
+    dotenvy::dotenv().ok();
+
+    // Library logs go to stderr (filter with RUST_LOG); the report below goes to stdout
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "rust_ingester=warn".into()),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    let args: Vec<String> = std::env::args().collect();
+    let json_output = args.iter().any(|a| a == "--json");
+    let Some(file_path) = args.iter().skip(1).find(|a| !a.starts_with("--")) else {
+        eprintln!("Usage: {} <path-to-json-file> [--json]", args[0]);
+        eprintln!("Example: {} Data/ok.json", args[0]);
+        std::process::exit(1);
+    };
+
+    let cfg = Config::try_from_env()?;
+    cfg.validate()?;
+    let cfg = Config::init(cfg);
+
+    let timings = StageTimings::default();
+    let started = std::time::Instant::now();
+    let content = tokio::fs::read_to_string(file_path).await?;
+    let data: KnowledgeGraphData = parse_payload(&content).with_context(|| format!("parsing {}", file_path))?;
+    timings.record(IngestStage::Parse, started.elapsed());
+
+    let opts = SessionIngestOptions {
+        timings: Some(timings.clone()),
+        ..Default::default()
+    };
+    let stats = ingest::ingest_knowledge_graph_data(cfg, &data, &opts).await?;
+    let wall_ms = started.elapsed().as_secs_f64() * 1000.0;
+    let summary = timings.summary();
+
+    if json_output {
+        let report = serde_json::json!({
+            "file": file_path,
+            "sessions": stats.total_sessions,
+            "nodes": stats.total_nodes,
+            "edges": stats.total_edges,
+            "embeddings": stats.total_embeddings,
+            "skipped_unchanged": stats.skipped_unchanged,
+            "errors": stats.errors.len(),
+            "wall_ms": wall_ms,
+            "embed_concurrency": cfg.embed_concurrency,
+            "stages": summary,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("⏱️  Ingest benchmark: {} ({} sessions, {} nodes, {} edges, {} embeddings)",
+        file_path, stats.total_sessions, stats.total_nodes, stats.total_edges, stats.total_embeddings);
+    if stats.skipped_unchanged > 0 {
+        println!("   {} edges were unchanged and skipped; use a fresh database to time every stage", stats.skipped_unchanged);
+    }
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    println!("   {:<14} {:>7} {:>11} {:>9} {:>9} {:>9} {:>9} {:>9} {:>6}",
+        "stage", "samples", "total ms", "mean", "p50", "p95", "p99", "max", "share");
+    for stage in &summary {
+        println!("   {:<14} {:>7} {:>11.1} {:>9.2} {:>9.2} {:>9.2} {:>9.2} {:>9.2} {:>5.1}%",
+            stage.stage, stage.samples, stage.total_ms, stage.mean_ms,
+            stage.p50_ms, stage.p95_ms, stage.p99_ms, stage.max_ms, stage.share * 100.0);
+    }
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    println!("   Wall time: {:.1} ms", wall_ms);
+    if cfg.embed_concurrency > 1 {
+        println!("   EMBED_CONCURRENCY={}: embedding requests overlap, so their total exceeds the time they add to the wall clock",
+            cfg.embed_concurrency);
+    }
+    if !stats.errors.is_empty() {
+        println!("   ⚠️  {} errors during ingest (run with RUST_LOG=rust_ingester=info for details)", stats.errors.len());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use rust_ingester::{config::Config, eval::{EvalReport, EvalSet, QueryOutcome}, ingest, retrieve};
+use anyhow::{Context, Result};
+
+/// Measure retrieval quality (recall@k, precision@k, MRR, LSH fallback rate) for a
+/// labeled query set, through the real embedding + LSH + database path
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Load environment variables
+    dotenvy::dotenv().ok();
+
+    // Library logs go to stderr (filter with RUST_LOG); the report below goes to stdout
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "rust_ingester=warn".into()),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    let args: Vec<String> = std::env::args().collect();
+    let json_output = args.iter().any(|a| a == "--json");
+    let positional: Vec<&String> = args.iter().skip(1).filter(|a| !a.starts_with("--")).collect();
+    let Some(file_path) = positional.first() else {
+        eprintln!("Usage: {} <eval-set.json> [k] [--json]", args[0]);
+        eprintln!("Example: {} Data/eval_synthetic.json 5", args[0]);
+        std::process::exit(1);
+    };
+    let k: usize = match positional.get(1) {
+        Some(k) => k.parse().context("k must be a positive integer")?,
+        None => 5,
+    };
+
+    let cfg = Config::try_from_env()?;
+    cfg.validate()?;
+    let cfg = Config::init(cfg);
+
+    let content = tokio::fs::read_to_string(file_path).await?;
+    let set: EvalSet = serde_json::from_str(&content).with_context(|| format!("parsing {}", file_path))?;
+
+    for triplet in &set.triplets {
+        ingest::ingest_triplet(cfg, triplet.clone()).await?;
+    }
+
+    let mut outcomes = Vec::with_capacity(set.queries.len());
+    for case in &set.queries {
+        let found = retrieve::query_similar_with_status(cfg, &case.query, k as i64).await?;
+        outcomes.push(QueryOutcome {
+            retrieved: found.results.iter().map(|(id, _)| *id).collect(),
+            degraded: found.degraded,
+        });
+    }
+    let report = EvalReport::new(&set.queries, &outcomes, k);
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("📏 Retrieval evaluation: {} ({} queries, {} triplets ingested)", file_path, report.queries, set.triplets.len());
+    println!("   LSH_BUCKETS={} LSH_TABLES={} SIMILARITY_METRIC={} FALLBACK_SCAN_LIMIT={}",
+        cfg.lsh_buckets, cfg.lsh_tables, cfg.similarity_metric.as_str(), cfg.fallback_scan_limit);
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    for score in &report.per_query {
+        println!("   {:<40} recall {:.2}  precision {:.2}  rr {:.2}{}",
+            score.query, score.recall, score.precision, score.reciprocal_rank,
+            if score.degraded { "  (fallback scan)" } else { "" });
+    }
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    println!("   Recall@{}:      {:.3}", k, report.recall_at_k);
+    println!("   Precision@{}:   {:.3}", k, report.precision_at_k);
+    println!("   MRR:            {:.3}", report.mrr);
+    println!("   Fallback rate:  {:.1}%", report.fallback_rate * 100.0);
+
+    Ok(())
+}
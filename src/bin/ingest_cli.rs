@@ -1,27 +1,115 @@
-use rust_ingester::ingest;
+use rust_ingester::{
+    api::{handlers::query_similar_edges, models::{EdgeFilters, ReembedResponse, DEFAULT_SIMILAR_TOP_K}},
+    config::Config,
+    db,
+    ingest::{self, ReembedScope},
+};
 use anyhow::Result;
+use clap::{Parser, Subcommand};
+
+/// Ingest knowledge graph files, and query or maintain what was ingested
+#[derive(Parser)]
+#[command(name = "ingest_cli", args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// File to ingest; `ingest_cli <file>` is the same as `ingest_cli ingest <file>`
+    file: Option<String>,
+    /// Print machine-readable JSON instead of the formatted report
+    #[arg(long, global = true)]
+    json: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Ingest a knowledge graph JSON file
+    Ingest { file: String },
+    /// Find the stored edges most similar to a text
+    Query {
+        text: String,
+        /// Number of edges to return
+        #[arg(long, default_value_t = DEFAULT_SIMILAR_TOP_K)]
+        top_k: i64,
+    },
+    /// Show totals and breakdowns of the ingested data
+    Stats,
+    /// Regenerate missing or placeholder edge embeddings
+    Reembed {
+        /// Only this session's edges
+        #[arg(long)]
+        session: Option<String>,
+    },
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // Load environment variables
     dotenvy::dotenv().ok();
 
-    // Get file path from command line args
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() < 2 {
-        eprintln!("Usage: {} <path-to-json-file>", args[0]);
-        eprintln!("Example: {} Data/ok.json", args[0]);
-        std::process::exit(1);
+    // Library logs go to stderr (filter with RUST_LOG); the report below goes to stdout
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "rust_ingester=info".into()),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    let cli = Cli::parse();
+    let command = match (cli.command, cli.file) {
+        (Some(command), _) => command,
+        (None, Some(file)) => Command::Ingest { file },
+        (None, None) => {
+            eprintln!("Usage: ingest_cli <path-to-json-file>  (or ingest_cli --help for subcommands)");
+            eprintln!("Example: ingest_cli Data/ok.json");
+            std::process::exit(1);
+        }
+    };
+
+    let cfg = Config::try_from_env()?;
+    cfg.validate()?;
+    let cfg = Config::init(cfg);
+
+    match command {
+        Command::Ingest { file } => run_ingest(cfg, &file, cli.json).await,
+        Command::Query { text, top_k } => run_query(cfg, &text, top_k, cli.json).await,
+        Command::Stats => run_stats(cli.json).await,
+        Command::Reembed { session } => run_reembed(cfg, session, cli.json).await,
+    }
+}
+
+async fn run_ingest(cfg: &'static Config, file_path: &str, json_output: bool) -> Result<()> {
+    // New embeddings hashed with a different bucket count won't be comparable to the stored ones
+    let client = db::connect::get_client().await?;
+    if let Some(stored) = db::vector::check_lsh_buckets(&client, cfg.lsh_buckets).await? {
+        eprintln!("🚨 LSH_BUCKETS mismatch: configured {} but stored embeddings were hashed with {}", cfg.lsh_buckets, stored);
+        eprintln!("   Run `cargo run --bin reindex` afterwards, or set LSH_BUCKETS={}", stored);
+    }
+    drop(client);
+
+    if !json_output {
+        println!("🚀 Starting ingestion from: {}", file_path);
+        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
     }
 
-    let file_path = &args[1];
-    
-    println!("🚀 Starting ingestion from: {}", file_path);
-    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-    
     let start = std::time::Instant::now();
-    
-    match ingest::ingest_from_file(file_path).await {
+
+    match ingest::ingest_from_file(cfg, file_path).await {
+        Ok(stats) if json_output => {
+            let report = serde_json::json!({
+                "file": file_path,
+                "sessions": stats.total_sessions,
+                "nodes": stats.total_nodes,
+                "edges": stats.total_edges,
+                "embeddings": stats.total_embeddings,
+                "edges_skipped": stats.total_edges_skipped,
+                "skipped_unchanged": stats.skipped_unchanged,
+                "duration_ms": stats.duration_ms,
+                "errors": stats.errors,
+            });
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            Ok(())
+        }
         Ok(stats) => {
             println!("\n✅ Ingestion completed successfully!");
             println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
@@ -30,25 +118,107 @@ async fn main() -> Result<()> {
             println!("   Total Nodes:      {}", stats.total_nodes);
             println!("   Total Edges:      {}", stats.total_edges);
             println!("   Total Embeddings: {}", stats.total_embeddings);
+            println!("   Edges Skipped:    {}", stats.total_edges_skipped);
+            println!("   Unchanged:        {}", stats.skipped_unchanged);
             println!("   Duration:         {} ms", stats.duration_ms);
-            
+
             if !stats.errors.is_empty() {
                 println!("\n⚠️  Errors encountered:");
                 for error in &stats.errors {
                     println!("   - {}", error);
                 }
             }
-            
+
             println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-            
+
             let elapsed = start.elapsed();
             println!("⏱️  Total time: {:.2}s", elapsed.as_secs_f64());
-            
+
             Ok(())
         }
         Err(e) => {
-            eprintln!("\n❌ Ingestion failed: {}", e);
+            eprintln!("\n❌ Ingestion failed: {:#}", e);
             std::process::exit(1);
         }
     }
 }
+
+async fn run_query(cfg: &Config, text: &str, top_k: i64, json_output: bool) -> Result<()> {
+    let (results, degraded) = query_similar_edges(
+        cfg, text, top_k, None, cfg.similarity_metric, None, None, None, &EdgeFilters::default(),
+    ).await?;
+
+    if json_output {
+        let report = serde_json::json!({ "count": results.len(), "results": results, "degraded": degraded });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("🔎 Top {} edges similar to \"{}\" ({})", top_k, text, cfg.similarity_metric.as_str());
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    if results.is_empty() {
+        println!("   No matches");
+    }
+    for (rank, r) in results.iter().enumerate() {
+        println!("   {:>2}. {:.4}  {} -[{}]-> {}  (session {})",
+            rank + 1, r.similarity, r.edge.source, r.edge.relation, r.edge.target, r.session_id);
+    }
+    if degraded {
+        println!("   ⚠️  LSH buckets were empty; results come from a fallback scan of FALLBACK_SCAN_LIMIT embeddings");
+    }
+    Ok(())
+}
+
+async fn run_stats(json_output: bool) -> Result<()> {
+    let client = db::connect::get_client().await?;
+    let sessions = db::sessions::count_sessions(&client).await?;
+    let mut stats = db::kg_ops::get_kg_statistics(&client).await?;
+
+    if json_output {
+        stats["total_sessions"] = sessions.into();
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+
+    println!("📊 Ingested data");
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    println!("   Sessions:       {}", sessions);
+    println!("   Conversations:  {}", stats["total_conversations"]);
+    println!("   Messages:       {}", stats["total_messages"]);
+    println!("   KG Nodes:       {}", stats["total_nodes"]);
+    println!("   KG Edges:       {}", stats["total_edges"]);
+    for (title, key) in [("Nodes by type", "nodes_by_type"), ("Edges by relation", "edges_by_relation"), ("Edges by evidence count", "evidence_count_distribution")] {
+        let Some(counts) = stats[key].as_object().filter(|c| !c.is_empty()) else { continue };
+        println!("\n   {}:", title);
+        for (name, count) in counts {
+            println!("     {:<28} {}", name, count);
+        }
+    }
+    Ok(())
+}
+
+async fn run_reembed(cfg: &Config, session: Option<String>, json_output: bool) -> Result<()> {
+    let scope = ReembedScope { session_id: session, conversation_id: None };
+    let response = ReembedResponse::from(ingest::reembed_missing(cfg, &scope).await?);
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&response)?);
+        return Ok(());
+    }
+
+    println!("🔁 Re-embedded edges{}", scope.session_id.map(|s| format!(" of session {}", s)).unwrap_or_default());
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    println!("   {:<20} {:>7} {:>11} {:>17} {:>7}", "table", "found", "reembedded", "still_placeholder", "failed");
+    for (table, counts) in [("embeddings", &response.embeddings), ("kg_edge_embeddings", &response.kg_edge_embeddings)] {
+        println!("   {:<20} {:>7} {:>11} {:>17} {:>7}",
+            table, counts.found, counts.reembedded, counts.still_placeholder, counts.failed);
+    }
+    println!("   Duration: {} ms", response.duration_ms);
+    if !response.errors.is_empty() {
+        println!("\n⚠️  Errors encountered:");
+        for error in &response.errors {
+            println!("   - {}", error);
+        }
+    }
+    Ok(())
+}
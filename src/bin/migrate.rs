@@ -0,0 +1,45 @@
+use rust_ingester::{config::Config, db};
+use anyhow::Result;
+
+/// Apply pending schema migrations and reconcile the configured graph, vector storage
+/// and ANN indexes. Safe to re-run: applied versions are skipped.
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Load environment variables
+    dotenvy::dotenv().ok();
+
+    // Library logs go to stderr (filter with RUST_LOG); the report below goes to stdout
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "rust_ingester=info".into()),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    let cfg = Config::try_from_env()?;
+    cfg.validate()?;
+    let cfg = Config::init(cfg);
+    let mut client = db::connect::connect(cfg).await?;
+
+    let start = std::time::Instant::now();
+    match db::migrate::run(&mut client, cfg).await {
+        Ok(report) => {
+            if report.applied.is_empty() {
+                println!("✅ Schema up to date ({} migrations already applied)", report.already_applied);
+            }
+            for (version, name) in &report.applied {
+                println!("✅ Applied migration {:03} {}", version, name);
+            }
+            if !report.age_available {
+                println!("⚠️  AGE extension not available - knowledge graph features will be limited");
+            }
+            println!("⏱️  Total time: {:.2}s", start.elapsed().as_secs_f64());
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("❌ Migration failed: {:#}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use rust_ingester::{config::Config, db};
+use anyhow::Result;
+
+/// Drop and recreate the message embedding index with the current VECTOR_INDEX settings,
+/// e.g. after a bulk load so ivfflat's lists are built from the real data
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Load environment variables
+    dotenvy::dotenv().ok();
+
+    // Library logs go to stderr (filter with RUST_LOG); the report below goes to stdout
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "rust_ingester=info".into()),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    let cfg = Config::try_from_env()?;
+    cfg.validate()?;
+    let cfg = Config::init(cfg);
+    let client = db::connect::get_client().await?;
+
+    println!("🔁 Rebuilding {} on message_embeddings: {:?}", cfg.vector_index.index_name(), cfg.vector_index);
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+
+    let start = std::time::Instant::now();
+    match db::vector_index::rebuild_message_embedding_index(&client, &cfg.vector_index, cfg.vector_storage).await {
+        Ok(()) => {
+            println!("✅ Index rebuilt");
+            println!("⏱️  Total time: {:.2}s", start.elapsed().as_secs_f64());
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("❌ Index rebuild failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use rust_ingester::{config::Config, db};
+use anyhow::Result;
+
+/// Re-hash all stored edge embeddings with the current LSH_BUCKETS and LSH_TABLES settings
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Load environment variables
+    dotenvy::dotenv().ok();
+
+    // Library logs go to stderr (filter with RUST_LOG); the report below goes to stdout
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "rust_ingester=info".into()),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    let cfg = Config::try_from_env()?;
+    cfg.validate()?;
+    let cfg = Config::init(cfg);
+    let mut client = db::connect::get_client().await?;
+
+    let previous = db::vector::get_metadata(&client, db::vector::LSH_BUCKETS_KEY).await?;
+    println!("🔁 Re-hashing embeddings into {} LSH buckets x {} tables (previously: {} buckets)",
+        cfg.lsh_buckets, cfg.lsh_tables, previous.as_deref().unwrap_or("unknown"));
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+
+    let start = std::time::Instant::now();
+    match db::vector::reindex_lsh_buckets(&mut client, cfg.lsh_buckets, cfg.lsh_tables).await {
+        Ok(updated) => {
+            println!("✅ Reindexed {} embeddings", updated);
+            println!("⏱️  Total time: {:.2}s", start.elapsed().as_secs_f64());
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("❌ Reindex failed (no rows were changed): {}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use rust_ingester::api::routes;
+use rust_ingester::{api::{jobs::JobStore, routes}, config::Config, db, etl::embed::{self, EmbedWarmup}};
 use std::net::SocketAddr;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
@@ -27,23 +27,147 @@ async fn main() {
     tracing::info!("🚀 RustIngester Service starting on {}", addr);
     tracing::info!("📊 Endpoints:");
     tracing::info!("   GET  /status");
+    tracing::info!("   GET  /health/live");
+    tracing::info!("   GET  /health/ready");
+    tracing::info!("   GET  /metrics");
     tracing::info!("   POST /ingest/session");
     tracing::info!("   POST /ingest/batch");
     tracing::info!("   POST /ingest/messages");
     tracing::info!("   POST /ingest/knowledge-graph");
+    tracing::info!("   GET  /ingest/jobs/:job_id");
     tracing::info!("   GET  /ingest/statistics");
+    tracing::info!("   GET  /ingest/quality");
+    tracing::info!("   GET  /ingest/sessions");
+    tracing::info!("   GET  /ingest/sessions/:session_id");
     tracing::info!("   POST /query/similar");
+    tracing::info!("   GET  /query/similar?q=...");
+    tracing::info!("   POST /query/embed");
     tracing::info!("   GET  /query/session/:session_id");
     tracing::info!("   POST /query/llm-context");
     tracing::info!("   POST /query/messages");
+    tracing::info!("   POST /query/messages/search");
+    tracing::info!("   GET  /query/conversation/:conversation_id");
     tracing::info!("   POST /graph/cypher");
+    tracing::info!("   GET  /graph/node/:pk");
+    tracing::info!("   PATCH /graph/node/:pk");
+    tracing::info!("   GET  /graph/path?from=...&to=...");
+    tracing::info!("   GET  /graph/schema");
+    tracing::info!("   GET  /graph/export");
+    tracing::info!("   POST /maintenance/reembed");
+    tracing::info!("   GET  /debug/lsh-distribution");
+
+    // Refuse to start on a bad configuration rather than failing on the first request
+    let cfg = match Config::try_from_env().and_then(|cfg| cfg.validate().map(|_| cfg)) {
+        Ok(cfg) => Config::init(cfg),
+        Err(e) => {
+            tracing::error!(error = %e, "invalid configuration");
+            std::process::exit(1);
+        }
+    };
+
+    // Apply pending schema migrations once, rather than on every connection
+    match db::connect::connect(cfg).await {
+        Ok(mut client) => match db::migrate::run(&mut client, cfg).await {
+            Ok(report) => {
+                for (version, name) in &report.applied {
+                    tracing::info!("🗄️  Applied migration {:03} {}", version, name);
+                }
+            }
+            Err(e) => tracing::error!("❌ Schema migration failed: {:#} (retry with `cargo run --bin migrate`)", e),
+        },
+        Err(e) => tracing::warn!("⚠️  Could not run schema migrations (database unavailable): {}", e),
+    }
+
+    // Warn loudly if LSH_BUCKETS no longer matches the stored embeddings
+    match db::connect::get_client().await {
+        Ok(client) => match db::vector::check_lsh_buckets(&client, cfg.lsh_buckets).await {
+            Ok(Some(stored)) => {
+                tracing::warn!("🚨 LSH_BUCKETS mismatch: configured {} but stored embeddings were hashed with {}", cfg.lsh_buckets, stored);
+                tracing::warn!("   Similarity lookups will miss existing data. Run `cargo run --bin reindex` to re-hash.");
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("⚠️  Could not check LSH bucket config: {}", e),
+        },
+        Err(e) => tracing::warn!("⚠️  Could not check LSH bucket config (database unavailable): {}", e),
+    }
+
+    // Embed a probe string now, so a missing server or a wrong dimension shows up at boot
+    // rather than at the first insert
+    if cfg.embed_warmup != EmbedWarmup::Off {
+        let column_dim = match db::connect::get_client().await {
+            Ok(client) => db::vector::vector_column_dim(&client, "message_embeddings", "embedding").await
+                .unwrap_or_else(|e| {
+                    tracing::warn!("⚠️  Could not read the message_embeddings dimension: {}", e);
+                    None
+                }),
+            Err(_) => None,
+        };
+        match embed::warmup(cfg, column_dim).await {
+            Ok(report) => {
+                tracing::info!(
+                    dim = report.dim,
+                    provider = report.provider.as_str(),
+                    duration_ms = report.duration_ms,
+                    "🔥 Embedding warmup done"
+                );
+                for problem in &report.problems {
+                    tracing::warn!("🚨 Embedding warmup: {}", problem);
+                }
+                if cfg.embed_warmup == EmbedWarmup::Strict && !report.problems.is_empty() {
+                    tracing::error!("refusing to start (EMBED_WARMUP=strict); set EMBED_WARMUP=warn to start anyway");
+                    std::process::exit(1);
+                }
+            }
+            Err(e) => {
+                tracing::warn!("⚠️  Embedding warmup failed: {}", e);
+                if cfg.embed_warmup == EmbedWarmup::Strict {
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
 
     // Create router
-    let app = routes::create_router();
+    let jobs = JobStore::new();
+    let app = routes::create_router(jobs.clone());
 
     // Run server
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
     tracing::info!("✅ Server listening on {}", addr);
     
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+        .unwrap();
+
+    // Batch and knowledge-graph ingests run on background tasks the server doesn't wait for
+    jobs.drain().await;
+
+    tracing::info!("👋 Server shut down cleanly");
+}
+
+/// Resolve on Ctrl-C or SIGTERM so in-flight requests can drain before exit
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl-C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("🛑 Received Ctrl-C, starting graceful shutdown"),
+        _ = terminate => tracing::info!("🛑 Received SIGTERM, starting graceful shutdown"),
+    }
+    tracing::info!("⏳ No longer accepting connections, draining in-flight requests");
 }
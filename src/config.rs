@@ -1,31 +1,635 @@
+use std::collections::{HashMap, HashSet};
 use std::env;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::db::kg_ops::{self, FieldScoring, NodeTypeConflict};
+use crate::db::vector_index::{self, VectorIndex, VectorStorage};
+use crate::etl::embed::{EmbedOverflow, EmbedWarmup, PlaceholderKind};
+use crate::etl::roles::{self, RoleScheme};
+use crate::etl::stop_words;
+use crate::etl::similarity::{DimensionMismatchMode, SimilarityMetric};
+use crate::ingest::MissingNodeMode;
+
+/// TLS mode for database connections (mirrors libpq's `sslmode` names)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DbSslMode {
+    /// Plain TCP (local dev)
+    #[default]
+    Disable,
+    /// Encrypt, but don't verify the server certificate
+    Require,
+    /// Encrypt and verify the certificate chain and hostname
+    VerifyFull,
+}
+
+impl DbSslMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "disable" | "false" | "0" => Some(DbSslMode::Disable),
+            "require" | "true" | "1" => Some(DbSslMode::Require),
+            "verify-full" => Some(DbSslMode::VerifyFull),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DbSslMode::Disable => "disable",
+            DbSslMode::Require => "require",
+            DbSslMode::VerifyFull => "verify-full",
+        }
+    }
+}
+
+/// Embedding model recorded when neither `EMBED_MODEL_NAME` nor `EMBED_MODEL_PATH` is set
+/// (matches the `embedding_model` column default)
+pub const DEFAULT_EMBED_MODEL: &str = "nomic-embed-text-v1.5";
+
+/// Request body limit when `MAX_BODY_BYTES` is unset (500MB, for large ingestion)
+pub const DEFAULT_MAX_BODY_BYTES: usize = 500 * 1024 * 1024;
+
+/// Rows accepted per ingest request when `MAX_INGEST_ROWS` is unset
+pub const DEFAULT_MAX_INGEST_ROWS: usize = 100_000;
+
+/// Rows scanned when a query's LSH buckets are empty and `FALLBACK_SCAN_LIMIT` is unset
+pub const DEFAULT_FALLBACK_SCAN_LIMIT: usize = 1000;
+
+/// AGE graph used when `GRAPH_NAME` is unset
+pub const DEFAULT_GRAPH_NAME: &str = "sem_graph";
+
+/// `MULTI_FIELD_WEIGHTS`: comma-separated source, relation and target weights,
+/// non-negative and not all zero
+pub fn parse_field_weights(s: &str) -> Option<[f32; 3]> {
+    let weights: Vec<f32> = s.split(',').map(|w| w.trim().parse::<f32>().ok()).collect::<Option<_>>()?;
+    let weights: [f32; 3] = weights.try_into().ok()?;
+    (weights.iter().all(|w| w.is_finite() && *w >= 0.0) && weights.iter().sum::<f32>() > 0.0).then_some(weights)
+}
+
+/// Whether `name` is usable as an AGE graph name: an identifier of ASCII
+/// letters, digits and underscores not starting with a digit
+pub fn is_valid_graph_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Embedding dimension when `EMBED_DIM` is unset (nomic-embed-text-v1.5, matches `vector(768)`)
+pub const DEFAULT_EMBED_DIM: usize = 768;
+
+/// Embedding server request timeout when `EMBED_TIMEOUT_SECS` is unset
+pub const DEFAULT_EMBED_TIMEOUT_SECS: u64 = 30;
+
+/// Embedding server calls in flight at once when `MAX_CONCURRENT_EMBEDS` is unset
+pub const DEFAULT_MAX_CONCURRENT_EMBEDS: usize = 4;
+
+/// Embedding calls allowed to wait for a slot when `EMBED_QUEUE_LIMIT` is unset
+pub const DEFAULT_EMBED_QUEUE_LIMIT: usize = 64;
+
+/// Seconds a cached `/query/llm-context` response is served when `QUERY_CACHE_TTL_SECS` is unset
+pub const DEFAULT_QUERY_CACHE_TTL_SECS: u64 = 60;
+
+/// Longest path `/graph/path` searches for when `MAX_PATH_DEPTH` is unset
+pub const DEFAULT_MAX_PATH_DEPTH: usize = 6;
+
+/// Age at which `order_by: "mixed"` halves a message's relevance, when `RECENCY_HALF_LIFE_HOURS` is unset
+pub const DEFAULT_RECENCY_HALF_LIFE_HOURS: f64 = 168.0;
+
+/// Environment variable naming a TOML config file read by `Config::from_env`
+pub const CONFIG_FILE_VAR: &str = "CONFIG_FILE";
+
+/// Settings accepted in a config file (lowercase) or the environment (as written)
+pub const CONFIG_KEYS: &[&str] = &[
+    "DATABASE_URL", "DB_SSLMODE", "DB_TLS", "DB_SSL_ROOT_CERT", "LSH_BUCKETS", "LSH_TABLES",
+    "FALLBACK_SCAN_LIMIT", "EMBED_MODEL_PATH", "EMBED_SERVER_URL", "EMBED_MODEL_NAME", "EMBED_DIM",
+    "EMBED_TIMEOUT_SECS", "EMBED_CONCURRENCY", "MAX_CONCURRENT_EMBEDS", "EMBED_QUEUE_LIMIT",
+    "API_KEY", "CORS_ALLOWED_ORIGINS", "CYPHER_ALLOW_WRITES", "SIMILARITY_METRIC", "GRAPH_NAME",
+    "MAX_BODY_BYTES", "MAX_INGEST_ROWS", "MESSAGE_FETCH_CHUNK_SIZE", "ON_DIMENSION_MISMATCH",
+    "ROLE_PREFIXES", "ROLE_SEPARATORS", "DEFAULT_ROLE", "VECTOR_INDEX", "IVFFLAT_LISTS", "HNSW_M",
+    "HNSW_EF_CONSTRUCTION", "MIN_CONTENT_LENGTH", "NODE_TYPE_CONFLICT",
+    "MISSING_NODE_MODE", "STOP_WORDS_FILE", "MAX_FANOUT_PER_NODE", "HUB_DEGREE_THRESHOLD",
+    "EMBED_WARMUP", "QUERY_CACHE_SIZE", "QUERY_CACHE_TTL_SECS",
+    "PLACEHOLDER_EMBEDDINGS", "MAX_PATH_DEPTH", "VECTOR_STORAGE", "MAX_EMBED_CHARS", "EMBED_OVERFLOW", "SESSION_CONCURRENCY", "MULTI_FIELD_EMBED", "MULTI_FIELD_WEIGHTS",
+    "RECENCY_HALF_LIFE_HOURS",
+];
+
+/// Why a configuration could not be loaded or is unusable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// `DATABASE_URL` is unset or empty
+    MissingDatabaseUrl,
+    /// `DATABASE_URL` is not a valid connection string
+    InvalidDatabaseUrl(String),
+    /// A setting has a value outside what it accepts
+    InvalidValue { key: &'static str, value: String, expected: &'static str },
+    /// The config file could not be read or parsed
+    File { path: String, message: String },
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::MissingDatabaseUrl => write!(f, "DATABASE_URL not set"),
+            ConfigError::InvalidDatabaseUrl(e) => write!(f, "Invalid DATABASE_URL: {}", e),
+            ConfigError::InvalidValue { key, value, expected } => {
+                write!(f, "Invalid {} '{}' (expected {})", key, value, expected)
+            }
+            ConfigError::File { path, message } => write!(f, "Config file {}: {}", path, message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Where settings are read from: the environment first, then the config file
+#[derive(Debug, Default)]
+struct ConfigSource {
+    file: HashMap<String, String>,
+}
+
+impl ConfigSource {
+    /// Read a TOML file of `key = value` settings named like the environment
+    /// variables in lowercase (`lsh_buckets = 8`). Arrays are joined with commas.
+    fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let file_err = |message: String| ConfigError::File { path: path.display().to_string(), message };
+        let text = std::fs::read_to_string(path).map_err(|e| file_err(e.to_string()))?;
+        let table: toml::Table = text.parse().map_err(|e: toml::de::Error| file_err(e.message().to_string()))?;
+
+        let mut file = HashMap::new();
+        for (key, value) in table {
+            let upper = key.to_uppercase();
+            if !CONFIG_KEYS.contains(&upper.as_str()) {
+                return Err(file_err(format!("unknown setting '{}'", key)));
+            }
+            let value = match value {
+                toml::Value::String(s) => s,
+                toml::Value::Array(items) => items
+                    .iter()
+                    .map(|v| v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string()))
+                    .collect::<Vec<_>>()
+                    .join(","),
+                toml::Value::Table(_) => return Err(file_err(format!("'{}' must not be a table", key))),
+                other => other.to_string(),
+            };
+            file.insert(upper, value);
+        }
+        Ok(Self { file })
+    }
+
+    fn var(&self, key: &str) -> Option<String> {
+        env::var(key).ok().or_else(|| self.file.get(key).cloned())
+    }
+
+    /// `key` parsed as a `T`, or `None` if unset. A value that doesn't parse is an
+    /// error rather than the default.
+    fn parse<T: std::str::FromStr>(&self, key: &'static str, expected: &'static str) -> Result<Option<T>, ConfigError> {
+        self.var(key)
+            .map(|s| s.trim().parse().map_err(|_| ConfigError::InvalidValue { key, value: s, expected }))
+            .transpose()
+    }
+}
+
+/// Process-wide configuration, installed by `Config::init` or loaded on first use
+static GLOBAL: OnceLock<Config> = OnceLock::new();
 
 #[derive(Clone)]
 pub struct Config {
     pub db_url: String,
+    pub db_sslmode: DbSslMode,
+    pub db_ssl_root_cert: Option<String>,
     pub lsh_buckets: usize,
+    pub lsh_tables: usize,
+    pub fallback_scan_limit: usize,
     pub embed_model_path: Option<String>,
     pub embed_server_url: Option<String>,
+    pub embed_model_name: String,
+    pub embed_dim: usize,
+    pub embed_timeout_secs: u64,
+    pub embed_concurrency: usize,
+    /// Sessions of a knowledge graph payload ingested at once, each on its own connection
+    /// (not a pool) and writing in one transaction. The transactions hold the per-graph
+    /// advisory lock until commit, so only embedding overlaps.
+    pub session_concurrency: usize,
+    pub max_concurrent_embeds: usize,
+    pub embed_queue_limit: usize,
+    pub api_key: Option<String>,
+    pub cors_allowed_origins: Vec<String>,
+    pub cypher_allow_writes: bool,
+    pub similarity_metric: SimilarityMetric,
+    pub graph_name: String,
+    pub max_body_bytes: usize,
+    pub max_ingest_rows: usize,
+    pub message_fetch_chunk_size: usize,
+    pub on_dimension_mismatch: DimensionMismatchMode,
+    pub role_scheme: RoleScheme,
+    pub vector_index: VectorIndex,
+    /// Element type of the `message_embeddings` and `kg_edge_embeddings` vectors
+    pub vector_storage: VectorStorage,
+    pub min_content_length: usize,
+    pub node_type_conflict: NodeTypeConflict,
+    pub missing_node_mode: MissingNodeMode,
+    /// Lowercased words dropped from queries before keyword search
+    pub stop_words: HashSet<String>,
+    /// Neighbor edges a graph traversal expands per node, most similar first (0 = no cap)
+    pub max_fanout_per_node: usize,
+    /// Nodes with more edges than this aren't expanded by graph traversal (0 = no limit)
+    pub hub_degree_threshold: usize,
+    /// Startup probe of the embedding server (`bin/service`)
+    pub embed_warmup: EmbedWarmup,
+    /// `/query/llm-context` responses kept in the query cache (0 = off)
+    pub query_cache_size: usize,
+    pub query_cache_ttl_secs: u64,
+    /// Placeholder vectors stored when the embedding server is unset or down
+    pub placeholder_embeddings: PlaceholderKind,
+    /// Longest path `/graph/path` searches for
+    pub max_path_depth: usize,
+    /// Longest text sent to the embedding server in one request, in characters (0 = no limit)
+    pub max_embed_chars: usize,
+    /// What longer texts are shortened to
+    pub embed_overflow: EmbedOverflow,
+    /// Also embed KG edges' source, relation and target, and rank edges with them (None = off)
+    pub multi_field_embed: Option<FieldScoring>,
+    /// Age difference over which `order_by: "mixed"` halves a message's relevance
+    pub recency_half_life_hours: f64,
 }
 
 impl Config {
+    /// Load from the environment (and `.env`), plus the TOML file named by
+    /// `CONFIG_FILE` if set. Panics on an invalid setting; use `try_from_env`
+    /// and `validate` at startup to report errors instead.
     pub fn from_env() -> Self {
+        Self::try_from_env().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Install `cfg` as the process-wide configuration returned by `Config::global`.
+    /// Call once at startup, after `validate`; a later call keeps the first config.
+    pub fn init(cfg: Config) -> &'static Config {
+        GLOBAL.get_or_init(|| cfg)
+    }
+
+    /// The process-wide configuration, loaded with `from_env` on first use if
+    /// `init` was never called (CLI entry points and tests). Panics on an invalid
+    /// setting, like `from_env`.
+    pub fn global() -> &'static Config {
+        Self::try_global().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Same as `global`, returning an error if the lazy load fails
+    pub fn try_global() -> Result<&'static Config, ConfigError> {
+        if let Some(cfg) = GLOBAL.get() {
+            return Ok(cfg);
+        }
+        let cfg = Self::try_from_env()?;
+        Ok(GLOBAL.get_or_init(|| cfg))
+    }
+
+    /// Same as `from_env`, returning an error for an invalid setting or config file
+    pub fn try_from_env() -> Result<Self, ConfigError> {
+        dotenvy::dotenv().ok();
+        match env::var(CONFIG_FILE_VAR).ok().filter(|p| !p.is_empty()) {
+            Some(path) => Self::load(ConfigSource::from_file(Path::new(&path))?),
+            None => Self::load(ConfigSource::default()),
+        }
+    }
+
+    /// Load settings from a TOML file; environment variables override file values
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
         dotenvy::dotenv().ok();
-        let db_url = env::var("DATABASE_URL").expect("DATABASE_URL not set");
-        let lsh_buckets = env::var("LSH_BUCKETS")
-            .ok()
+        Self::load(ConfigSource::from_file(path.as_ref())?)
+    }
+
+    /// Check the settings are usable: the database URL parses, and the LSH and
+    /// embedding sizes are positive
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.db_url.is_empty() {
+            return Err(ConfigError::MissingDatabaseUrl);
+        }
+        self.db_url
+            .parse::<tokio_postgres::Config>()
+            .map_err(|e| ConfigError::InvalidDatabaseUrl(e.to_string()))?;
+        let positive = |key: &'static str, value: usize| {
+            if value == 0 {
+                Err(ConfigError::InvalidValue { key, value: value.to_string(), expected: "a positive integer" })
+            } else {
+                Ok(())
+            }
+        };
+        positive("LSH_BUCKETS", self.lsh_buckets)?;
+        positive("LSH_TABLES", self.lsh_tables)?;
+        positive("EMBED_DIM", self.embed_dim)?;
+        Ok(())
+    }
+
+    fn load(src: ConfigSource) -> Result<Self, ConfigError> {
+        // Missing is reported by `validate`, so per-request loads don't panic
+        let db_url = src.var("DATABASE_URL").unwrap_or_default();
+        // DB_TLS is accepted as an alias; an unknown mode is an error rather than silently plaintext
+        let db_sslmode = src.var("DB_SSLMODE")
+            .or_else(|| src.var("DB_TLS"))
+            .map(|s| DbSslMode::parse(&s).ok_or(ConfigError::InvalidValue {
+                key: "DB_SSLMODE",
+                value: s,
+                expected: "disable, require or verify-full",
+            }))
+            .transpose()?
+            .unwrap_or_default();
+        let db_ssl_root_cert = src.var("DB_SSL_ROOT_CERT").filter(|p| !p.is_empty());
+        // Zero buckets would make every hash a division by zero; kept so `validate` rejects it
+        let lsh_buckets = src.parse("LSH_BUCKETS", "a positive integer")?.unwrap_or(128);
+        // Independent LSH hash tables; more tables trade storage for recall
+        let lsh_tables = src.parse("LSH_TABLES", "a positive integer")?.unwrap_or(1);
+        let embed_model_path = src.var("EMBED_MODEL_PATH");
+        let embed_server_url = src.var("EMBED_SERVER_URL");
+        // Recorded on every stored vector; defaults to the GGUF file name without extension
+        let embed_model_name = src.var("EMBED_MODEL_NAME")
+            .filter(|m| !m.is_empty())
+            .or_else(|| {
+                embed_model_path.as_deref()
+                    .and_then(|p| std::path::Path::new(p).file_stem())
+                    .map(|s| s.to_string_lossy().into_owned())
+            })
+            .unwrap_or_else(|| DEFAULT_EMBED_MODEL.to_string());
+        let embed_dim = src.parse("EMBED_DIM", "a positive integer")?.unwrap_or(DEFAULT_EMBED_DIM);
+        // Slow CPU-only llama.cpp servers may need longer than the default
+        let embed_timeout_secs = src.var("EMBED_TIMEOUT_SECS")
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|&t| t > 0)
+            .unwrap_or(DEFAULT_EMBED_TIMEOUT_SECS);
+        // Parallel embedding requests per session ingest; 1 keeps requests sequential
+        let embed_concurrency = src.var("EMBED_CONCURRENCY")
+            .and_then(|s| s.parse::<usize>().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(1);
+        // Sessions ingested in parallel per payload; 1 keeps them sequential
+        let session_concurrency = src.var("SESSION_CONCURRENCY")
+            .and_then(|s| s.parse::<usize>().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(1);
+        // Process-wide cap on embedding server calls (queries and ingests together); more wait
+        let max_concurrent_embeds = src.var("MAX_CONCURRENT_EMBEDS")
+            .and_then(|s| s.parse::<usize>().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(DEFAULT_MAX_CONCURRENT_EMBEDS);
+        // Calls beyond this many waiting are rejected (429) instead of piling up
+        let embed_queue_limit = src.var("EMBED_QUEUE_LIMIT")
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_EMBED_QUEUE_LIMIT);
+        // Auth is opt-in: an unset or empty API_KEY leaves the API open for local dev
+        let api_key = src.var("API_KEY").filter(|k| !k.is_empty());
+        let cors_allowed_origins = src.var("CORS_ALLOWED_ORIGINS")
+            .map(|s| {
+                s.split(',')
+                    .map(|o| o.trim().to_string())
+                    .filter(|o| !o.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let cypher_allow_writes = src.var("CYPHER_ALLOW_WRITES")
+            .map(|s| s.eq_ignore_ascii_case("true") || s == "1")
+            .unwrap_or(false);
+        let similarity_metric = src.var("SIMILARITY_METRIC")
+            .map(|s| SimilarityMetric::parse(&s).ok_or(ConfigError::InvalidValue {
+                key: "SIMILARITY_METRIC",
+                value: s,
+                expected: "cosine, dot or euclidean",
+            }))
+            .transpose()?
+            .unwrap_or_default();
+        // Interpolated into cypher() calls (the graph name can't be a bind parameter there)
+        let graph_name = src.var("GRAPH_NAME")
+            .filter(|g| !g.is_empty())
+            .map(|g| {
+                if is_valid_graph_name(&g) {
+                    Ok(g)
+                } else {
+                    Err(ConfigError::InvalidValue { key: "GRAPH_NAME", value: g, expected: "letters, digits and underscores" })
+                }
+            })
+            .transpose()?
+            .unwrap_or_else(|| DEFAULT_GRAPH_NAME.to_string());
+        // Request size guards; zero would reject everything, so it falls back to the default
+        let max_body_bytes = src.var("MAX_BODY_BYTES")
+            .and_then(|s| s.parse::<usize>().ok())
+            .filter(|&b| b > 0)
+            .unwrap_or(DEFAULT_MAX_BODY_BYTES);
+        let max_ingest_rows = src.var("MAX_INGEST_ROWS")
+            .and_then(|s| s.parse::<usize>().ok())
+            .filter(|&r| r > 0)
+            .unwrap_or(DEFAULT_MAX_INGEST_ROWS);
+        // Cap on the degraded full-table scan when the LSH lookup finds nothing
+        let fallback_scan_limit = src.var("FALLBACK_SCAN_LIMIT")
+            .and_then(|s| s.parse::<usize>().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(DEFAULT_FALLBACK_SCAN_LIMIT);
+        // Message ids per query when fetching evidence; keeps huge id lists under parameter limits
+        let message_fetch_chunk_size = src.var("MESSAGE_FETCH_CHUNK_SIZE")
+            .and_then(|s| s.parse::<usize>().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(crate::db::message_ops::DEFAULT_MESSAGE_FETCH_CHUNK_SIZE);
+        // Stored vectors of another length (e.g. placeholders from a different model) can't be scored
+        let on_dimension_mismatch = src.var("ON_DIMENSION_MISMATCH")
+            .map(|s| DimensionMismatchMode::parse(&s).ok_or(ConfigError::InvalidValue {
+                key: "ON_DIMENSION_MISMATCH",
+                value: s,
+                expected: "skip or error",
+            }))
+            .transpose()?
+            .unwrap_or_default();
+        // How stored message content marks its speaker (`user: ...`, `AI> ...`)
+        let role_prefixes = src.var("ROLE_PREFIXES")
+            .map(|s| roles::parse_role_prefixes(&s).filter(|p| !p.is_empty()).ok_or(ConfigError::InvalidValue {
+                key: "ROLE_PREFIXES",
+                value: s,
+                expected: "comma-separated prefix=role pairs",
+            }))
+            .transpose()?;
+        let role_separators = src.var("ROLE_SEPARATORS")
+            .map(|s| roles::parse_role_separators(&s))
+            .filter(|s| !s.is_empty());
+        let default_role = src.var("DEFAULT_ROLE")
+            .map(|r| r.trim().to_lowercase())
+            .filter(|r| !r.is_empty());
+        let defaults = RoleScheme::default();
+        let role_scheme = RoleScheme {
+            prefixes: role_prefixes.unwrap_or(defaults.prefixes),
+            separators: role_separators.unwrap_or(defaults.separators),
+            default_role: default_role.unwrap_or(defaults.default_role),
+        };
+        // ANN index on message embeddings; ivfflat lists should grow with the row count
+        let positive = |key: &str, default: u32| {
+            src.var(key).and_then(|s| s.parse::<u32>().ok()).filter(|&n| n > 0).unwrap_or(default)
+        };
+        let vector_index = match src.var("VECTOR_INDEX") {
+            None => VectorIndex::IvfFlat { lists: positive("IVFFLAT_LISTS", vector_index::DEFAULT_IVFFLAT_LISTS) },
+            Some(s) => match s.to_lowercase().as_str() {
+                "ivfflat" => VectorIndex::IvfFlat { lists: positive("IVFFLAT_LISTS", vector_index::DEFAULT_IVFFLAT_LISTS) },
+                "hnsw" => VectorIndex::Hnsw {
+                    m: positive("HNSW_M", vector_index::DEFAULT_HNSW_M),
+                    ef_construction: positive("HNSW_EF_CONSTRUCTION", vector_index::DEFAULT_HNSW_EF_CONSTRUCTION),
+                },
+                _ => return Err(ConfigError::InvalidValue { key: "VECTOR_INDEX", value: s, expected: "ivfflat or hnsw" }),
+            },
+        };
+        // halfvec halves vector storage; existing columns are converted by db::migrate
+        let vector_storage = src.var("VECTOR_STORAGE")
+            .map(|s| VectorStorage::parse(&s).ok_or(ConfigError::InvalidValue {
+                key: "VECTOR_STORAGE",
+                value: s,
+                expected: "f32 or f16",
+            }))
+            .transpose()?
+            .unwrap_or_default();
+        // Shorter messages are left out of LLM context unless a KG edge cites them; 0 keeps all
+        let min_content_length = src.var("MIN_CONTENT_LENGTH")
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(0);
+        // What a KG re-ingest does to a node whose extracted type changed
+        let node_type_conflict = src.var("NODE_TYPE_CONFLICT")
+            .map(|s| NodeTypeConflict::parse(&s).ok_or(ConfigError::InvalidValue {
+                key: "NODE_TYPE_CONFLICT",
+                value: s,
+                expected: "overwrite, overwrite_and_log, keep_existing or error",
+            }))
+            .transpose()?
+            .unwrap_or_default();
+        // What a session ingest does with an edge whose node isn't in the session
+        let missing_node_mode = src.var("MISSING_NODE_MODE")
+            .map(|s| MissingNodeMode::parse(&s).ok_or(ConfigError::InvalidValue {
+                key: "MISSING_NODE_MODE",
+                value: s,
+                expected: "strict or lenient",
+            }))
+            .transpose()?
+            .unwrap_or_default();
+        // Read once here so keyword extraction is a set lookup per query word
+        let stop_words = match src.var("STOP_WORDS_FILE").filter(|p| !p.is_empty()) {
+            Some(path) => stop_words::load_stop_words(Path::new(&path)).map_err(|e| ConfigError::InvalidValue {
+                key: "STOP_WORDS_FILE",
+                value: format!("{}: {}", path, e),
+                expected: "a readable file with one stop word per line",
+            })?,
+            None => stop_words::default_stop_words(),
+        };
+        // Caps on graph traversal so a hub node doesn't pull in its whole neighborhood
+        let max_fanout_per_node = src.var("MAX_FANOUT_PER_NODE")
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(kg_ops::DEFAULT_MAX_FANOUT_PER_NODE);
+        let hub_degree_threshold = src.var("HUB_DEGREE_THRESHOLD")
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(kg_ops::DEFAULT_HUB_DEGREE_THRESHOLD);
+        // Probe the embedding server at startup; `off` for offline and test environments
+        let embed_warmup = src.var("EMBED_WARMUP")
+            .map(|s| EmbedWarmup::parse(&s).ok_or(ConfigError::InvalidValue {
+                key: "EMBED_WARMUP",
+                value: s,
+                expected: "off, warn or strict",
+            }))
+            .transpose()?
+            .unwrap_or_default();
+        // Repeated identical context queries are answered from memory; off unless sized
+        let query_cache_size = src.var("QUERY_CACHE_SIZE")
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(0);
+        let query_cache_ttl_secs = src.var("QUERY_CACHE_TTL_SECS")
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_QUERY_CACHE_TTL_SECS);
+        // Text-dependent placeholders are opt-in: they look like real vectors to similarity search
+        let placeholder_embeddings = src.var("PLACEHOLDER_EMBEDDINGS")
+            .map(|s| PlaceholderKind::parse(&s).ok_or(ConfigError::InvalidValue {
+                key: "PLACEHOLDER_EMBEDDINGS",
+                value: s,
+                expected: "constant or hashed",
+            }))
+            .transpose()?
+            .unwrap_or_default();
+        // Each extra hop of a path search can multiply the paths AGE expands
+        let max_path_depth = src.var("MAX_PATH_DEPTH")
+            .and_then(|s| s.parse::<usize>().ok())
+            .filter(|&d| d > 0)
+            .unwrap_or(DEFAULT_MAX_PATH_DEPTH);
+        // Texts past the model's context window are rejected or cut short by llama.cpp
+        let max_embed_chars = src.var("MAX_EMBED_CHARS")
             .and_then(|s| s.parse::<usize>().ok())
-            .unwrap_or(128);
-        let embed_model_path = env::var("EMBED_MODEL_PATH").ok();
-        let embed_server_url = env::var("EMBED_SERVER_URL").ok();
+            .unwrap_or(0);
+        let embed_overflow = src.var("EMBED_OVERFLOW")
+            .map(|s| EmbedOverflow::parse(&s).ok_or(ConfigError::InvalidValue {
+                key: "EMBED_OVERFLOW",
+                value: s,
+                expected: "truncate or chunk",
+            }))
+            .transpose()?
+            .unwrap_or_default();
+        // Three extra embedding requests per KG edge, so off unless asked for
+        let multi_field_weights = src.var("MULTI_FIELD_WEIGHTS")
+            .map(|s| parse_field_weights(&s).ok_or(ConfigError::InvalidValue {
+                key: "MULTI_FIELD_WEIGHTS",
+                value: s,
+                expected: "three non-negative numbers for source,relation,target, e.g. 1,0.5,1",
+            }))
+            .transpose()?
+            .unwrap_or([1.0; 3]);
+        let multi_field_embed = match src.var("MULTI_FIELD_EMBED") {
+            Some(s) if matches!(s.to_lowercase().as_str(), "off" | "false" | "0") => None,
+            Some(s) => Some(FieldScoring::parse(&s, multi_field_weights).ok_or(ConfigError::InvalidValue {
+                key: "MULTI_FIELD_EMBED",
+                value: s,
+                expected: "off, max or weighted",
+            })?),
+            None => None,
+        };
+        // Positive, or the decay would be undefined
+        let recency_half_life_hours = src.var("RECENCY_HALF_LIFE_HOURS")
+            .and_then(|s| s.parse::<f64>().ok())
+            .filter(|h| h.is_finite() && *h > 0.0)
+            .unwrap_or(DEFAULT_RECENCY_HALF_LIFE_HOURS);
         
-        // Log configuration on startup
-        eprintln!("📋 Configuration loaded:");
-        eprintln!("   DATABASE_URL: {}", if db_url.is_empty() { "NOT SET" } else { "SET" });
-        eprintln!("   LSH_BUCKETS: {}", lsh_buckets);
-        eprintln!("   EMBED_MODEL_PATH: {}", embed_model_path.as_deref().unwrap_or("NOT SET"));
-        eprintln!("   EMBED_SERVER_URL: {}", embed_server_url.as_deref().unwrap_or("NOT SET"));
+        // Logged once per load; the service and CLIs load once at startup
+        tracing::debug!(
+            database_url = if db_url.is_empty() { "NOT SET" } else { "SET" },
+            db_sslmode = db_sslmode.as_str(),
+            lsh_buckets,
+            lsh_tables,
+            fallback_scan_limit,
+            embed_model_path = embed_model_path.as_deref().unwrap_or("NOT SET"),
+            embed_server_url = embed_server_url.as_deref().unwrap_or("NOT SET"),
+            embed_model_name = %embed_model_name,
+            embed_dim,
+            embed_timeout_secs,
+            embed_concurrency,
+            session_concurrency,
+            max_concurrent_embeds,
+            embed_queue_limit,
+            api_key = if api_key.is_some() { "SET" } else { "NOT SET" },
+            cypher_allow_writes,
+            similarity_metric = similarity_metric.as_str(),
+            graph_name = %graph_name,
+            max_body_bytes,
+            max_ingest_rows,
+            message_fetch_chunk_size,
+            on_dimension_mismatch = on_dimension_mismatch.as_str(),
+            role_prefixes = role_scheme.prefixes.len(),
+            role_separators = ?role_scheme.separators,
+            default_role = %role_scheme.default_role,
+            vector_index = ?vector_index,
+            vector_storage = vector_storage.as_str(),
+            min_content_length,
+            node_type_conflict = node_type_conflict.as_str(),
+            missing_node_mode = missing_node_mode.as_str(),
+            stop_words = stop_words.len(),
+            max_fanout_per_node,
+            hub_degree_threshold,
+            embed_warmup = embed_warmup.as_str(),
+            query_cache_size,
+            query_cache_ttl_secs,
+            placeholder_embeddings = placeholder_embeddings.as_str(),
+            max_path_depth,
+            max_embed_chars,
+            embed_overflow = embed_overflow.as_str(),
+            multi_field_embed = multi_field_embed.as_ref().map_or("off", FieldScoring::as_str),
+            recency_half_life_hours,
+            "📋 Configuration loaded"
+        );
         
-        Self { db_url, lsh_buckets, embed_model_path, embed_server_url }
+        Ok(Self { db_url, db_sslmode, db_ssl_root_cert, lsh_buckets, lsh_tables, fallback_scan_limit, embed_model_path, embed_server_url, embed_model_name, embed_dim, embed_timeout_secs, embed_concurrency, session_concurrency, max_concurrent_embeds, embed_queue_limit, api_key, cors_allowed_origins, cypher_allow_writes, similarity_metric, graph_name, max_body_bytes, max_ingest_rows, message_fetch_chunk_size, on_dimension_mismatch, role_scheme, vector_index, vector_storage, min_content_length, node_type_conflict, missing_node_mode, stop_words, max_fanout_per_node, hub_degree_threshold, embed_warmup, query_cache_size, query_cache_ttl_secs, placeholder_embeddings, max_path_depth, max_embed_chars, embed_overflow, multi_field_embed, recency_half_life_hours })
     }
 }
@@ -1,207 +1,73 @@
-use anyhow::Result;
-use tokio_postgres::{Client, NoTls};
+use anyhow::{Context, Result};
+use postgres_native_tls::MakeTlsConnector;
+use tokio_postgres::{config::SslMode, Client, NoTls};
 
-use crate::config::Config;
+use crate::config::{Config, ConfigError, DbSslMode};
 
-/// Obtain a connected `tokio_postgres::Client` and spawn the connection task.
+/// Obtain a connected `tokio_postgres::Client` and spawn the connection task. The
+/// schema is not touched; it's created by `db::migrate::run` (`cargo run --bin migrate`).
 pub async fn get_client() -> Result<Client> {
-    let cfg = Config::from_env();
-    let (client, connection) = tokio_postgres::connect(&cfg.db_url, NoTls).await?;
-    // Drive the connection on a background task
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("connection error: {e}");
-        }
-    });
-
-    // Ensure pgvector extension exists (for message embeddings)
-    client
-        .batch_execute(
-            "CREATE EXTENSION IF NOT EXISTS vector;"
-        )
-        .await?;
-
-    // Try to ensure AGE extension and graph exist (optional - for knowledge graph features)
-    let age_result = client
-        .batch_execute(
-            "CREATE EXTENSION IF NOT EXISTS age;\nLOAD 'age';\nSET search_path = ag_catalog, \"$user\", public;"
-        )
-        .await;
-    
-    if age_result.is_ok() {
-        eprintln!("✅ AGE extension loaded successfully");
-        
-        // Create common vertex labels if they don't exist
-        let labels = vec!["Node", "TestNode", "Person", "City"];
-        for label in labels {
-            let create_label_sql = format!(
-                "SELECT ag_catalog.create_vlabel('sem_graph', '{}') WHERE NOT EXISTS (SELECT 1 FROM ag_catalog.ag_label WHERE name='{}' AND graph=17033);",
-                label, label
-            );
-            let _ = client.execute(&create_label_sql, &[]).await; // Ignore errors if label exists
-        }
-        // create graph if not exists
-        let _ = client
-            .batch_execute(
-                "SELECT create_graph('sem_graph') WHERE NOT EXISTS (SELECT 1 FROM ag_graph WHERE name='sem_graph');"
-            )
-            .await;
-    } else {
-        eprintln!("⚠️  AGE extension not available - knowledge graph features will be limited");
+    let cfg = Config::try_global()?;
+    let client = connect(cfg).await?;
+
+    // Cypher queries need AGE loaded in each session (optional - for knowledge graph features)
+    if let Err(e) = client
+        .batch_execute("LOAD 'age';\nSET search_path = ag_catalog, \"$user\", public;")
+        .await
+    {
+        tracing::debug!(error = %e, "AGE not loaded - knowledge graph features will be limited");
     }
-    
-    // Create embeddings table if it doesn't exist (explicitly in ag_catalog schema)
-    client
-        .batch_execute(
-            "CREATE TABLE IF NOT EXISTS ag_catalog.embeddings (
-                 triplet_id BIGINT PRIMARY KEY,
-                 vec TEXT,
-                 lsh_bucket INTEGER,
-                 session_id TEXT,
-                 edge_text TEXT
-             );"
-        )
-        .await?;
-    
-    // Create sessions metadata table (explicitly in ag_catalog schema)
-    client
-        .batch_execute(
-            "CREATE TABLE IF NOT EXISTS ag_catalog.sessions (
-                 session_id TEXT PRIMARY KEY,
-                 ingested_at TIMESTAMP DEFAULT NOW(),
-                 node_count INTEGER,
-                 edge_count INTEGER
-             );"
-        )
-        .await?;
-    
-    // Create edge evidence tracking table (explicitly in ag_catalog schema)
-    client
-        .batch_execute(
-            "CREATE TABLE IF NOT EXISTS ag_catalog.edge_evidence (
-                 edge_id BIGINT,
-                 session_id TEXT,
-                 evidence_message_id TEXT,
-                 PRIMARY KEY (edge_id, evidence_message_id)
-             );"
-        )
-        .await?;
-    
-    // Run message and knowledge graph schema migration
-    run_message_schema_migration(&client).await?;
 
     Ok(client)
 }
 
-/// Run the message and knowledge graph schema migration
-async fn run_message_schema_migration(client: &Client) -> Result<()> {
-    println!("Running message schema migration...");
-
-    // Enable UUID extension
-    client.execute("CREATE EXTENSION IF NOT EXISTS \"uuid-ossp\";", &[]).await?;
-
-    // Conversations table
-    client.batch_execute(
-        "CREATE TABLE IF NOT EXISTS conversations (
-            conversation_id UUID PRIMARY KEY,
-            created_at TIMESTAMP DEFAULT NOW(),
-            updated_at TIMESTAMP DEFAULT NOW(),
-            metadata JSONB DEFAULT '{}'::jsonb
-        );"
-    ).await?;
-
-    // Messages table with full-text search support
-    client.batch_execute(
-        "CREATE TABLE IF NOT EXISTS messages (
-            message_id UUID PRIMARY KEY,
-            conversation_id UUID NOT NULL REFERENCES conversations(conversation_id) ON DELETE CASCADE,
-            content TEXT NOT NULL,
-            content_tsv tsvector, -- Full-text search vector
-            created_at TIMESTAMP DEFAULT NOW(),
-            metadata JSONB DEFAULT '{}'::jsonb
-        );"
-    ).await?;
-    
-    // Create GIN index for full-text search (BM25-style ranking)
-    client.execute(
-        "CREATE INDEX IF NOT EXISTS idx_messages_content_tsv 
-         ON messages USING GIN(content_tsv);",
-        &[]
-    ).await?;
-    
-    // Create trigger to auto-update tsvector on insert/update
-    client.batch_execute(
-        "CREATE OR REPLACE FUNCTION messages_tsv_trigger() RETURNS trigger AS $$
-         BEGIN
-           NEW.content_tsv := to_tsvector('english', NEW.content);
-           RETURN NEW;
-         END
-         $$ LANGUAGE plpgsql;
-         
-         DROP TRIGGER IF EXISTS tsvectorupdate ON messages;
-         CREATE TRIGGER tsvectorupdate BEFORE INSERT OR UPDATE
-         ON messages FOR EACH ROW EXECUTE FUNCTION messages_tsv_trigger();"
-    ).await?;
-
-    // Message embeddings with pgvector
-    client.batch_execute(
-        "CREATE TABLE IF NOT EXISTS message_embeddings (
-            message_id UUID PRIMARY KEY REFERENCES messages(message_id) ON DELETE CASCADE,
-            embedding vector(768) NOT NULL,
-            embedding_model VARCHAR(100) DEFAULT 'nomic-embed-text-v1.5',
-            created_at TIMESTAMP DEFAULT NOW()
-        );"
-    ).await?;
-
-    // Knowledge graph nodes
-    client.batch_execute(
-        "CREATE TABLE IF NOT EXISTS kg_nodes (
-            node_id VARCHAR(255),
-            conversation_id UUID REFERENCES conversations(conversation_id) ON DELETE CASCADE,
-            node_type VARCHAR(100),
-            created_at TIMESTAMP DEFAULT NOW(),
-            PRIMARY KEY (node_id, conversation_id)
-        );"
-    ).await?;
-
-    // Knowledge graph edges
-    client.batch_execute(
-        "CREATE TABLE IF NOT EXISTS kg_edges (
-            edge_id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
-            conversation_id UUID REFERENCES conversations(conversation_id) ON DELETE CASCADE,
-            source_node VARCHAR(255) NOT NULL,
-            target_node VARCHAR(255) NOT NULL,
-            relation VARCHAR(255) NOT NULL,
-            evidence_message_ids UUID[] NOT NULL,
-            created_at TIMESTAMP DEFAULT NOW()
-        );"
-    ).await?;
-
-    // Knowledge graph edge embeddings (for semantic search on edges)
-    client.batch_execute(
-        "CREATE TABLE IF NOT EXISTS kg_edge_embeddings (
-            edge_id UUID PRIMARY KEY REFERENCES kg_edges(edge_id) ON DELETE CASCADE,
-            embedding vector(768) NOT NULL,
-            edge_text TEXT NOT NULL,
-            embedding_model VARCHAR(100) DEFAULT 'nomic-embed-text-v1.5',
-            created_at TIMESTAMP DEFAULT NOW()
-        );"
-    ).await?;
-
-    // Create indexes
-    client.batch_execute(
-        "CREATE INDEX IF NOT EXISTS idx_messages_conversation ON messages(conversation_id);
-         CREATE INDEX IF NOT EXISTS idx_message_embeddings_ivfflat ON message_embeddings
-             USING ivfflat (embedding vector_cosine_ops) WITH (lists = 100);
-         CREATE INDEX IF NOT EXISTS idx_kg_edge_embeddings_ivfflat ON kg_edge_embeddings
-             USING ivfflat (embedding vector_cosine_ops) WITH (lists = 50);
-         CREATE INDEX IF NOT EXISTS idx_kg_edges_conversation ON kg_edges(conversation_id);
-         CREATE INDEX IF NOT EXISTS idx_kg_edges_evidence ON kg_edges USING GIN(evidence_message_ids);
-         CREATE INDEX IF NOT EXISTS idx_kg_nodes_conversation ON kg_nodes(conversation_id);
-         CREATE INDEX IF NOT EXISTS idx_kg_nodes_type ON kg_nodes(node_type);"
-    ).await?;
+/// Open a connection with the configured TLS mode, without loading AGE.
+pub async fn connect(cfg: &Config) -> Result<Client> {
+    if cfg.db_url.is_empty() {
+        return Err(ConfigError::MissingDatabaseUrl.into());
+    }
+    let mut pg_config: tokio_postgres::Config = cfg.db_url.parse()?;
+
+    let client = match cfg.db_sslmode {
+        DbSslMode::Disable => {
+            let (client, connection) = pg_config.connect(NoTls).await?;
+            // Drive the connection on a background task
+            tokio::spawn(async move {
+                if let Err(e) = connection.await {
+                    tracing::error!(error = %e, "connection error");
+                }
+            });
+            client
+        }
+        mode => {
+            pg_config.ssl_mode(SslMode::Require);
+            let tls = make_tls_connector(mode, cfg.db_ssl_root_cert.as_deref())?;
+            let (client, connection) = pg_config.connect(tls).await?;
+            tokio::spawn(async move {
+                if let Err(e) = connection.await {
+                    tracing::error!(error = %e, "connection error");
+                }
+            });
+            client
+        }
+    };
 
-    println!("Message schema migration completed successfully");
-    Ok(())
+    Ok(client)
 }
 
+/// Build the TLS connector for `require` / `verify-full`, trusting the optional
+/// CA certificate (PEM) in addition to the system roots.
+fn make_tls_connector(mode: DbSslMode, root_cert: Option<&str>) -> Result<MakeTlsConnector> {
+    let mut builder = native_tls::TlsConnector::builder();
+    if let Some(path) = root_cert {
+        let pem = std::fs::read(path).with_context(|| format!("reading DB_SSL_ROOT_CERT {}", path))?;
+        builder.add_root_certificate(native_tls::Certificate::from_pem(&pem)?);
+    }
+    if mode == DbSslMode::Require {
+        // Like libpq's sslmode=require: encrypted, but the certificate is not checked
+        builder
+            .danger_accept_invalid_certs(true)
+            .danger_accept_invalid_hostnames(true);
+    }
+    Ok(MakeTlsConnector::new(builder.build()?))
+}
@@ -0,0 +1,174 @@
+//! Whole-graph export as GraphML or node-link JSON, streamed row by row
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use tokio_postgres::Client;
+
+use crate::db::graph::parse_agtype;
+use crate::db::models::{AgEdge, AgVertex};
+
+/// Serialization for `GET /graph/export`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    /// Node-link JSON (`{"nodes": [...], "links": [...]}`), as read by networkx and Cytoscape
+    #[default]
+    Json,
+    /// GraphML, as read by Gephi and Cytoscape
+    GraphMl,
+}
+
+impl ExportFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "json" => Some(ExportFormat::Json),
+            "graphml" => Some(ExportFormat::GraphMl),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::GraphMl => "graphml",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "application/json",
+            ExportFormat::GraphMl => "application/graphml+xml",
+        }
+    }
+
+    /// Everything before the first node
+    pub fn header(&self, graph: &str) -> String {
+        match self {
+            ExportFormat::Json => format!(
+                "{{\"directed\":true,\"multigraph\":true,\"graph\":{{\"name\":{}}},\"nodes\":[",
+                serde_json::Value::from(graph)
+            ),
+            // Properties vary per label, so they're exported as one JSON-encoded attribute
+            ExportFormat::GraphMl => format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                 <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n\
+                 <key id=\"label\" for=\"all\" attr.name=\"label\" attr.type=\"string\"/>\n\
+                 <key id=\"properties\" for=\"all\" attr.name=\"properties\" attr.type=\"string\"/>\n\
+                 <graph id=\"{}\" edgedefault=\"directed\">\n",
+                xml_escape(graph)
+            ),
+        }
+    }
+
+    /// One node; `first` is whether it's the first node written
+    pub fn node(&self, node: &AgVertex, first: bool) -> String {
+        match self {
+            ExportFormat::Json => {
+                let json = serde_json::json!({ "id": node.id, "label": node.label, "properties": node.properties });
+                format!("{}{}", if first { "" } else { "," }, json)
+            }
+            ExportFormat::GraphMl => format!(
+                "<node id=\"n{}\"><data key=\"label\">{}</data><data key=\"properties\">{}</data></node>\n",
+                node.id,
+                xml_escape(&node.label),
+                xml_escape(&node.properties.to_string())
+            ),
+        }
+    }
+
+    /// Everything between the last node and the first edge
+    pub fn separator(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "],\"links\":[",
+            ExportFormat::GraphMl => "",
+        }
+    }
+
+    /// One edge; `first` is whether it's the first edge written
+    pub fn edge(&self, edge: &AgEdge, first: bool) -> String {
+        match self {
+            ExportFormat::Json => {
+                let json = serde_json::json!({
+                    "id": edge.id,
+                    "source": edge.start_id,
+                    "target": edge.end_id,
+                    "label": edge.label,
+                    "properties": edge.properties,
+                });
+                format!("{}{}", if first { "" } else { "," }, json)
+            }
+            ExportFormat::GraphMl => format!(
+                "<edge id=\"e{}\" source=\"n{}\" target=\"n{}\"><data key=\"label\">{}</data><data key=\"properties\">{}</data></edge>\n",
+                edge.id,
+                edge.start_id,
+                edge.end_id,
+                xml_escape(&edge.label),
+                xml_escape(&edge.properties.to_string())
+            ),
+        }
+    }
+
+    /// Everything after the last edge
+    pub fn footer(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "]}",
+            ExportFormat::GraphMl => "</graph>\n</graphml>\n",
+        }
+    }
+}
+
+/// Escape text for an XML attribute or element body
+pub fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Rows of a single-column cypher query, parsed from agtype as they arrive
+fn cypher_rows<T: serde::de::DeserializeOwned>(
+    client: Arc<Client>,
+    graph: &str,
+    pattern: &str,
+) -> impl Stream<Item = Result<T>> {
+    let sql = format!(
+        "SELECT x::text FROM ag_catalog.cypher('{graph}'::name, $$ {pattern} $$::cstring) AS (x ag_catalog.agtype);"
+    );
+    stream::once(async move {
+        let rows = client.query_raw(sql.as_str(), std::iter::empty::<&str>()).await?;
+        // Keep the client (and its connection) alive until the rows are consumed
+        Ok::<_, anyhow::Error>(rows.map_err(anyhow::Error::from).map(move |row| {
+            let _client = &client;
+            let text: String = row?.try_get(0)?;
+            Ok(serde_json::from_value(parse_agtype(&text)?)?)
+        }))
+    })
+    .try_flatten()
+}
+
+/// Every node, then every edge, of `graph` serialized in `format`. Rows are streamed
+/// from the database and written as they arrive, so the graph is never held in memory.
+pub fn export_graph(client: Client, graph: String, format: ExportFormat) -> impl Stream<Item = Result<String>> {
+    let client = Arc::new(client);
+    let nodes = cypher_rows::<AgVertex>(client.clone(), &graph, "MATCH (n) RETURN n")
+        .enumerate()
+        .map(move |(i, node)| node.map(|node| format.node(&node, i == 0)));
+    let edges = cypher_rows::<AgEdge>(client, &graph, "MATCH ()-[e]->() RETURN e")
+        .enumerate()
+        .map(move |(i, edge)| edge.map(|edge| format.edge(&edge, i == 0)));
+
+    stream::once(async move { Ok(format.header(&graph)) })
+        .chain(nodes)
+        .chain(stream::once(async move { Ok(format.separator().to_string()) }))
+        .chain(edges)
+        .chain(stream::once(async move { Ok(format.footer().to_string()) }))
+}
@@ -1,60 +1,545 @@
 use anyhow::Result;
-use serde_json::Value;
+use bytes::{BufMut, BytesMut};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::future::Future;
+use tokio_postgres::types::{to_sql_checked, IsNull, ToSql, Type};
 use tokio_postgres::Client;
 
-/// upsert (MERGE) a node with given label and primary key `pk` property.
+use crate::db::models::{AgEdge, AgVertex, GraphNeighbor, GraphPath, GraphSchema, NearbyEdge, SchemaEntry};
+
+/// Upper bound on paths expanded by `get_node_neighbors`, so a hub node at a
+/// large depth can't return an unbounded result
+pub const MAX_NEIGHBOR_PATHS: usize = 1000;
+
+/// Values for the `$name` parameters of a Cypher query, bound as the third (agtype)
+/// argument of `cypher()` so they never become part of the SQL text
+#[derive(Debug)]
+struct CypherParams(Value);
+
+impl ToSql for CypherParams {
+    fn to_sql(&self, _ty: &Type, out: &mut BytesMut) -> std::result::Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        // Like jsonb, agtype's binary format is a version byte followed by the text form
+        out.put_u8(1);
+        serde_json::to_writer(out.writer(), &self.0)?;
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "agtype"
+    }
+
+    to_sql_checked!();
+}
+
+/// Create the graph if it doesn't exist yet. A concurrent creator winning the
+/// race is not an error.
+pub async fn ensure_graph(client: &Client, graph: &str) -> Result<()> {
+    let result = client
+        .execute(
+            "SELECT ag_catalog.create_graph($1)
+             WHERE NOT EXISTS (SELECT 1 FROM ag_catalog.ag_graph WHERE name = $1)",
+            &[&graph],
+        )
+        .await;
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) if e.to_string().contains("already exists") => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Run `write` holding a Postgres advisory lock on `graph`. AGE has no unique
+/// constraints, so two connections MERGEing the same node or edge at once would
+/// both create it; MERGE writes are serialized across connections and processes.
+async fn with_graph_write_lock<T>(client: &Client, graph: &str, write: impl Future<Output = Result<T>>) -> Result<T> {
+    let key = graph_lock_key(graph);
+    client.execute("SELECT pg_advisory_lock(hashtext($1))", &[&key]).await?;
+    let result = write.await;
+    client.execute("SELECT pg_advisory_unlock(hashtext($1))", &[&key]).await?;
+    result
+}
+
+fn graph_lock_key(graph: &str) -> String {
+    format!("rust_ingester.graph:{}", graph)
+}
+
+/// Take the graph's write lock until the open transaction ends. Inside a transaction
+/// the per-write lock is released before commit, so without this another connection
+/// could MERGE a node this one has created but not committed yet.
+pub async fn lock_graph_for_transaction(client: &Client, graph: &str) -> Result<()> {
+    client.execute("SELECT pg_advisory_xact_lock(hashtext($1))", &[&graph_lock_key(graph)]).await?;
+    Ok(())
+}
+
+/// Create the vertex label if the graph doesn't have it yet, so any node type
+/// can be used as a label. A concurrent creator winning the race is not an error.
+pub async fn ensure_vlabel(client: &Client, graph: &str, label: &str) -> Result<()> {
+    let result = client
+        .execute(
+            "SELECT ag_catalog.create_vlabel($1, $2)
+             WHERE NOT EXISTS (
+                 SELECT 1 FROM ag_catalog.ag_label l
+                 JOIN ag_catalog.ag_graph g ON l.graph = g.graphid
+                 WHERE g.name = $1 AND l.name = $2
+             )",
+            &[&graph, &label],
+        )
+        .await;
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) if e.to_string().contains("already exists") => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Create the edge label if the graph doesn't have it yet, like `ensure_vlabel`
+pub async fn ensure_elabel(client: &Client, graph: &str, rel_type: &str) -> Result<()> {
+    let result = client
+        .execute(
+            "SELECT ag_catalog.create_elabel($1, $2)
+             WHERE NOT EXISTS (
+                 SELECT 1 FROM ag_catalog.ag_label l
+                 JOIN ag_catalog.ag_graph g ON l.graph = g.graphid
+                 WHERE g.name = $1 AND l.name = $2
+             )",
+            &[&graph, &rel_type],
+        )
+        .await;
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) if e.to_string().contains("already exists") => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Node properties to set on upsert: a JSON object (without `pk`), or null for none
+fn node_props(props: &Value) -> Result<serde_json::Map<String, Value>> {
+    match props {
+        Value::Null => Ok(serde_json::Map::new()),
+        Value::Object(map) if map.contains_key("pk") => anyhow::bail!("Node properties must not set pk"),
+        Value::Object(map) => Ok(map.clone()),
+        other => anyhow::bail!("Node properties must be a JSON object, got: {}", other),
+    }
+}
+
+/// upsert (MERGE) a node with given label and primary key `pk` property,
+/// creating the label on demand, and set `props` on it (`SET n += props`).
 /// Returns AGE internal id.
-pub async fn upsert_node(client: &Client, label: &str, pk: &str, props: &Value) -> Result<i64> {
-    let _props_str = if props.is_null() {
-        "{}".to_string()
-    } else {
-        props.to_string()
-    };
+pub async fn upsert_node(client: &Client, graph: &str, label: &str, pk: &str, props: &Value) -> Result<i64> {
+    let props = node_props(props)?;
+    let label_name = cypher_identifier(label)?;
+    ensure_vlabel(client, graph, label).await?;
     
     // Use the correct AGE syntax and cast result to text
     let cypher = format!(
-        "SELECT result::text FROM ag_catalog.cypher('sem_graph'::name, $$
-         CREATE (n:{label} {{pk: '{pk}'}}) 
+        "SELECT result::text FROM ag_catalog.cypher('{graph}'::name, $$
+         MERGE (n:{label_name} {{pk: $pk}})
+         SET n += $props
          RETURN id(n)
-         $$::cstring) AS (result ag_catalog.agtype);",
-        label = label, pk = pk
+         $$::cstring, $1) AS (result ag_catalog.agtype);"
     );
-    println!("Executing cypher: {}", cypher);
+    tracing::trace!(cypher = %cypher, "Executing cypher");
     
-    let row = client.query_one(&cypher, &[]).await?;
+    let params = CypherParams(json!({ "pk": pk, "props": props }));
+    let row = with_graph_write_lock(client, graph, async { Ok(client.query_one(&cypher, &[&params]).await?) }).await?;
     // Now it should be text that we can extract
     let result_text: String = row.get(0);
-    println!("AGE returned as text: {}", result_text);
+    tracing::trace!(result = %result_text, "AGE returned as text");
     let id: i64 = result_text.trim_matches('"').parse()?;
     Ok(id)
 }
 
-/// upsert edge between two internal node ids.
+/// upsert (MERGE) an edge between two internal node ids and set `props` (a JSON
+/// object, or null for none) on it, bound as a parameter. Returns the AGE internal
+/// edge id; upserting the same relationship again returns the same id.
 pub async fn upsert_edge(
     client: &Client,
+    graph: &str,
     rel_type: &str,
     from_id: i64,
     to_id: i64,
-    _props: &Value,
-) -> Result<()> {
-    // First create the edge label if it doesn't exist
-    let create_label_sql = format!(
-        "SELECT ag_catalog.create_elabel('sem_graph', '{}');",
-        rel_type
+    props: &Value,
+) -> Result<i64> {
+    let props = match props {
+        Value::Null => serde_json::Map::new(),
+        Value::Object(map) => map.clone(),
+        other => anyhow::bail!("Edge properties must be a JSON object, got: {}", other),
+    };
+    let rel_label = cypher_identifier(rel_type)?;
+    ensure_elabel(client, graph, rel_type).await?;
+
+    // MERGE so re-ingesting the same relationship updates it instead of duplicating it
+    let cypher = format!(
+        "SELECT result::text FROM ag_catalog.cypher('{graph}'::name, $$
+         MATCH (a) WHERE id(a) = $from_id
+         MATCH (b) WHERE id(b) = $to_id
+         MERGE (a)-[r:{rel_label}]->(b)
+         SET r += $props
+         RETURN id(r)
+         $$::cstring, $1) AS (result ag_catalog.agtype);"
     );
-    println!("Creating edge label: {}", create_label_sql);
-    let _ = client.execute(&create_label_sql, &[]).await; // Ignore errors if label exists
-    
-    // Create the edge using correct AGE syntax
+    tracing::trace!(cypher = %cypher, "Executing edge cypher");
+    let params = CypherParams(json!({ "from_id": from_id, "to_id": to_id, "props": props }));
+    let row = with_graph_write_lock(client, graph, async { Ok(client.query_opt(&cypher, &[&params]).await?) })
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Cannot create {} edge: node {} or {} not found", rel_type, from_id, to_id))?;
+    let result_text: String = row.get(0);
+    let id: i64 = result_text.trim_matches('"').parse()?;
+    Ok(id)
+}
+
+/// Upsert many nodes with few round trips: one UNWIND + MERGE statement per
+/// distinct label (Cypher labels cannot be parameterized; pks are).
+/// Returns AGE internal ids keyed by pk. Like `upsert_node`, properties are
+/// merged into the node's existing ones.
+pub async fn upsert_nodes_batch(
+    client: &Client,
+    graph: &str,
+    nodes: &[(&str, &str, &Value)],
+) -> Result<HashMap<String, i64>> {
+    let mut by_label: HashMap<&str, Vec<Value>> = HashMap::new();
+    for (label, pk, props) in nodes {
+        by_label.entry(label).or_default().push(json!({ "pk": pk, "props": node_props(props)? }));
+    }
+
+    let mut ids = HashMap::with_capacity(nodes.len());
+    for (label, rows) in by_label {
+        let label_name = cypher_identifier(label)?;
+        ensure_vlabel(client, graph, label).await?;
+
+        let cypher = format!(
+            "SELECT pk::text, id::text FROM ag_catalog.cypher('{graph}'::name, $$
+             UNWIND $rows AS row
+             MERGE (n:{label_name} {{pk: row.pk}})
+             SET n += row.props
+             RETURN row.pk, id(n)
+             $$::cstring, $1) AS (pk ag_catalog.agtype, id ag_catalog.agtype);"
+        );
+        tracing::trace!(node_count = rows.len(), label, "Executing batch cypher");
+
+        let params = CypherParams(json!({ "rows": rows }));
+        let rows = with_graph_write_lock(client, graph, async { Ok(client.query(&cypher, &[&params]).await?) }).await?;
+        for row in rows {
+            let pk_text: String = row.get(0);
+            let id_text: String = row.get(1);
+            let pk: String = serde_json::from_str(&pk_text)?;
+            let id: i64 = id_text.trim_matches('"').parse()?;
+            ids.insert(pk, id);
+        }
+    }
+
+    Ok(ids)
+}
+
+/// Fetch the node with the given pk (any label). Returns None when it doesn't exist.
+pub async fn get_node_by_pk(client: &Client, graph: &str, pk: &str) -> Result<Option<AgVertex>> {
     let cypher = format!(
-        "SELECT * FROM ag_catalog.cypher('sem_graph'::name, $$
-         MATCH (a) WHERE id(a) = {from_id}
-         MATCH (b) WHERE id(b) = {to_id}
-         CREATE (a)-[r:`{rel_type}`]->(b)
-         $$::cstring) AS (result ag_catalog.agtype);",
-        rel_type = rel_type, from_id = from_id, to_id = to_id
+        "SELECT n::text FROM ag_catalog.cypher('{graph}'::name, $$
+         MATCH (n {{pk: $pk}})
+         RETURN n
+         LIMIT 1
+         $$::cstring, $1) AS (n ag_catalog.agtype);"
     );
-    println!("Executing edge cypher: {}", cypher);
-    client.execute(&cypher, &[]).await?;
-    Ok(())
+
+    match client.query_opt(&cypher, &[&CypherParams(json!({ "pk": pk }))]).await? {
+        Some(row) => {
+            let text: String = row.get(0);
+            Ok(Some(serde_json::from_value(parse_agtype(&text)?)?))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Set `props` on the node with the given pk, keeping its other properties (`SET n +=
+/// props`; a null value removes the property). The pk and properties are bound as
+/// parameters, so any key or value is safe. `pk` itself can't be changed.
+/// Returns the updated node, or None when it doesn't exist.
+pub async fn update_node_properties(
+    client: &Client,
+    graph: &str,
+    pk: &str,
+    props: &serde_json::Map<String, Value>,
+) -> Result<Option<AgVertex>> {
+    if props.contains_key("pk") {
+        anyhow::bail!("The pk property can't be changed");
+    }
+    if props.is_empty() {
+        return get_node_by_pk(client, graph, pk).await;
+    }
+    let cypher = format!(
+        "SELECT n::text FROM ag_catalog.cypher('{graph}'::name, $$
+         MATCH (n {{pk: $pk}})
+         SET n += $props
+         RETURN n
+         $$::cstring, $1) AS (n ag_catalog.agtype);"
+    );
+    tracing::trace!(cypher = %cypher, "Executing node update cypher");
+
+    let params = CypherParams(json!({ "pk": pk, "props": props }));
+    match client.query(&cypher, &[&params]).await?.first() {
+        Some(row) => {
+            let text: String = row.get(0);
+            Ok(Some(serde_json::from_value(parse_agtype(&text)?)?))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Label (node type) of each node whose pk is in `pks`, keyed by pk. Pks without a
+/// node are left out.
+pub async fn get_node_labels(client: &Client, graph: &str, pks: &[&str]) -> Result<HashMap<String, String>> {
+    if pks.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let cypher = format!(
+        "SELECT pk::text, label::text FROM ag_catalog.cypher('{graph}'::name, $$
+         MATCH (n)
+         WHERE n.pk IN $pks
+         RETURN n.pk, label(n)
+         $$::cstring, $1) AS (pk ag_catalog.agtype, label ag_catalog.agtype);"
+    );
+
+    let mut labels = HashMap::with_capacity(pks.len());
+    for row in client.query(&cypher, &[&CypherParams(json!({ "pks": pks }))]).await? {
+        let pk: String = serde_json::from_str(&row.get::<_, String>(0))?;
+        let label: String = serde_json::from_str(&row.get::<_, String>(1))?;
+        labels.insert(pk, label);
+    }
+    Ok(labels)
+}
+
+/// Nodes within `depth` hops of the node with the given pk, ignoring edge direction.
+/// Each neighbor is reported once, with the relationships of the shortest path found.
+pub async fn get_node_neighbors(client: &Client, graph: &str, pk: &str, depth: usize) -> Result<Vec<GraphNeighbor>> {
+    if depth == 0 {
+        return Ok(Vec::new());
+    }
+
+    let cypher = format!(
+        "SELECT p::text FROM ag_catalog.cypher('{graph}'::name, $$
+         MATCH p = (n {{pk: $pk}})-[*1..{depth}]-(m)
+         RETURN p
+         LIMIT {limit}
+         $$::cstring, $1) AS (p ag_catalog.agtype);",
+        depth = depth,
+        limit = MAX_NEIGHBOR_PATHS
+    );
+
+    let mut start_id = None;
+    let mut neighbors: HashMap<i64, GraphNeighbor> = HashMap::new();
+    for row in client.query(&cypher, &[&CypherParams(json!({ "pk": pk }))]).await? {
+        let GraphPath { nodes: vertices, relationships: edges } = parse_path(&row.get::<_, String>(0))?;
+        let (Some(first), Some(last)) = (vertices.first(), vertices.last()) else {
+            continue;
+        };
+        let start = *start_id.get_or_insert(first.id);
+        if last.id == start {
+            continue;
+        }
+
+        let hops = edges.len();
+        let keep = neighbors.get(&last.id).is_none_or(|n| hops < n.hops);
+        if keep {
+            neighbors.insert(last.id, GraphNeighbor { node: last.clone(), hops, relationships: edges });
+        }
+    }
+
+    let mut neighbors: Vec<GraphNeighbor> = neighbors.into_values().collect();
+    neighbors.sort_by(|a, b| a.hops.cmp(&b.hops).then(a.node.id.cmp(&b.node.id)));
+    Ok(neighbors)
+}
+
+/// Shortest path between the nodes with pks `from` and `to`, ignoring edge direction,
+/// of at most `max_depth` relationships. `None` when they aren't connected within
+/// `max_depth` (or either node doesn't exist). Lengths are tried in increasing order,
+/// so a short path is found without expanding longer ones.
+pub async fn get_shortest_path(client: &Client, graph: &str, from: &str, to: &str, max_depth: usize) -> Result<Option<GraphPath>> {
+    if from == to {
+        return Ok(get_node_by_pk(client, graph, from).await?.map(|node| GraphPath { nodes: vec![node], relationships: vec![] }));
+    }
+
+    let params = CypherParams(json!({ "from": from, "to": to }));
+    for depth in 1..=max_depth {
+        let cypher = format!(
+            "SELECT p::text FROM ag_catalog.cypher('{graph}'::name, $$
+             MATCH p = (a {{pk: $from}})-[*{depth}..{depth}]-(b {{pk: $to}})
+             RETURN p
+             LIMIT 1
+             $$::cstring, $1) AS (p ag_catalog.agtype);",
+            depth = depth
+        );
+        if let Some(row) = client.query_opt(&cypher, &[&params]).await? {
+            return Ok(Some(parse_path(&row.get::<_, String>(0))?));
+        }
+    }
+    Ok(None)
+}
+
+/// Split an agtype path, an alternating `[vertex, edge, vertex, ...]` array
+fn parse_path(text: &str) -> Result<GraphPath> {
+    let Value::Array(elements) = parse_agtype(text)? else {
+        anyhow::bail!("Expected an agtype path, got: {}", text);
+    };
+    let mut path = GraphPath::default();
+    for (i, element) in elements.into_iter().enumerate() {
+        if i % 2 == 0 {
+            path.nodes.push(serde_json::from_value::<AgVertex>(element)?);
+        } else {
+            path.relationships.push(serde_json::from_value::<AgEdge>(element)?);
+        }
+    }
+    Ok(path)
+}
+
+/// Edges within `hops` hops of any node whose pk is in `pks`, ignoring direction. Each
+/// edge is reported once, at the fewest hops it was found; edges between two start nodes
+/// are included at 1 hop. Sorted by hops, then edge id.
+pub async fn get_edges_near(client: &Client, graph: &str, pks: &[&str], hops: usize) -> Result<Vec<NearbyEdge>> {
+    if hops == 0 || pks.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let cypher = format!(
+        "SELECT p::text FROM ag_catalog.cypher('{graph}'::name, $$
+         MATCH p = (n)-[*1..{hops}]-(m)
+         WHERE n.pk IN $pks
+         RETURN p
+         LIMIT {limit}
+         $$::cstring, $1) AS (p ag_catalog.agtype);",
+        hops = hops,
+        limit = MAX_NEIGHBOR_PATHS
+    );
+
+    let mut edges: HashMap<i64, NearbyEdge> = HashMap::new();
+    for row in client.query(&cypher, &[&CypherParams(json!({ "pks": pks }))]).await? {
+        let text: String = row.get(0);
+        let Value::Array(elements) = parse_agtype(&text)? else {
+            anyhow::bail!("Expected an agtype path, got: {}", text);
+        };
+        let mut pk_by_id = HashMap::new();
+        let mut path_edges = Vec::new();
+        for (i, element) in elements.into_iter().enumerate() {
+            if i % 2 == 0 {
+                let vertex: AgVertex = serde_json::from_value(element)?;
+                let pk = vertex.properties.get("pk").and_then(Value::as_str).unwrap_or_default().to_string();
+                pk_by_id.insert(vertex.id, pk);
+            } else {
+                path_edges.push(serde_json::from_value::<AgEdge>(element)?);
+            }
+        }
+
+        for (i, edge) in path_edges.into_iter().enumerate() {
+            let hops = i + 1;
+            if edges.get(&edge.id).is_some_and(|e| e.hops <= hops) {
+                continue;
+            }
+            let pk = |id: i64| pk_by_id.get(&id).cloned().unwrap_or_default();
+            edges.insert(edge.id, NearbyEdge {
+                id: edge.id,
+                source: pk(edge.start_id),
+                relation: edge.label,
+                target: pk(edge.end_id),
+                hops,
+            });
+        }
+    }
+
+    let mut edges: Vec<NearbyEdge> = edges.into_values().collect();
+    edges.sort_by(|a, b| a.hops.cmp(&b.hops).then(a.id.cmp(&b.id)));
+    Ok(edges)
+}
+
+/// Relation types and node types in the knowledge graph tables, plus the AGE labels
+/// registered for `graph` with their row counts
+pub async fn get_graph_schema(client: &Client, graph: &str) -> Result<GraphSchema> {
+    let entries = |rows: Vec<tokio_postgres::Row>| -> Vec<SchemaEntry> {
+        rows.iter().map(|row| SchemaEntry { name: row.get(0), count: row.get(1) }).collect()
+    };
+    let relations = entries(client.query(
+        "SELECT relation, COUNT(*) FROM ag_catalog.kg_edges GROUP BY relation ORDER BY relation",
+        &[],
+    ).await?);
+    let node_types = entries(client.query(
+        "SELECT COALESCE(node_type, 'unknown'), COUNT(*) FROM ag_catalog.kg_nodes GROUP BY 1 ORDER BY 1",
+        &[],
+    ).await?);
+
+    // The built-in parent labels hold no rows of their own
+    let labels = client.query(
+        "SELECT l.name::text, l.kind::text, l.relation::text
+         FROM ag_catalog.ag_label l
+         JOIN ag_catalog.ag_graph g ON l.graph = g.graphid
+         WHERE g.name = $1 AND l.name NOT IN ('_ag_label_vertex', '_ag_label_edge')
+         ORDER BY l.name",
+        &[&graph],
+    ).await?;
+    let mut vertex_labels = Vec::new();
+    let mut edge_labels = Vec::new();
+    for row in labels {
+        let name: String = row.get(0);
+        let kind: String = row.get(1);
+        // regclass text output is already quoted and schema-qualified
+        let table: String = row.get(2);
+        let count: i64 = client.query_one(&format!("SELECT COUNT(*) FROM {}", table), &[]).await?.get(0);
+        let entry = SchemaEntry { name, count };
+        if kind == "e" {
+            edge_labels.push(entry);
+        } else {
+            vertex_labels.push(entry);
+        }
+    }
+
+    Ok(GraphSchema { graph: graph.to_string(), relations, node_types, vertex_labels, edge_labels })
+}
+
+/// Parse an agtype value as printed by AGE into JSON, dropping the `::vertex`,
+/// `::edge`, `::path` and `::numeric` type annotations it appends to composite values
+pub fn parse_agtype(text: &str) -> Result<Value> {
+    let mut json = String::with_capacity(text.len());
+    let mut chars = text.trim().chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            json.push(c);
+            match c {
+                '\\' => {
+                    if let Some(escaped) = chars.next() {
+                        json.push(escaped);
+                    }
+                }
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match c {
+            '"' => {
+                in_string = true;
+                json.push(c);
+            }
+            ':' if chars.peek() == Some(&':') => {
+                chars.next();
+                while chars.peek().is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    chars.next();
+                }
+            }
+            _ => json.push(c),
+        }
+    }
+
+    Ok(serde_json::from_str(&json)?)
+}
+
+/// Quote a label or relationship type as a backticked Cypher identifier. Labels can't be
+/// parameterized, so names containing `$$` are rejected: they would end the
+/// dollar-quoted cypher() argument.
+fn cypher_identifier(name: &str) -> Result<String> {
+    if name.contains("$$") {
+        anyhow::bail!("Labels and relationship types must not contain '$$'");
+    }
+    Ok(format!("`{}`", name.replace('`', "``")))
 }
@@ -1,34 +1,162 @@
+use std::collections::{BTreeMap, HashSet};
 use tokio_postgres::{Client, Error};
 use uuid::Uuid;
 use pgvector::Vector;
 use crate::db::models::*;
-use crate::db::message_ops::insert_conversation;
+use crate::db::message_ops::{insert_conversation, merge_pipeline_metadata};
+use crate::etl::similarity::SimilarityMetric;
 
-/// Insert a knowledge graph node
+/// What `insert_kg_node` does when a node is re-ingested with a different `node_type`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NodeTypeConflict {
+    /// Replace the stored type with the new one
+    #[default]
+    Overwrite,
+    /// Replace the stored type and log the change, so flapping extractors show up
+    OverwriteAndLog,
+    /// Keep the type the node was first stored with
+    KeepExisting,
+    /// Fail with `KgNodeError::TypeConflict`, leaving the stored type unchanged
+    Error,
+}
+
+impl NodeTypeConflict {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "overwrite" => Some(NodeTypeConflict::Overwrite),
+            "overwrite_and_log" => Some(NodeTypeConflict::OverwriteAndLog),
+            "keep_existing" => Some(NodeTypeConflict::KeepExisting),
+            "error" => Some(NodeTypeConflict::Error),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NodeTypeConflict::Overwrite => "overwrite",
+            NodeTypeConflict::OverwriteAndLog => "overwrite_and_log",
+            NodeTypeConflict::KeepExisting => "keep_existing",
+            NodeTypeConflict::Error => "error",
+        }
+    }
+}
+
+/// Why `insert_kg_node` failed
+#[derive(Debug)]
+pub enum KgNodeError {
+    Db(Error),
+    /// The node is already stored with another type (`NodeTypeConflict::Error`)
+    TypeConflict { node_id: String, existing: String, incoming: String },
+}
+
+impl std::fmt::Display for KgNodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KgNodeError::Db(e) => write!(f, "{}", e),
+            KgNodeError::TypeConflict { node_id, existing, incoming } => write!(
+                f,
+                "Node '{}' is already stored with type '{}', not '{}'",
+                node_id, existing, incoming
+            ),
+        }
+    }
+}
+
+impl std::error::Error for KgNodeError {}
+
+impl From<Error> for KgNodeError {
+    fn from(e: Error) -> Self {
+        KgNodeError::Db(e)
+    }
+}
+
+/// Insert a knowledge graph node, resolving a changed `node_type` on an existing
+/// node with `on_conflict`
 pub async fn insert_kg_node(
     client: &Client,
     conversation_id: Uuid,
     node: &KGNode,
-) -> Result<(), Error> {
-    client.execute(
-        "INSERT INTO kg_nodes (node_id, conversation_id, node_type)
-         VALUES ($1, $2, $3)
-         ON CONFLICT (node_id, conversation_id) DO UPDATE
-         SET node_type = EXCLUDED.node_type",
+    on_conflict: NodeTypeConflict,
+) -> Result<(), KgNodeError> {
+    // The CTE reads the row as it was before the insert, so one statement both
+    // writes the node and reports the type it replaced (NULL for a new node)
+    let resolve = match on_conflict {
+        NodeTypeConflict::Overwrite | NodeTypeConflict::OverwriteAndLog => "EXCLUDED.node_type",
+        NodeTypeConflict::KeepExisting | NodeTypeConflict::Error => "kg_nodes.node_type",
+    };
+    let row = client.query_one(
+        &format!(
+            "WITH previous AS (
+                 SELECT node_type FROM ag_catalog.kg_nodes WHERE node_id = $1 AND conversation_id = $2
+             )
+             INSERT INTO ag_catalog.kg_nodes (node_id, conversation_id, node_type)
+             VALUES ($1, $2, $3)
+             ON CONFLICT (node_id, conversation_id) DO UPDATE
+             SET node_type = {}
+             RETURNING (SELECT node_type FROM previous)",
+            resolve
+        ),
         &[&node.id, &conversation_id, &node.node_type],
     ).await?;
-    Ok(())
+
+    let previous: Option<String> = row.get(0);
+    match previous {
+        Some(existing) if existing != node.node_type => match on_conflict {
+            NodeTypeConflict::Overwrite | NodeTypeConflict::KeepExisting => Ok(()),
+            NodeTypeConflict::OverwriteAndLog => {
+                tracing::info!(node_id = %node.id, %conversation_id, from = %existing, to = %node.node_type, "🔀 Node type changed");
+                Ok(())
+            }
+            NodeTypeConflict::Error => Err(KgNodeError::TypeConflict {
+                node_id: node.id.clone(),
+                existing,
+                incoming: node.node_type.clone(),
+            }),
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Merge `props` into `kg_nodes.properties` of every conversation's row for `node_id`,
+/// dropping keys set to null. Returns the number of rows updated (0 when the node
+/// was never ingested as part of a knowledge graph).
+pub async fn update_kg_node_properties(
+    client: &Client,
+    node_id: &str,
+    props: &serde_json::Value,
+) -> Result<u64, Error> {
+    client.execute(
+        "UPDATE ag_catalog.kg_nodes
+         SET properties = (
+             SELECT COALESCE(jsonb_object_agg(key, value), '{}'::jsonb)
+             FROM jsonb_each(COALESCE(properties, '{}'::jsonb) || $2)
+             WHERE value <> 'null'::jsonb
+         )
+         WHERE node_id = $1",
+        &[&node_id, props],
+    ).await
+}
+
+/// Evidence ids sorted with duplicates removed, so repeated support for an edge
+/// doesn't inflate its evidence count
+pub fn normalize_evidence_ids(ids: &[Uuid]) -> Vec<Uuid> {
+    let mut ids = ids.to_vec();
+    ids.sort();
+    ids.dedup();
+    ids
 }
 
-/// Insert a knowledge graph edge with evidence
+/// Insert a knowledge graph edge with evidence (deduplicated and sorted; an
+/// empty list is stored as an empty array)
 /// Returns the edge_id of the inserted edge
 pub async fn insert_kg_edge(
     client: &Client,
     conversation_id: Uuid,
     edge: &KGEdge,
 ) -> Result<Uuid, Error> {
+    let evidence_message_ids = normalize_evidence_ids(&edge.evidence_message_ids);
     let row = client.query_one(
-        "INSERT INTO kg_edges (conversation_id, source_node, target_node, relation, evidence_message_ids)
+        "INSERT INTO ag_catalog.kg_edges (conversation_id, source_node, target_node, relation, evidence_message_ids)
          VALUES ($1, $2, $3, $4, $5)
          RETURNING edge_id",
         &[
@@ -36,7 +164,7 @@ pub async fn insert_kg_edge(
             &edge.source,
             &edge.target,
             &edge.relation,
-            &edge.evidence_message_ids,
+            &evidence_message_ids,
         ],
     ).await?;
     
@@ -48,26 +176,42 @@ pub async fn insert_kg_edge(
 pub async fn batch_insert_knowledge_graph(
     client: &Client,
     kg_data: ConversationKnowledgeGraph,
+) -> Result<(usize, usize, Vec<String>), Error> {
+    batch_insert_knowledge_graph_with_progress(client, kg_data, |_| {}).await
+}
+
+/// Same as `batch_insert_knowledge_graph`, calling `on_progress` with the number
+/// of conversations finished before each one starts
+pub async fn batch_insert_knowledge_graph_with_progress(
+    client: &Client,
+    kg_data: ConversationKnowledgeGraph,
+    on_progress: impl Fn(usize) + Send + Sync,
 ) -> Result<(usize, usize, Vec<String>), Error> {
     let mut total_nodes = 0;
     let mut total_edges = 0;
     let mut errors = Vec::new();
+    let cfg = crate::config::Config::global();
 
-    for (conversation_id, kg) in kg_data.conversations {
-        // Ensure conversation exists
-        if let Err(e) = insert_conversation(client, conversation_id).await {
+    for (done, (conversation_id, kg)) in kg_data.conversations.into_iter().enumerate() {
+        on_progress(done);
+        
+        // Ensure conversation exists, keeping the extraction pipeline's metadata
+        let created = match &kg.pipeline_metadata {
+            Some(pipeline_metadata) => merge_pipeline_metadata(client, conversation_id, pipeline_metadata).await,
+            None => insert_conversation(client, conversation_id).await,
+        };
+        if let Err(e) = created {
             errors.push(format!("Conversation {}: {}", conversation_id, e));
             continue;
         }
 
         // Insert nodes
         for node in &kg.nodes {
-            match insert_kg_node(client, conversation_id, node).await {
+            match insert_kg_node(client, conversation_id, node, cfg.node_type_conflict).await {
                 Ok(_) => total_nodes += 1,
                 Err(e) => {
                     errors.push(format!("Node {} in conv {}: {}", node.id, conversation_id, e));
-                    eprintln!("Failed to insert node {} in conversation {}: {}",
-                        node.id, conversation_id, e);
+                    tracing::warn!(node_id = %node.id, %conversation_id, error = %e, "Failed to insert node");
                 }
             }
         }
@@ -83,37 +227,68 @@ pub async fn batch_insert_knowledge_graph(
                     
                     // Generate embedding using llama.cpp
                     use crate::etl::embed;
-                    match embed::embed_text(&edge_text).await {
-                        Ok(embedding) => {
+                    match embed::embed_text_with_provider(cfg, &edge_text).await {
+                        Ok((embedding, provider)) => {
                             // Insert the edge embedding
-                            if let Err(e) = insert_kg_edge_embedding(client, edge_id, &embedding, &edge_text).await {
-                                errors.push(format!("Embedding for edge {}->{}: {}", 
-                                    edge.source, edge.target, e));
-                                eprintln!("Failed to insert embedding for edge {}->{}: {}",
-                                    edge.source, edge.target, e);
+                            let model = provider.model_name(&cfg.embed_model_name);
+                            match insert_kg_edge_embedding(client, edge_id, &embedding, &edge_text, model).await {
+                                Ok(_) => {
+                                    crate::telemetry::record_ingested("embeddings", 1);
+                                    if cfg.multi_field_embed.is_some() && provider == embed::EmbeddingProvider::Http {
+                                        if let Err(e) = embed_edge_fields(client, edge_id, edge).await {
+                                            errors.push(format!("Field embeddings for edge {}->{}: {}",
+                                                edge.source, edge.target, e));
+                                            tracing::warn!(source = %edge.source, target = %edge.target, error = %e, "Failed to store edge field embeddings");
+                                        }
+                                    }
+                                }
+                                Err(e) => {
+                                    errors.push(format!("Embedding for edge {}->{}: {}", 
+                                        edge.source, edge.target, e));
+                                    tracing::warn!(source = %edge.source, target = %edge.target, error = %e, "Failed to insert edge embedding");
+                                }
                             }
                         }
                         Err(e) => {
                             errors.push(format!("Failed to generate embedding for edge {}->{}: {}",
                                 edge.source, edge.target, e));
-                            eprintln!("Failed to generate embedding for edge {}->{}: {}",
-                                edge.source, edge.target, e);
+                            tracing::warn!(source = %edge.source, target = %edge.target, error = %e, "Failed to generate edge embedding");
                         }
                     }
                 }
                 Err(e) => {
                     errors.push(format!("Edge {}->{} in conv {}: {}",
                         edge.source, edge.target, conversation_id, e));
-                    eprintln!("Failed to insert edge {}->{} in conversation {}: {}",
-                        edge.source, edge.target, conversation_id, e);
+                    tracing::warn!(source = %edge.source, target = %edge.target, %conversation_id, error = %e, "Failed to insert edge");
                 }
             }
         }
     }
 
+    crate::telemetry::record_ingested("nodes", total_nodes);
+    crate::telemetry::record_ingested("edges", total_edges);
+
     Ok((total_nodes, total_edges, errors))
 }
 
+/// Embed an edge's source, relation and target separately and store the vectors
+/// (`MULTI_FIELD_EMBED`). Placeholder vectors aren't stored: identical vectors would
+/// make every edge match a field-focused query equally.
+async fn embed_edge_fields(client: &Client, edge_id: Uuid, edge: &KGEdge) -> anyhow::Result<()> {
+    use crate::etl::embed;
+    let cfg = crate::config::Config::global();
+    let mut vectors = Vec::with_capacity(3);
+    for text in [&edge.source, &edge.relation, &edge.target] {
+        let (vector, provider) = embed::embed_text_with_provider(cfg, text).await?;
+        if provider != embed::EmbeddingProvider::Http {
+            anyhow::bail!("embedding server unavailable; field embeddings left unset");
+        }
+        vectors.push(vector);
+    }
+    set_kg_edge_field_embeddings(client, edge_id, [&vectors[0], &vectors[1], &vectors[2]]).await?;
+    Ok(())
+}
+
 /// Query knowledge graph edges by keyword matching
 pub async fn get_edges_by_query(
     client: &Client,
@@ -131,7 +306,7 @@ pub async fn get_edges_by_query(
 
     let rows = client.query(
         "SELECT DISTINCT conversation_id, source_node, target_node, relation, evidence_message_ids
-         FROM kg_edges
+         FROM ag_catalog.kg_edges
          WHERE source_node ILIKE ANY($1)
             OR target_node ILIKE ANY($1)
             OR relation ILIKE ANY($1)
@@ -145,6 +320,8 @@ pub async fn get_edges_by_query(
         target: row.get(2),
         relation: row.get(3),
         evidence_message_ids: row.get(4),
+        similarity: None,
+        evidence: None,
     }).collect();
 
     Ok(edges)
@@ -161,7 +338,7 @@ pub async fn get_edges_by_message_ids(
 
     let rows = client.query(
         "SELECT conversation_id, source_node, target_node, relation, evidence_message_ids
-         FROM kg_edges
+         FROM ag_catalog.kg_edges
          WHERE evidence_message_ids && $1::uuid[]",
         &[&message_ids],
     ).await?;
@@ -172,61 +349,216 @@ pub async fn get_edges_by_message_ids(
         target: row.get(2),
         relation: row.get(3),
         evidence_message_ids: row.get(4),
+        similarity: None,
+        evidence: None,
     }).collect();
 
     Ok(edges)
 }
 
-/// Get statistics about the knowledge graph
+/// Outcome of `prune_dangling_evidence`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EvidencePruneReport {
+    /// Edges with at least one evidence id pointing at a missing or soft-deleted message
+    pub edges_affected: usize,
+    /// Evidence ids stripped (or that would be, on a dry run)
+    pub ids_removed: usize,
+    /// Affected edges with no evidence left afterwards
+    pub edges_without_evidence: usize,
+    pub dry_run: bool,
+}
+
+/// Find evidence ids on KG edges that point at missing or soft-deleted messages, and
+/// unless `dry_run`, strip them (keeping the order of the rest). Limited to one
+/// conversation when `conversation_id` is set. Edges left without evidence are kept.
+pub async fn prune_dangling_evidence(
+    client: &Client,
+    conversation_id: Option<Uuid>,
+    dry_run: bool,
+) -> Result<EvidencePruneReport, Error> {
+    let pruned = "WITH pruned AS (
+             SELECT e.edge_id,
+                    cardinality(e.evidence_message_ids) AS before,
+                    ARRAY(
+                        SELECT u.id FROM unnest(e.evidence_message_ids) WITH ORDINALITY AS u(id, ord)
+                        WHERE EXISTS (
+                            SELECT 1 FROM ag_catalog.messages m
+                            WHERE m.message_id = u.id AND m.deleted_at IS NULL
+                        )
+                        ORDER BY u.ord
+                    ) AS kept
+             FROM ag_catalog.kg_edges e
+             WHERE $1::uuid IS NULL OR e.conversation_id = $1
+         ), changed AS (
+             SELECT * FROM pruned WHERE cardinality(kept) < before
+         )";
+    let sql = if dry_run {
+        format!("{pruned} SELECT before, cardinality(kept) FROM changed")
+    } else {
+        format!(
+            "{pruned} UPDATE ag_catalog.kg_edges e SET evidence_message_ids = c.kept
+             FROM changed c WHERE e.edge_id = c.edge_id
+             RETURNING c.before, cardinality(c.kept)"
+        )
+    };
+    let rows = client.query(&sql, &[&conversation_id]).await?;
+
+    let mut report = EvidencePruneReport { dry_run, ..Default::default() };
+    for row in rows {
+        let (before, kept): (i32, i32) = (row.get(0), row.get(1));
+        report.edges_affected += 1;
+        report.ids_removed += (before - kept) as usize;
+        if kept == 0 {
+            report.edges_without_evidence += 1;
+        }
+    }
+    if report.edges_affected > 0 {
+        tracing::info!(
+            edges = report.edges_affected,
+            ids = report.ids_removed,
+            dry_run,
+            "Found dangling KG evidence ids"
+        );
+    }
+    Ok(report)
+}
+
+/// Get statistics about the knowledge graph: totals, plus counts per node type,
+/// per relation and per number of evidence messages on an edge
 pub async fn get_kg_statistics(client: &Client) -> Result<serde_json::Value, Error> {
     let node_count: i64 = client.query_one(
-        "SELECT COUNT(*) FROM kg_nodes",
+        "SELECT COUNT(*) FROM ag_catalog.kg_nodes",
         &[]
     ).await?.get(0);
 
     let edge_count: i64 = client.query_one(
-        "SELECT COUNT(*) FROM kg_edges",
+        "SELECT COUNT(*) FROM ag_catalog.kg_edges",
         &[]
     ).await?.get(0);
 
     let conversation_count: i64 = client.query_one(
-        "SELECT COUNT(DISTINCT conversation_id) FROM conversations",
+        "SELECT COUNT(DISTINCT conversation_id) FROM ag_catalog.conversations",
         &[]
     ).await?.get(0);
 
     let message_count: i64 = client.query_one(
-        "SELECT COUNT(*) FROM messages",
+        "SELECT COUNT(*) FROM ag_catalog.messages WHERE deleted_at IS NULL",
         &[]
     ).await?.get(0);
 
+    let nodes_by_type: BTreeMap<String, i64> = client.query(
+        "SELECT COALESCE(node_type, 'unknown'), COUNT(*) FROM ag_catalog.kg_nodes GROUP BY 1",
+        &[]
+    ).await?.iter().map(|row| (row.get(0), row.get(1))).collect();
+
+    let edges_by_relation: BTreeMap<String, i64> = client.query(
+        "SELECT relation, COUNT(*) FROM ag_catalog.kg_edges GROUP BY relation",
+        &[]
+    ).await?.iter().map(|row| (row.get(0), row.get(1))).collect();
+
+    // Number of edges with each evidence count (cardinality is NULL for empty arrays)
+    let evidence_count_distribution: BTreeMap<i32, i64> = client.query(
+        "SELECT COALESCE(cardinality(evidence_message_ids), 0), COUNT(*)
+         FROM ag_catalog.kg_edges GROUP BY 1",
+        &[]
+    ).await?.iter().map(|row| (row.get(0), row.get(1))).collect();
+
     Ok(serde_json::json!({
         "total_nodes": node_count,
         "total_edges": edge_count,
         "total_conversations": conversation_count,
         "total_messages": message_count,
+        "nodes_by_type": nodes_by_type,
+        "edges_by_relation": edges_by_relation,
+        "evidence_count_distribution": evidence_count_distribution,
     }))
 }
 
-/// Insert an embedding for a knowledge graph edge
+/// Evidence and embedding coverage of the knowledge graph edges, optionally of one
+/// conversation: the failure modes that ingest reports only as log lines
+pub async fn get_evidence_quality(client: &Client, conversation_id: Option<Uuid>) -> Result<EvidenceQualityReport, Error> {
+    let row = client.query_one(
+        "SELECT COUNT(*),
+                COUNT(*) FILTER (WHERE COALESCE(cardinality(e.evidence_message_ids), 0) = 0),
+                COUNT(*) FILTER (WHERE ee.edge_id IS NULL),
+                COUNT(*) FILTER (WHERE ee.embedding_model = $2)
+         FROM ag_catalog.kg_edges e
+         LEFT JOIN ag_catalog.kg_edge_embeddings ee ON ee.edge_id = e.edge_id
+         WHERE $1::uuid IS NULL OR e.conversation_id = $1",
+        &[&conversation_id, &crate::etl::embed::PLACEHOLDER_MODEL],
+    ).await?;
+
+    let dangling = client.query_one(
+        "SELECT COUNT(*), COUNT(DISTINCT e.edge_id)
+         FROM ag_catalog.kg_edges e
+         CROSS JOIN LATERAL unnest(e.evidence_message_ids) AS ev(message_id)
+         LEFT JOIN ag_catalog.messages m ON m.message_id = ev.message_id AND m.deleted_at IS NULL
+         WHERE m.message_id IS NULL AND ($1::uuid IS NULL OR e.conversation_id = $1)",
+        &[&conversation_id],
+    ).await?;
+
+    // cardinality is NULL for empty arrays
+    let evidence_per_edge_histogram = client.query(
+        "SELECT COALESCE(cardinality(evidence_message_ids), 0), COUNT(*)
+         FROM ag_catalog.kg_edges
+         WHERE $1::uuid IS NULL OR conversation_id = $1
+         GROUP BY 1",
+        &[&conversation_id],
+    ).await?.iter().map(|row| (row.get(0), row.get(1))).collect();
+
+    Ok(EvidenceQualityReport {
+        conversation_id,
+        total_edges: row.get(0),
+        edges_without_evidence: row.get(1),
+        dangling_evidence_count: dangling.get(0),
+        edges_with_dangling_evidence: dangling.get(1),
+        evidence_per_edge_histogram,
+        edges_without_embedding: row.get(2),
+        edges_with_placeholder_embedding: row.get(3),
+    })
+}
+
+/// Insert an embedding for a knowledge graph edge, recording the model that produced it
 pub async fn insert_kg_edge_embedding(
     client: &Client,
     edge_id: Uuid,
     embedding: &[f32],
     edge_text: &str,
+    embedding_model: &str,
 ) -> Result<(), Error> {
     let embedding_vec = Vector::from(embedding.to_vec());
     
     client.execute(
-        "INSERT INTO ag_catalog.kg_edge_embeddings (edge_id, embedding, edge_text)
-         VALUES ($1, $2, $3)
+        "INSERT INTO ag_catalog.kg_edge_embeddings (edge_id, embedding, edge_text, embedding_model)
+         VALUES ($1, $2::vector, $3, $4)
          ON CONFLICT (edge_id) DO UPDATE 
-         SET embedding = EXCLUDED.embedding, edge_text = EXCLUDED.edge_text",
-        &[&edge_id, &embedding_vec, &edge_text],
+         SET embedding = EXCLUDED.embedding, edge_text = EXCLUDED.edge_text,
+             embedding_model = EXCLUDED.embedding_model",
+        &[&edge_id, &embedding_vec, &edge_text, &embedding_model],
     ).await?;
     
     Ok(())
 }
 
+/// KG edges without an embedding or with a placeholder one, as `(edge_id, edge_text)`
+/// in the `"source relation target"` form they're embedded from, optionally limited
+/// to one conversation
+pub async fn get_edges_needing_embedding(
+    client: &Client,
+    conversation_id: Option<Uuid>,
+) -> Result<Vec<(Uuid, String)>, Error> {
+    let rows = client.query(
+        "SELECT e.edge_id, e.source_node || ' ' || e.relation || ' ' || e.target_node
+         FROM ag_catalog.kg_edges e
+         LEFT JOIN ag_catalog.kg_edge_embeddings ee ON ee.edge_id = e.edge_id
+         WHERE (ee.edge_id IS NULL OR ee.embedding_model = $1)
+           AND ($2::uuid IS NULL OR e.conversation_id = $2)
+         ORDER BY e.created_at",
+        &[&crate::etl::embed::PLACEHOLDER_MODEL, &conversation_id],
+    ).await?;
+    Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+}
+
 /// Get similar edges by embedding similarity (for RAG retrieval)
 /// Returns edges with their evidence_message_ids
 pub async fn get_similar_edges_by_embedding(
@@ -234,93 +566,309 @@ pub async fn get_similar_edges_by_embedding(
     query_embedding: &[f32],
     limit: i64,
 ) -> Result<Vec<(KGEdgeWithContext, f32)>, Error> {
+    get_similar_edges_by_embedding_with_metric(client, query_embedding, limit, SimilarityMetric::Cosine, None).await
+}
+
+/// Same as `get_similar_edges_by_embedding`, ranking with the pgvector operator for `metric`
+/// and, when `embedding_model` is set, only considering vectors from that model. With
+/// `MULTI_FIELD_EMBED` on, edges are ranked by `get_similar_edges_by_field_embeddings`.
+pub async fn get_similar_edges_by_embedding_with_metric(
+    client: &Client,
+    query_embedding: &[f32],
+    limit: i64,
+    metric: SimilarityMetric,
+    embedding_model: Option<&str>,
+) -> Result<Vec<(KGEdgeWithContext, f32)>, Error> {
+    let cfg = crate::config::Config::global();
+    if let Some(scoring) = cfg.multi_field_embed {
+        return get_similar_edges_by_field_embeddings(client, query_embedding, limit, metric, embedding_model, scoring).await;
+    }
     let embedding_vec = Vector::from(query_embedding.to_vec());
+    let query = cfg.vector_storage.query_param("$1");
     
-    eprintln!("DEBUG: Searching for similar edges with embedding dim={}, limit={}", 
-        query_embedding.len(), limit);
+    tracing::debug!(dim = query_embedding.len(), limit, metric = metric.as_str(), "Searching for similar edges");
 
-    let rows = client.query(
+    let sql = format!(
         "SELECT e.edge_id, e.conversation_id, e.source_node, e.target_node, e.relation, 
-                e.evidence_message_ids, 1 - (ee.embedding <=> $1) as similarity
+                e.evidence_message_ids, {} as similarity
          FROM ag_catalog.kg_edges e
          JOIN ag_catalog.kg_edge_embeddings ee ON e.edge_id = ee.edge_id
-         ORDER BY ee.embedding <=> $1
+         WHERE $3::text IS NULL OR ee.embedding_model = $3
+         ORDER BY ee.embedding {} {}
          LIMIT $2",
-        &[&embedding_vec, &limit],
-    ).await?;
+        metric.pg_similarity_sql("ee.embedding", &query),
+        metric.pg_operator(),
+        query,
+    );
+    let rows = client.query(&sql, &[&embedding_vec, &limit, &embedding_model]).await?;
     
-    eprintln!("DEBUG: Query returned {} rows", rows.len());
-
-    let edges = rows.iter().map(|row| {
-        let similarity: f64 = row.get(6);
-        let edge = KGEdgeWithContext {
-            conversation_id: row.get(1),
-            source: row.get(2),
-            target: row.get(3),
-            relation: row.get(4),
-            evidence_message_ids: row.get(5),
-        };
-        (edge, similarity as f32)
-    }).collect();
+    tracing::debug!(result_count = rows.len(), "Similar edge query returned");
 
-    Ok(edges)
+    Ok(rows.iter().map(edge_with_similarity).collect())
+}
+
+/// How edges are ranked against the per-field (source, relation, target) embeddings
+/// stored when `MULTI_FIELD_EMBED` is on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldScoring {
+    /// The best of the whole-edge and the three field similarities, so a query about
+    /// only the subject (or relation, or object) matches on that field alone
+    Max,
+    /// Weighted mean of the source, relation and target similarities
+    Weighted([f32; 3]),
+}
+
+impl FieldScoring {
+    /// `max` or `weighted`, the latter with `weights` for source, relation and target
+    pub fn parse(s: &str, weights: [f32; 3]) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "max" => Some(FieldScoring::Max),
+            "weighted" => Some(FieldScoring::Weighted(weights)),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FieldScoring::Max => "max",
+            FieldScoring::Weighted(_) => "weighted",
+        }
+    }
+
+    /// SQL score of an edge given the similarity expressions of its whole-edge and
+    /// field embeddings. Edges stored without field embeddings score by `edge` alone.
+    fn sql(&self, edge: &str, fields: [String; 3]) -> String {
+        match self {
+            // GREATEST skips NULLs
+            FieldScoring::Max => format!("GREATEST({}, {}, {}, {})", edge, fields[0], fields[1], fields[2]),
+            FieldScoring::Weighted(w) => {
+                let total: f32 = w.iter().sum();
+                format!(
+                    "COALESCE(({} * {} + {} * {} + {} * {}) / {}, {})",
+                    w[0], fields[0], w[1], fields[1], w[2], fields[2], total, edge
+                )
+            }
+        }
+    }
+}
+
+/// Store the source, relation and target embeddings of an edge that already has its
+/// whole-edge embedding
+pub async fn set_kg_edge_field_embeddings(
+    client: &Client,
+    edge_id: Uuid,
+    fields: [&[f32]; 3],
+) -> Result<(), Error> {
+    let [source, relation, target] = fields.map(|v| Vector::from(v.to_vec()));
+    client.execute(
+        "UPDATE ag_catalog.kg_edge_embeddings
+         SET source_embedding = $2::vector, relation_embedding = $3::vector, target_embedding = $4::vector
+         WHERE edge_id = $1",
+        &[&edge_id, &source, &relation, &target],
+    ).await?;
+    Ok(())
+}
+
+/// Same as `get_similar_edges_by_embedding_with_metric`, ranking each edge by `scoring`
+/// over its whole-edge and per-field similarities. The score is computed for every
+/// candidate row, so this is a sequential scan rather than an ivfflat index lookup.
+pub async fn get_similar_edges_by_field_embeddings(
+    client: &Client,
+    query_embedding: &[f32],
+    limit: i64,
+    metric: SimilarityMetric,
+    embedding_model: Option<&str>,
+    scoring: FieldScoring,
+) -> Result<Vec<(KGEdgeWithContext, f32)>, Error> {
+    let embedding_vec = Vector::from(query_embedding.to_vec());
+    let query = crate::config::Config::global().vector_storage.query_param("$1");
+    let fields = crate::db::vector_index::KG_EDGE_FIELD_COLUMNS
+        .map(|column| metric.pg_similarity_sql(&format!("ee.{}", column), &query));
+    let score = scoring.sql(&metric.pg_similarity_sql("ee.embedding", &query), fields);
+
+    tracing::debug!(dim = query_embedding.len(), limit, metric = metric.as_str(), scoring = scoring.as_str(), "Searching for similar edges by field");
+
+    let sql = format!(
+        "SELECT e.edge_id, e.conversation_id, e.source_node, e.target_node, e.relation,
+                e.evidence_message_ids, ({})::float8 as similarity
+         FROM ag_catalog.kg_edges e
+         JOIN ag_catalog.kg_edge_embeddings ee ON e.edge_id = ee.edge_id
+         WHERE $3::text IS NULL OR ee.embedding_model = $3
+         ORDER BY similarity DESC
+         LIMIT $2",
+        score,
+    );
+    let rows = client.query(&sql, &[&embedding_vec, &limit, &embedding_model]).await?;
+
+    Ok(rows.iter().map(edge_with_similarity).collect())
+}
+
+fn edge_with_similarity(row: &tokio_postgres::Row) -> (KGEdgeWithContext, f32) {
+    let similarity: f64 = row.get(6);
+    let edge = KGEdgeWithContext {
+        conversation_id: row.get(1),
+        source: row.get(2),
+        target: row.get(3),
+        relation: row.get(4),
+        evidence_message_ids: row.get(5),
+        similarity: Some(similarity as f32),
+        evidence: None,
+    };
+    (edge, similarity as f32)
+}
+
+/// Most edges a graph traversal returns in total
+pub const MAX_TRAVERSAL_EDGES: usize = 50;
+
+/// Neighbor edges expanded per node when `MAX_FANOUT_PER_NODE` is unset
+pub const DEFAULT_MAX_FANOUT_PER_NODE: usize = 20;
+
+/// Degree above which a node isn't expanded when `HUB_DEGREE_THRESHOLD` is unset
+pub const DEFAULT_HUB_DEGREE_THRESHOLD: usize = 1000;
+
+/// Caps that keep a traversal from pulling in a hub node's whole neighborhood
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraversalLimits {
+    /// Edges expanded per node, most similar to the query first (0 = no cap)
+    pub max_fanout_per_node: usize,
+    /// Nodes with more edges than this are not expanded at all (0 = no limit)
+    pub hub_degree_threshold: usize,
+}
+
+impl Default for TraversalLimits {
+    fn default() -> Self {
+        TraversalLimits {
+            max_fanout_per_node: DEFAULT_MAX_FANOUT_PER_NODE,
+            hub_degree_threshold: DEFAULT_HUB_DEGREE_THRESHOLD,
+        }
+    }
+}
+
+/// Edges found by a traversal, and the hub nodes it didn't expand
+#[derive(Debug, Default)]
+pub struct TraversalResult {
+    pub edges: Vec<KGEdgeWithContext>,
+    pub hubs_skipped: Vec<String>,
 }
 
 /// Graph traversal: Find related edges via multi-hop traversal
 /// This expands the context by following graph relationships
+/// Each expanded edge carries its similarity to `query_embedding` when it has an embedding
+/// (from `embedding_model`, when set)
+///
+/// Starting from the seed edges' nodes, each hop expands the edges of the nodes reached
+/// so far (hop 0 covers the seed nodes themselves), up to `max_hops`. A node expands at
+/// most `limits.max_fanout_per_node` edges, the most similar to the query first, and a
+/// node with more than `limits.hub_degree_threshold` edges is skipped and reported.
 pub async fn traverse_graph_from_edges(
     client: &Client,
     seed_edges: &[(KGEdgeWithContext, f32)],
     max_hops: i32,
-) -> Result<Vec<KGEdgeWithContext>, Error> {
+    query_embedding: &[f32],
+    embedding_model: Option<&str>,
+    limits: TraversalLimits,
+) -> Result<TraversalResult, Error> {
+    let mut result = TraversalResult::default();
     if seed_edges.is_empty() {
-        return Ok(Vec::new());
+        return Ok(result);
     }
     
     // Collect seed nodes (both source and target from matched edges)
-    let mut seed_nodes: Vec<String> = seed_edges.iter()
+    let mut frontier: Vec<String> = seed_edges.iter()
         .flat_map(|(edge, _)| vec![edge.source.clone(), edge.target.clone()])
         .collect();
-    seed_nodes.sort();
-    seed_nodes.dedup();
+    frontier.sort();
+    frontier.dedup();
     
-    println!("  Graph traversal from {} seed nodes, max {} hops", seed_nodes.len(), max_hops);
+    tracing::debug!(seed_nodes = frontier.len(), max_hops, ?limits, "Graph traversal");
     
-    // Multi-hop traversal query
-    let rows = client.query(
-        "WITH RECURSIVE graph_traverse AS (
-            -- Base case: seed edges
-            SELECT DISTINCT e.edge_id, e.conversation_id, e.source_node, e.target_node, 
-                   e.relation, e.evidence_message_ids, 0 as hop
-            FROM ag_catalog.kg_edges e
-            WHERE e.source_node = ANY($1) OR e.target_node = ANY($1)
-            
-            UNION
-            
-            -- Recursive case: follow edges
-            SELECT DISTINCT e.edge_id, e.conversation_id, e.source_node, e.target_node,
-                   e.relation, e.evidence_message_ids, gt.hop + 1
-            FROM ag_catalog.kg_edges e
-            JOIN graph_traverse gt ON (e.source_node = gt.target_node OR e.target_node = gt.source_node)
-            WHERE gt.hop < $2
-        )
-        SELECT DISTINCT edge_id, conversation_id, source_node, target_node, relation, evidence_message_ids
-        FROM graph_traverse
-        LIMIT 50",
-        &[&seed_nodes, &max_hops],
-    ).await?;
+    let query_vec = Vector::from(query_embedding.to_vec());
+    let query = crate::config::Config::global().vector_storage.query_param("$2");
+    let fanout = if limits.max_fanout_per_node == 0 { i64::MAX } else { limits.max_fanout_per_node as i64 };
+    let mut visited: HashSet<String> = frontier.iter().cloned().collect();
+    let mut seen_edges: HashSet<Uuid> = HashSet::new();
     
-    let expanded_edges: Vec<KGEdgeWithContext> = rows.iter().map(|row| KGEdgeWithContext {
-        conversation_id: row.get(1),
-        source: row.get(2),
-        target: row.get(3),
-        relation: row.get(4),
-        evidence_message_ids: row.get(5),
-    }).collect();
+    for _hop in 0..=max_hops.max(0) {
+        if frontier.is_empty() || result.edges.len() >= MAX_TRAVERSAL_EDGES {
+            break;
+        }
+        
+        if limits.hub_degree_threshold > 0 {
+            let hubs: Vec<String> = client.query(
+                "SELECT n.node
+                 FROM unnest($1::text[]) AS n(node)
+                 JOIN ag_catalog.kg_edges e ON e.source_node = n.node OR e.target_node = n.node
+                 GROUP BY n.node
+                 HAVING COUNT(*) > $2",
+                &[&frontier, &(limits.hub_degree_threshold as i64)],
+            ).await?.iter().map(|row| row.get(0)).collect();
+            for hub in &hubs {
+                tracing::info!(node = %hub, hub_degree_threshold = limits.hub_degree_threshold, "Skipping expansion of hub node");
+            }
+            frontier.retain(|node| !hubs.contains(node));
+            result.hubs_skipped.extend(hubs);
+        }
+        
+        // Each node's edges ranked by similarity to the query (edges without an embedding last)
+        let rows = client.query(
+            &format!("SELECT edge_id, conversation_id, source_node, target_node, relation, evidence_message_ids, similarity
+             FROM (
+                 SELECT e.edge_id, e.conversation_id, e.source_node, e.target_node, e.relation,
+                        e.evidence_message_ids, 1 - (ee.embedding <=> {query}) AS similarity,
+                        ROW_NUMBER() OVER (
+                            PARTITION BY n.node ORDER BY ee.embedding <=> {query} NULLS LAST, e.edge_id
+                        ) AS node_rank
+                 FROM unnest($1::text[]) AS n(node)
+                 JOIN ag_catalog.kg_edges e ON e.source_node = n.node OR e.target_node = n.node
+                 LEFT JOIN ag_catalog.kg_edge_embeddings ee ON ee.edge_id = e.edge_id
+                     AND ($3::text IS NULL OR ee.embedding_model = $3)
+             ) ranked
+             WHERE node_rank <= $4
+             ORDER BY similarity DESC NULLS LAST, edge_id"),
+            &[&frontier, &query_vec, &embedding_model, &fanout],
+        ).await?;
+        
+        let mut next = Vec::new();
+        for row in rows {
+            if result.edges.len() >= MAX_TRAVERSAL_EDGES {
+                break;
+            }
+            if !seen_edges.insert(row.get(0)) {
+                continue;
+            }
+            let edge = KGEdgeWithContext {
+                conversation_id: row.get(1),
+                source: row.get(2),
+                target: row.get(3),
+                relation: row.get(4),
+                evidence_message_ids: row.get(5),
+                similarity: row.get::<_, Option<f64>>(6).map(|s| s as f32),
+                evidence: None,
+            };
+            for node in [&edge.source, &edge.target] {
+                if visited.insert(node.clone()) {
+                    next.push(node.clone());
+                }
+            }
+            result.edges.push(edge);
+        }
+        frontier = next;
+    }
     
-    println!("  Graph traversal found {} related edges", expanded_edges.len());
+    tracing::debug!(
+        result_count = result.edges.len(),
+        hubs_skipped = result.hubs_skipped.len(),
+        "Graph traversal found related edges"
+    );
     
-    Ok(expanded_edges)
+    Ok(result)
+}
+
+/// Edges from `hybrid_kg_retrieval`, and the hub nodes its traversal didn't expand
+#[derive(Debug, Default)]
+pub struct KgRetrieval {
+    pub edges: Vec<KGEdgeWithContext>,
+    pub hubs_skipped: Vec<String>,
 }
 
 /// Hybrid KG retrieval: Embedding search + Graph traversal
@@ -329,23 +877,44 @@ pub async fn hybrid_kg_retrieval(
     query_embedding: &[f32],
     top_k: i64,
     enable_traversal: bool,
-) -> Result<Vec<KGEdgeWithContext>, Error> {
+    embedding_model: Option<&str>,
+    limits: TraversalLimits,
+) -> Result<KgRetrieval, Error> {
     // Step 1: Find seed edges via embedding similarity
-    let seed_edges = get_similar_edges_by_embedding(client, query_embedding, top_k).await?;
+    let seed_edges = get_similar_edges_by_embedding_with_metric(
+        client, query_embedding, top_k, SimilarityMetric::Cosine, embedding_model,
+    ).await?;
     
     if !enable_traversal || seed_edges.is_empty() {
-        return Ok(seed_edges.into_iter().map(|(edge, _)| edge).collect());
+        return Ok(KgRetrieval {
+            edges: seed_edges.into_iter().map(|(edge, _)| edge).collect(),
+            hubs_skipped: Vec::new(),
+        });
     }
     
     // Step 2: Expand via graph traversal (1-2 hops)
-    let expanded_edges = traverse_graph_from_edges(client, &seed_edges, 2).await?;
+    let expanded = traverse_graph_from_edges(client, &seed_edges, 2, query_embedding, embedding_model, limits).await?;
     
     // Step 3: Combine seed + expanded (dedup by edge_id happens in caller)
-    let mut all_edges: Vec<KGEdgeWithContext> = seed_edges.into_iter()
+    let mut edges: Vec<KGEdgeWithContext> = seed_edges.into_iter()
         .map(|(edge, _)| edge)
         .collect();
-    all_edges.extend(expanded_edges);
+    edges.extend(expanded.edges);
     
-    Ok(all_edges)
+    Ok(KgRetrieval { edges, hubs_skipped: expanded.hubs_skipped })
 }
 
+
+/// Drop edges whose similarity to the query is below `min_similarity`.
+/// Edges without a similarity score are dropped too, since their relevance is unknown.
+pub fn filter_edges_by_similarity(
+    edges: Vec<KGEdgeWithContext>,
+    min_similarity: Option<f32>,
+) -> Vec<KGEdgeWithContext> {
+    let Some(min) = min_similarity else {
+        return edges;
+    };
+    edges.into_iter()
+        .filter(|edge| edge.similarity.is_some_and(|s| s >= min))
+        .collect()
+}
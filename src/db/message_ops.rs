@@ -1,8 +1,17 @@
 use tokio_postgres::{Client, Error};
 use uuid::Uuid;
 use pgvector::Vector;
+use crate::config::Config;
 use crate::db::models::*;
-use std::collections::HashSet;
+use crate::etl::content_hash::content_hash;
+use crate::etl::sanitize::sanitize_content;
+use crate::etl::similarity::{score_order, SimilarityMetric};
+use std::collections::{HashMap, HashSet};
+use std::pin::pin;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio_postgres::binary_copy::BinaryCopyInWriter;
+use tokio_postgres::types::Type;
 
 /// Insert or update a conversation record
 pub async fn insert_conversation(
@@ -10,7 +19,7 @@ pub async fn insert_conversation(
     conversation_id: Uuid,
 ) -> Result<(), Error> {
     client.execute(
-        "INSERT INTO conversations (conversation_id) 
+        "INSERT INTO ag_catalog.conversations (conversation_id) 
          VALUES ($1) 
          ON CONFLICT (conversation_id) DO NOTHING",
         &[&conversation_id],
@@ -18,45 +27,156 @@ pub async fn insert_conversation(
     Ok(())
 }
 
-/// Insert a message with its embedding
-pub async fn insert_message_with_embedding(
+/// Insert a conversation with metadata. For an existing conversation the new
+/// keys are merged into its metadata object (JSONB `||`), overwriting on conflict.
+pub async fn insert_conversation_with_metadata(
     client: &Client,
-    turn_data: &TurnEmbedding,
+    conversation_id: Uuid,
+    metadata: &serde_json::Value,
 ) -> Result<(), Error> {
-    // Insert message
     client.execute(
-        "INSERT INTO messages (message_id, conversation_id, content)
-         VALUES ($1, $2, $3)
+        "INSERT INTO ag_catalog.conversations (conversation_id, metadata)
+         VALUES ($1, $2)
+         ON CONFLICT (conversation_id) DO UPDATE
+         SET metadata = ag_catalog.conversations.metadata || EXCLUDED.metadata,
+             updated_at = NOW()",
+        &[&conversation_id, metadata],
+    ).await?;
+    Ok(())
+}
+
+/// Metadata key holding the `pipeline_metadata` of a conversation's knowledge graph
+pub const PIPELINE_METADATA_KEY: &str = "pipeline_metadata";
+
+/// Insert a conversation (if new) and record the KG extraction pipeline's metadata
+/// under `metadata.pipeline_metadata`. When both the stored and new values are
+/// objects their keys are merged, new values winning; otherwise the new value replaces it.
+pub async fn merge_pipeline_metadata(
+    client: &Client,
+    conversation_id: Uuid,
+    pipeline_metadata: &serde_json::Value,
+) -> Result<(), Error> {
+    client.execute(
+        "INSERT INTO ag_catalog.conversations (conversation_id, metadata)
+         VALUES ($1, jsonb_build_object($2::text, $3::jsonb))
+         ON CONFLICT (conversation_id) DO UPDATE
+         SET metadata = COALESCE(ag_catalog.conversations.metadata, '{}'::jsonb) || jsonb_build_object($2::text,
+                 CASE WHEN jsonb_typeof(ag_catalog.conversations.metadata -> $2::text) = 'object'
+                       AND jsonb_typeof($3::jsonb) = 'object'
+                      THEN (ag_catalog.conversations.metadata -> $2::text) || $3::jsonb
+                      ELSE $3::jsonb
+                 END),
+             updated_at = NOW()",
+        &[&conversation_id, &PIPELINE_METADATA_KEY, pipeline_metadata],
+    ).await?;
+    Ok(())
+}
+
+/// Fetch a conversation and its metadata
+pub async fn get_conversation(
+    client: &Client,
+    conversation_id: Uuid,
+) -> Result<Option<Conversation>, Error> {
+    let row = client.query_opt(
+        "SELECT conversation_id, metadata FROM ag_catalog.conversations WHERE conversation_id = $1",
+        &[&conversation_id],
+    ).await?;
+
+    Ok(row.map(|row| Conversation {
+        conversation_id: row.get(0),
+        metadata: row.get(1),
+    }))
+}
+
+/// Content to store for a turn: the sanitized text (`sanitize_content`), its hash,
+/// and the original bytes when sanitizing changed them
+fn stored_content(text: &str) -> (String, String, Option<Vec<u8>>) {
+    let content = sanitize_content(text);
+    let original = (content != text).then(|| text.as_bytes().to_vec());
+    let hash = content_hash(&content);
+    (content.into_owned(), hash, original)
+}
+
+/// Insert a message with its embedding. The content is stored sanitized (NUL bytes
+/// and control characters removed), with the original kept in `original_content`.
+/// Unchanged content and embeddings are left untouched; returns whether anything
+/// was written.
+pub async fn insert_message_with_embedding(
+    client: &Client,
+    turn_data: &TurnEmbedding,
+) -> Result<bool, Error> {
+    insert_message_row(client, turn_data, None).await
+}
+
+/// Same as `insert_message_with_embedding`, recording the turn's position in its
+/// batch as `turn_index` for a new message, as `copy_insert_messages` does
+pub async fn insert_message_with_embedding_at(
+    client: &Client,
+    turn_data: &TurnEmbedding,
+    turn_index: i32,
+) -> Result<bool, Error> {
+    insert_message_row(client, turn_data, Some(turn_index)).await
+}
+
+async fn insert_message_row(
+    client: &Client,
+    turn_data: &TurnEmbedding,
+    turn_index: Option<i32>,
+) -> Result<bool, Error> {
+    // Insert message (unchanged content is left untouched)
+    let (content, hash, original) = stored_content(&turn_data.actual_text);
+    let message_written = client.execute(
+        "INSERT INTO ag_catalog.messages (message_id, conversation_id, content, content_hash, turn_index, original_content)
+         VALUES ($1, $2, $3, $4, $5, $6)
          ON CONFLICT (message_id) DO UPDATE 
-         SET content = EXCLUDED.content",
+         SET content = EXCLUDED.content, content_hash = EXCLUDED.content_hash,
+             original_content = EXCLUDED.original_content
+         WHERE ag_catalog.messages.content_hash IS DISTINCT FROM EXCLUDED.content_hash",
         &[
             &turn_data.message_id,
             &turn_data.conversation_id,
-            &turn_data.actual_text,
+            &content,
+            &hash,
+            &turn_index,
+            &original,
         ],
     ).await?;
 
     // Convert embedding Vec<f32> to pgvector Vector type
     let embedding_vec = Vector::from(turn_data.embedding.clone());
 
-    // Insert embedding
-    client.execute(
-        "INSERT INTO message_embeddings (message_id, embedding)
-         VALUES ($1, $2)
+    // Insert embedding (an identical vector from the same model is left untouched)
+    let embedding_written = client.execute(
+        "INSERT INTO ag_catalog.message_embeddings (message_id, embedding, embedding_model)
+         VALUES ($1, $2::vector, $3)
          ON CONFLICT (message_id) DO UPDATE 
-         SET embedding = EXCLUDED.embedding",
-        &[&turn_data.message_id, &embedding_vec],
+         SET embedding = EXCLUDED.embedding, embedding_model = EXCLUDED.embedding_model
+         WHERE ag_catalog.message_embeddings.embedding IS DISTINCT FROM EXCLUDED.embedding
+            OR ag_catalog.message_embeddings.embedding_model IS DISTINCT FROM EXCLUDED.embedding_model",
+        &[&turn_data.message_id, &embedding_vec, &turn_data.embedding_model_or_default()],
     ).await?;
 
-    Ok(())
+    Ok(message_written + embedding_written > 0)
 }
 
-/// Batch insert messages and embeddings
+/// Batch insert messages and embeddings. Returns the number of turns written,
+/// the number skipped because content and embedding were unchanged, and errors.
 pub async fn batch_insert_messages(
     client: &Client,
     turns: &[TurnEmbedding],
-) -> Result<(usize, Vec<String>), Error> {
+) -> Result<(usize, usize, Vec<String>), Error> {
+    batch_insert_messages_with_metadata(client, turns, None).await
+}
+
+/// Batch insert messages and embeddings, attaching `metadata` to every
+/// conversation the turns belong to
+pub async fn batch_insert_messages_with_metadata(
+    client: &Client,
+    turns: &[TurnEmbedding],
+    metadata: Option<&serde_json::Value>,
+) -> Result<(usize, usize, Vec<String>), Error> {
     let mut success_count = 0;
+    let mut skipped = 0;
     let mut errors = Vec::new();
 
     // Collect unique conversation IDs
@@ -68,47 +188,294 @@ pub async fn batch_insert_messages(
 
     // Insert all conversations first
     for conv_id in conv_ids {
-        insert_conversation(client, conv_id).await?;
+        match metadata {
+            Some(metadata) => insert_conversation_with_metadata(client, conv_id, metadata).await?,
+            None => insert_conversation(client, conv_id).await?,
+        }
     }
 
-    // Insert messages and embeddings
-    for turn in turns {
-        match insert_message_with_embedding(client, turn).await {
-            Ok(_) => success_count += 1,
+    // Fast path: bulk COPY the whole batch in one transaction
+    match copy_insert_messages(client, turns).await {
+        Ok(count) => {
+            crate::telemetry::record_ingested("messages", count);
+            return Ok((count, turns.len().saturating_sub(count), errors));
+        }
+        Err(e) => {
+            tracing::warn!(error = %e, "COPY insert failed, falling back to row-by-row insert");
+            let _ = client.batch_execute("ROLLBACK").await;
+        }
+    }
+
+    // Insert messages and embeddings, keeping the batch order like the COPY path
+    for (turn_index, turn) in turns.iter().enumerate() {
+        match insert_message_with_embedding_at(client, turn, turn_index as i32).await {
+            Ok(true) => success_count += 1,
+            Ok(false) => skipped += 1,
             Err(e) => {
                 errors.push(format!("Message {}: {}", turn.message_id, e));
-                eprintln!("Failed to insert message {}: {}", turn.message_id, e);
+                tracing::warn!(message_id = %turn.message_id, error = %e, "Failed to insert message");
             }
         }
     }
 
-    Ok((success_count, errors))
+    crate::telemetry::record_ingested("messages", success_count);
+    Ok((success_count, skipped, errors))
 }
 
+/// Bulk insert messages and embeddings with binary `COPY ... FROM STDIN`.
+/// Rows are copied into temporary staging tables and then upserted, all in a
+/// single transaction, so a failure leaves nothing half-written. Content is
+/// sanitized as in `insert_message_with_embedding`. The rows share
+/// one `created_at`, so each new message records its position in `turns` as
+/// `turn_index` to keep the conversation order.
+/// Conversations must already exist. Returns the number of turns whose message
+/// or embedding was inserted or changed; turns with unchanged content hash and
+/// an identical embedding are not written or counted.
+pub async fn copy_insert_messages(
+    client: &Client,
+    turns: &[TurnEmbedding],
+) -> Result<usize, Error> {
+    if turns.is_empty() {
+        return Ok(0);
+    }
+
+    client.batch_execute(
+        "BEGIN;
+         CREATE TEMP TABLE staging_messages (
+             message_id UUID, conversation_id UUID, content TEXT, content_hash TEXT, turn_index INTEGER,
+             original_content BYTEA
+         ) ON COMMIT DROP;
+         CREATE TEMP TABLE staging_message_embeddings (
+             message_id UUID, embedding vector, embedding_model TEXT
+         ) ON COMMIT DROP;"
+    ).await?;
+
+    // pgvector's type oid differs per database, so look it up
+    let vector_type = client.prepare("SELECT NULL::vector").await?.columns()[0].type_().clone();
+
+    let sink = client.copy_in(
+        "COPY staging_messages (message_id, conversation_id, content, content_hash, turn_index, original_content)
+         FROM STDIN BINARY"
+    ).await?;
+    let mut writer = pin!(BinaryCopyInWriter::new(
+        sink,
+        &[Type::UUID, Type::UUID, Type::TEXT, Type::TEXT, Type::INT4, Type::BYTEA],
+    ));
+    for (turn_index, turn) in turns.iter().enumerate() {
+        let (content, hash, original) = stored_content(&turn.actual_text);
+        let turn_index = turn_index as i32;
+        writer.as_mut().write(&[&turn.message_id, &turn.conversation_id, &content, &hash, &turn_index, &original]).await?;
+    }
+    writer.finish().await?;
+
+    let sink = client.copy_in(
+        "COPY staging_message_embeddings (message_id, embedding, embedding_model) FROM STDIN BINARY"
+    ).await?;
+    let mut writer = pin!(BinaryCopyInWriter::new(sink, &[Type::UUID, vector_type, Type::TEXT]));
+    for turn in turns {
+        let embedding_vec = Vector::from(turn.embedding.clone());
+        writer.as_mut().write(&[&turn.message_id, &embedding_vec, &turn.embedding_model_or_default()]).await?;
+    }
+    writer.finish().await?;
+
+    let mut written: HashSet<Uuid> = client.query(
+        "INSERT INTO ag_catalog.messages (message_id, conversation_id, content, content_hash, turn_index, original_content)
+         SELECT message_id, conversation_id, content, content_hash, turn_index, original_content FROM staging_messages
+         ON CONFLICT (message_id) DO UPDATE
+         SET content = EXCLUDED.content, content_hash = EXCLUDED.content_hash,
+             original_content = EXCLUDED.original_content
+         WHERE ag_catalog.messages.content_hash IS DISTINCT FROM EXCLUDED.content_hash
+         RETURNING message_id",
+        &[],
+    ).await?.iter().map(|row| row.get(0)).collect();
+
+    let embeddings_written = client.query(
+        "INSERT INTO ag_catalog.message_embeddings (message_id, embedding, embedding_model)
+         SELECT message_id, embedding, embedding_model FROM staging_message_embeddings
+         ON CONFLICT (message_id) DO UPDATE
+         SET embedding = EXCLUDED.embedding, embedding_model = EXCLUDED.embedding_model
+         WHERE ag_catalog.message_embeddings.embedding IS DISTINCT FROM EXCLUDED.embedding
+            OR ag_catalog.message_embeddings.embedding_model IS DISTINCT FROM EXCLUDED.embedding_model
+         RETURNING message_id",
+        &[],
+    ).await?;
+    written.extend(embeddings_written.iter().map(|row| row.get::<_, Uuid>(0)));
+
+    client.batch_execute("COMMIT").await?;
+
+    Ok(written.len())
+}
+
+/// Soft-delete a message: it's kept (so evidence ids on KG edges still point at a row)
+/// but retrieval no longer returns it. Returns false if there's no such message or it
+/// was already deleted. `kg_ops::prune_dangling_evidence` strips its id from edges.
+pub async fn delete_message(client: &Client, message_id: Uuid) -> Result<bool, Error> {
+    let updated = client.execute(
+        "UPDATE ag_catalog.messages SET deleted_at = NOW()
+         WHERE message_id = $1 AND deleted_at IS NULL",
+        &[&message_id],
+    ).await?;
+    Ok(updated > 0)
+}
+
+/// Default number of ids fetched per query by `get_messages_by_ids_ordered`
+pub const DEFAULT_MESSAGE_FETCH_CHUNK_SIZE: usize = 1000;
+
 /// Retrieve messages by their IDs, maintaining the order of input IDs
 pub async fn get_messages_by_ids_ordered(
     client: &Client,
     message_ids: &[Uuid],
 ) -> Result<Vec<Message>, Error> {
-    if message_ids.is_empty() {
+    get_messages_by_ids_chunked(client, message_ids, DEFAULT_MESSAGE_FETCH_CHUNK_SIZE).await
+}
+
+/// Same as `get_messages_by_ids_ordered`, querying `chunk_size` ids at a time so
+/// large evidence sets stay well under Postgres parameter limits. Results follow
+/// the first occurrence of each id; ids without a stored message, or whose message
+/// was soft-deleted, are skipped.
+pub async fn get_messages_by_ids_chunked(
+    client: &Client,
+    message_ids: &[Uuid],
+    chunk_size: usize,
+) -> Result<Vec<Message>, Error> {
+    let mut seen = HashSet::new();
+    let unique_ids: Vec<Uuid> = message_ids.iter().filter(|id| seen.insert(**id)).copied().collect();
+    if unique_ids.is_empty() {
         return Ok(Vec::new());
     }
 
+    let mut by_id: HashMap<Uuid, Message> = HashMap::with_capacity(unique_ids.len());
+    for chunk in unique_ids.chunks(chunk_size.max(1)) {
+        let rows = client.query(
+            "SELECT m.message_id, m.conversation_id, m.content, m.created_at
+             FROM ag_catalog.messages m
+             WHERE m.message_id = ANY($1::uuid[]) AND m.deleted_at IS NULL",
+            &[&chunk],
+        ).await?;
+        for row in rows {
+            let message = Message {
+                message_id: row.get(0),
+                conversation_id: row.get(1),
+                content: row.get(2),
+                created_at: row.get(3),
+            };
+            by_id.insert(message.message_id, message);
+        }
+    }
+
+    // Reassemble in request order (a hash lookup per id instead of array_position per row)
+    Ok(unique_ids.iter().filter_map(|id| by_id.remove(id)).collect())
+}
+
+/// Up to `window` messages before and after each of `message_ids` in its conversation,
+/// in conversation order (`created_at`, then `turn_index` within one ingest batch).
+/// Each neighbor comes with its offset from the message (negative before it);
+/// soft-deleted messages are skipped and don't count towards the window.
+pub async fn get_conversation_neighbors(
+    client: &Client,
+    message_ids: &[Uuid],
+    window: usize,
+) -> Result<HashMap<Uuid, Vec<(i64, Message)>>, Error> {
+    let mut neighbors: HashMap<Uuid, Vec<(i64, Message)>> = HashMap::new();
+    if message_ids.is_empty() || window == 0 {
+        return Ok(neighbors);
+    }
+    let rows = client.query(
+        "WITH ordered AS (
+             SELECT m.message_id, m.conversation_id, m.content, m.created_at,
+                    row_number() OVER (
+                        PARTITION BY m.conversation_id ORDER BY m.created_at, m.turn_index, m.message_id
+                    ) AS pos
+             FROM ag_catalog.messages m
+             WHERE m.deleted_at IS NULL AND m.conversation_id IN (
+                 SELECT conversation_id FROM ag_catalog.messages WHERE message_id = ANY($1::uuid[])
+             )
+         )
+         SELECT a.message_id, o.message_id, o.conversation_id, o.content, o.pos - a.pos, o.created_at
+         FROM ordered a
+         JOIN ordered o ON o.conversation_id = a.conversation_id
+             AND o.pos BETWEEN a.pos - $2 AND a.pos + $2 AND o.pos <> a.pos
+         WHERE a.message_id = ANY($1::uuid[])
+         ORDER BY a.message_id, o.pos",
+        &[&message_ids, &(window as i64)],
+    ).await?;
+    for row in rows {
+        let message = Message {
+            message_id: row.get(1),
+            conversation_id: row.get(2),
+            content: row.get(3),
+            created_at: row.get(5),
+        };
+        neighbors.entry(row.get(0)).or_default().push((row.get(4), message));
+    }
+    Ok(neighbors)
+}
+
+/// Stored embeddings of `message_ids`; messages without one are left out
+pub async fn get_message_embeddings(
+    client: &Client,
+    message_ids: &[Uuid],
+) -> Result<HashMap<Uuid, Vec<f32>>, Error> {
+    if message_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
     let rows = client.query(
-        "SELECT m.message_id, m.conversation_id, m.content
-         FROM messages m
-         WHERE m.message_id = ANY($1::uuid[])
-         ORDER BY array_position($1::uuid[], m.message_id)",
+        "SELECT message_id, embedding::vector FROM ag_catalog.message_embeddings
+         WHERE message_id = ANY($1::uuid[])",
         &[&message_ids],
     ).await?;
+    Ok(rows
+        .iter()
+        .map(|row| (row.get(0), row.get::<_, Vector>(1).to_vec()))
+        .collect())
+}
 
-    let messages = rows.iter().map(|row| Message {
-        message_id: row.get(0),
-        conversation_id: row.get(1),
-        content: row.get(2),
-    }).collect();
+/// Default cap on evidence messages resolved per edge
+pub const DEFAULT_MAX_EVIDENCE_PER_EDGE: usize = 5;
 
-    Ok(messages)
+/// Resolve the evidence ids of several edges to messages with a single query.
+/// Ids shared between edges are fetched once. Each edge gets at most `max_per_edge`
+/// messages in evidence order; ids without a stored message are skipped.
+pub async fn resolve_evidence_messages(
+    client: &Client,
+    evidence: &[Vec<Uuid>],
+    max_per_edge: usize,
+) -> Result<Vec<Vec<Message>>, Error> {
+    let mut seen = HashSet::new();
+    let unique_ids: Vec<Uuid> = evidence
+        .iter()
+        .flatten()
+        .filter(|id| seen.insert(**id))
+        .copied()
+        .collect();
+
+    let by_id: HashMap<Uuid, Message> = get_messages_by_ids_ordered(client, &unique_ids)
+        .await?
+        .into_iter()
+        .map(|m| (m.message_id, m))
+        .collect();
+
+    Ok(assign_evidence_messages(evidence, &by_id, max_per_edge))
+}
+
+/// Build each edge's evidence list from the fetched messages, dropping ids repeated
+/// within an edge and capping at `max_per_edge`
+pub fn assign_evidence_messages(
+    evidence: &[Vec<Uuid>],
+    by_id: &HashMap<Uuid, Message>,
+    max_per_edge: usize,
+) -> Vec<Vec<Message>> {
+    evidence
+        .iter()
+        .map(|ids| {
+            let mut seen = HashSet::new();
+            ids.iter()
+                .filter(|id| seen.insert(**id))
+                .filter_map(|id| by_id.get(id).cloned())
+                .take(max_per_edge)
+                .collect()
+        })
+        .collect()
 }
 
 /// Get messages with their similarity scores based on embedding similarity to a query
@@ -116,18 +483,35 @@ pub async fn get_similar_messages_by_embedding(
     client: &Client,
     query_embedding: &[f32],
     limit: i64,
+) -> Result<Vec<MessageWithRelevance>, Error> {
+    get_similar_messages_by_embedding_with_metric(client, query_embedding, limit, SimilarityMetric::Cosine, None).await
+}
+
+/// Same as `get_similar_messages_by_embedding`, ranking with the pgvector operator for `metric`
+/// and, when `embedding_model` is set, only considering vectors from that model
+pub async fn get_similar_messages_by_embedding_with_metric(
+    client: &Client,
+    query_embedding: &[f32],
+    limit: i64,
+    metric: SimilarityMetric,
+    embedding_model: Option<&str>,
 ) -> Result<Vec<MessageWithRelevance>, Error> {
     let embedding_vec = Vector::from(query_embedding.to_vec());
+    let query = Config::global().vector_storage.query_param("$1");
 
-    let rows = client.query(
+    let sql = format!(
         "SELECT m.message_id, m.conversation_id, m.content,
-                1 - (me.embedding <=> $1) as similarity
+                {} as similarity
          FROM ag_catalog.messages m
          JOIN ag_catalog.message_embeddings me ON m.message_id = me.message_id
-         ORDER BY me.embedding <=> $1
+         WHERE m.deleted_at IS NULL AND ($3::text IS NULL OR me.embedding_model = $3)
+         ORDER BY me.embedding {} {}
          LIMIT $2",
-        &[&embedding_vec, &limit],
-    ).await?;
+        metric.pg_similarity_sql("me.embedding", &query),
+        metric.pg_operator(),
+        query,
+    );
+    let rows = client.query(&sql, &[&embedding_vec, &limit, &embedding_model]).await?;
 
     let messages = rows.iter().map(|row| {
         let similarity: f64 = row.get(3);
@@ -136,6 +520,7 @@ pub async fn get_similar_messages_by_embedding(
             conversation_id: row.get(1),
             content: row.get(2),
             relevance_score: similarity as f32,
+            source: RetrievalSource::Embedding,
         }
     }).collect();
 
@@ -148,6 +533,8 @@ pub async fn search_messages_by_keywords(
     keywords: &[String],
     limit: i64,
 ) -> Result<Vec<MessageWithRelevance>, Error> {
+    // A blank keyword would make an invalid tsquery, or an ILIKE that matches everything
+    let keywords: Vec<&str> = keywords.iter().map(|kw| kw.trim()).filter(|kw| !kw.is_empty()).collect();
     if keywords.is_empty() {
         return Ok(Vec::new());
     }
@@ -163,7 +550,7 @@ pub async fn search_messages_by_keywords(
         "SELECT message_id, conversation_id, content,
                 ts_rank(content_tsv, to_tsquery('english', $1), 1) as rank
          FROM ag_catalog.messages 
-         WHERE content_tsv @@ to_tsquery('english', $1)
+         WHERE content_tsv @@ to_tsquery('english', $1) AND deleted_at IS NULL
          ORDER BY rank DESC
          LIMIT $2",
         &[&query_string, &limit],
@@ -176,6 +563,7 @@ pub async fn search_messages_by_keywords(
             conversation_id: row.get(1),
             content: row.get(2),
             relevance_score: rank, // BM25-style rank from ts_rank_cd
+            source: RetrievalSource::Keyword,
         }
     }).collect();
     
@@ -188,6 +576,8 @@ pub async fn search_messages_by_keywords_simple(
     keywords: &[String],
     limit: i64,
 ) -> Result<Vec<Message>, Error> {
+    // A blank keyword would make an invalid tsquery, or an ILIKE that matches everything
+    let keywords: Vec<&str> = keywords.iter().map(|kw| kw.trim()).filter(|kw| !kw.is_empty()).collect();
     if keywords.is_empty() {
         return Ok(Vec::new());
     }
@@ -198,9 +588,9 @@ pub async fn search_messages_by_keywords_simple(
         .collect();
     
     let rows = client.query(
-        "SELECT message_id, conversation_id, content 
+        "SELECT message_id, conversation_id, content, created_at
          FROM ag_catalog.messages 
-         WHERE content ILIKE ANY($1)
+         WHERE content ILIKE ANY($1) AND deleted_at IS NULL
          LIMIT $2",
         &[&patterns, &limit],
     ).await?;
@@ -209,13 +599,14 @@ pub async fn search_messages_by_keywords_simple(
         message_id: row.get(0),
         conversation_id: row.get(1),
         content: row.get(2),
+        created_at: row.get(3),
     }).collect();
     
     Ok(messages)
 }
 
 /// Expand query with synonyms and related terms for better BM25 coverage
-fn expand_query_keywords(keywords: &[String]) -> Vec<String> {
+pub fn expand_query_keywords(keywords: &[String]) -> Vec<String> {
     let mut expanded = keywords.to_vec();
     
     // Common programming/tech synonyms
@@ -251,86 +642,286 @@ fn expand_query_keywords(keywords: &[String]) -> Vec<String> {
     expanded
 }
 
+/// How long a keyword's document frequency is reused before it is recounted
+pub const KEYWORD_DF_CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// Most keywords whose document frequency is kept; expired counts are pruned first
+pub const KEYWORD_DF_CACHE_CAPACITY: usize = 10_000;
+
+/// Lowercased keyword -> (counted at, corpus size, messages containing it)
+type DocumentFrequencyCache = Mutex<HashMap<String, (Instant, i64, i64)>>;
+
+fn document_frequency_cache() -> &'static DocumentFrequencyCache {
+    static CACHE: OnceLock<DocumentFrequencyCache> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Inverse document frequency of a term found in `doc_freq` of `total_docs` documents.
+/// This is BM25's smoothed form, which stays positive, so a term found everywhere
+/// still counts for a little.
+pub fn idf_weight(doc_freq: i64, total_docs: i64) -> f32 {
+    let total = total_docs.max(0);
+    let (n, df) = (total as f32, doc_freq.clamp(0, total) as f32);
+    (1.0 + (n - df + 0.5) / (df + 0.5)).ln()
+}
+
+/// Corpus size and the number of messages containing each keyword (lowercased),
+/// counted in one query against the `content_tsv` GIN index and cached for
+/// `KEYWORD_DF_CACHE_TTL`. A stopword is counted as found in every message.
+pub async fn keyword_document_frequencies(
+    client: &Client,
+    keywords: &[String],
+) -> Result<(i64, HashMap<String, i64>), Error> {
+    let mut frequencies = HashMap::new();
+    let mut total_docs = 0;
+    let mut missing = Vec::new();
+    {
+        let cache = document_frequency_cache().lock().unwrap();
+        for kw in keywords.iter().map(|k| k.to_lowercase()).collect::<HashSet<_>>() {
+            match cache.get(&kw) {
+                Some((counted_at, total, df)) if counted_at.elapsed() < KEYWORD_DF_CACHE_TTL => {
+                    total_docs = total_docs.max(*total);
+                    frequencies.insert(kw, *df);
+                }
+                _ => missing.push(kw),
+            }
+        }
+    }
+    if missing.is_empty() {
+        return Ok((total_docs, frequencies));
+    }
+
+    let rows = client.query(
+        "SELECT kw,
+                CASE WHEN numnode(q) = 0 THEN t.total ELSE (
+                    SELECT COUNT(*) FROM ag_catalog.messages m
+                    WHERE m.content_tsv @@ q AND m.deleted_at IS NULL
+                ) END,
+                t.total
+         FROM unnest($1::text[]) AS kw
+         CROSS JOIN LATERAL plainto_tsquery('english', kw) AS q
+         CROSS JOIN (SELECT COUNT(*) AS total FROM ag_catalog.messages WHERE deleted_at IS NULL) t",
+        &[&missing],
+    ).await?;
+
+    let now = Instant::now();
+    let mut cache = document_frequency_cache().lock().unwrap();
+    if cache.len() + rows.len() > KEYWORD_DF_CACHE_CAPACITY {
+        cache.retain(|_, (counted_at, _, _)| counted_at.elapsed() < KEYWORD_DF_CACHE_TTL);
+        if cache.len() + rows.len() > KEYWORD_DF_CACHE_CAPACITY {
+            cache.clear();
+        }
+    }
+    for row in rows {
+        let (kw, df, total): (String, i64, i64) = (row.get(0), row.get(1), row.get(2));
+        cache.insert(kw.clone(), (now, total, df));
+        total_docs = total;
+        frequencies.insert(kw, df);
+    }
+    Ok((total_docs, frequencies))
+}
+
+/// IDF weight of each keyword, in order. If the counts can't be read every keyword
+/// weighs the same.
+async fn keyword_idf_weights(client: &Client, keywords: &[String]) -> Vec<f32> {
+    match keyword_document_frequencies(client, keywords).await {
+        Ok((total_docs, frequencies)) => keywords
+            .iter()
+            .map(|kw| idf_weight(frequencies.get(&kw.to_lowercase()).copied().unwrap_or(0), total_docs))
+            .collect(),
+        Err(e) => {
+            tracing::warn!(error = %e, "Could not count keyword document frequencies; weighting keywords equally");
+            vec![1.0; keywords.len()]
+        }
+    }
+}
+
+/// Length in bytes of the prefix of `text` equal to `needle` ignoring case, if there is one
+fn caseless_prefix_len(text: &str, needle: &str) -> Option<usize> {
+    let mut text_chars = text.char_indices();
+    for n in needle.chars() {
+        let (_, t) = text_chars.next()?;
+        if !t.to_lowercase().eq(n.to_lowercase()) {
+            return None;
+        }
+    }
+    Some(text_chars.next().map_or(text.len(), |(i, _)| i))
+}
+
+/// Byte ranges `[start, end)` of `content` where any of `keywords` appears, ignoring
+/// case. Overlapping and touching matches are merged, so ranges are sorted and disjoint.
+pub fn keyword_highlights(content: &str, keywords: &[String]) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    for keyword in keywords.iter().filter(|k| !k.is_empty()) {
+        for (start, _) in content.char_indices() {
+            if let Some(len) = caseless_prefix_len(&content[start..], keyword) {
+                ranges.push((start, start + len));
+            }
+        }
+    }
+    ranges.sort_unstable();
+
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Optional per-message details computed by hybrid search, and how its results combine
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HybridSearchExtras {
+    /// Record why each returned message scored what it did
+    pub explain: bool,
+    /// Record where the query keywords appear in each returned message
+    pub highlight: bool,
+    /// Return only messages found by both keyword and embedding search (see `intersect_results`)
+    pub require_both: bool,
+}
+
+/// How many tokens a stop-word-only query falls back to searching on
+pub const STOP_WORD_FALLBACK_KEYWORDS: usize = 2;
+
+/// Significant query terms: punctuation trimmed, words of 1-2 characters and
+/// `stop_words` dropped
+pub fn extract_query_keywords(query: &str, stop_words: &HashSet<String>) -> Vec<String> {
+    query_tokens(query)
+        .filter(|w| !stop_words.contains(w.to_lowercase().as_str()))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Keywords for a query made only of stop words: its `STOP_WORD_FALLBACK_KEYWORDS`
+/// longest distinct tokens (earlier ones first on ties)
+pub fn stop_word_fallback_keywords(query: &str) -> Vec<String> {
+    let mut tokens: Vec<&str> = Vec::new();
+    for token in query_tokens(query) {
+        if !tokens.iter().any(|t| t.eq_ignore_ascii_case(token)) {
+            tokens.push(token);
+        }
+    }
+    tokens.sort_by_key(|t| std::cmp::Reverse(t.len()));
+    tokens.into_iter().take(STOP_WORD_FALLBACK_KEYWORDS).map(str::to_string).collect()
+}
+
+/// The keywords hybrid search scores coverage on: `extract_query_keywords` with the
+/// configured stop words, or `stop_word_fallback_keywords` for a query of nothing but stop words
+pub fn query_search_keywords(query: &str) -> Vec<String> {
+    let keywords = extract_query_keywords(query, &Config::global().stop_words);
+    if !keywords.is_empty() {
+        return keywords;
+    }
+    // Nothing but stop words: search on the longest tokens rather than skip BM25
+    let keywords = stop_word_fallback_keywords(query);
+    tracing::info!(query, fallback_keywords = ?keywords, "Stop-word-only query, falling back to its longest tokens");
+    keywords
+}
+
+/// Whitespace-separated words of at least 3 characters once punctuation is trimmed
+fn query_tokens(query: &str) -> impl Iterator<Item = &str> {
+    query
+        .split_whitespace()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|w| w.len() > 2)
+}
+
+/// Hybrid search results, with a scoring breakdown and keyword highlights per
+/// returned message when requested
+#[derive(Debug, Default)]
+pub struct HybridSearchResults {
+    pub messages: Vec<MessageWithRelevance>,
+    /// Empty unless `explain` was set
+    pub explanations: HashMap<Uuid, ScoreExplanation>,
+    /// Byte ranges of the original (non-expanded) query keywords in each message's
+    /// content; empty unless `highlight` was set
+    pub highlights: HashMap<Uuid, Vec<(usize, usize)>>,
+}
+
 /// Hybrid search: Combine keyword search + embedding search with smart prioritization
 pub async fn hybrid_search_messages(
     client: &Client,
     query: &str,
     query_embedding: &[f32],
     top_k: i64,
+    fusion: FusionStrategy,
+    embedding_model: Option<&str>,
 ) -> Result<Vec<MessageWithRelevance>, Error> {
+    hybrid_search_messages_with_explanations(client, query, query_embedding, top_k, fusion, embedding_model, HybridSearchExtras::default())
+        .await
+        .map(|r| r.messages)
+}
+
+/// Same as `hybrid_search_messages`, also computing the details selected in `extras`
+/// for each returned message. Nothing extra is computed otherwise.
+pub async fn hybrid_search_messages_with_explanations(
+    client: &Client,
+    query: &str,
+    query_embedding: &[f32],
+    top_k: i64,
+    fusion: FusionStrategy,
+    embedding_model: Option<&str>,
+    extras: HybridSearchExtras,
+) -> Result<HybridSearchResults, Error> {
+    if query.trim().is_empty() {
+        return Ok(HybridSearchResults::default());
+    }
+
+    let explain = extras.explain;
+    let mut keyword_explanations: HashMap<Uuid, ScoreExplanation> = HashMap::new();
+    let mut embedding_similarities: HashMap<Uuid, f32> = HashMap::new();
     let mut message_ids = HashSet::new();
-    let mut results = Vec::new();
+    let mut rejected_ids = HashSet::new();
+    let mut keyword_results = Vec::new();
 
     // Strategy 1: Extract meaningful keywords from query
-    // Filter out common stop words and keep only significant terms
-    let stop_words = [
-        // Common English stop words
-        "the", "and", "for", "with", "from", "this", "that", "what", "how",
-        "are", "was", "were", "been", "being", "have", "has", "had", "does",
-        "did", "will", "would", "could", "should", "may", "might", "must",
-        "can", "about", "into", "through", "during", "before", "after",
-        "above", "below", "between", "under", "again", "further", "then",
-        "once", "here", "there", "when", "where", "why", "all", "any",
-        "both", "each", "few", "more", "most", "other", "some", "such",
-        "only", "own", "same", "than", "too", "very", "just", "but",
-        // Conversational filler words
-        "hey", "hello", "hi", "please", "thanks", "thank", "you", "your",
-        "want", "need", "help", "tell", "show", "give", "get", "make",
-        "called", "named", "like", "know", "think", "see", "look",
-        // Question words
-        "who", "whom", "which", "whose",
-        // Common verbs that add little meaning
-        "doing", "done", "going", "gone", "come", "came",
-    ];
-    let keywords: Vec<String> = query
-        .split_whitespace()
-        .filter(|w| w.len() > 2) // Skip very short words
-        .filter(|w| !stop_words.contains(&w.to_lowercase().as_str())) // Skip stop words
-        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()).to_string()) // Remove punctuation
-        .filter(|w| !w.is_empty()) // Remove empty strings after trimming
-        .collect();
-    
-    println!("  Extracted keywords: {:?}", keywords);
+    let keywords = query_search_keywords(query);
+    tracing::debug!(keywords = ?keywords, "Extracted keywords");
     
     // Expand keywords for better coverage
     let expanded_keywords = expand_query_keywords(&keywords);
-    println!("  Expanded to: {:?}", expanded_keywords);
+    tracing::debug!(expanded_keywords = ?expanded_keywords, "Expanded keywords");
     
     // Strategy 2: BM25 Full-Text Search with expanded keywords
     let mut keyword_count = 0;
     if !expanded_keywords.is_empty() {
         if let Ok(keyword_messages) = search_messages_by_keywords(client, &expanded_keywords, top_k * 3).await {
             keyword_count = keyword_messages.len();
-            println!("  BM25 search found {} messages", keyword_count);
+            tracing::debug!(result_count = keyword_count, "BM25 search found messages");
             
+            // Rare keywords say more about relevance than common ones
+            let weights = keyword_idf_weights(client, &keywords).await;
+            // The rarest keyword (ties go to the longer one) is the most specific
+            let specific_keyword = keywords.iter()
+                .zip(&weights)
+                .max_by(|(a, wa), (b, wb)| wa.total_cmp(wb).then(a.len().cmp(&b.len())))
+                .map(|(k, _)| k.to_lowercase());
+            tracing::debug!(weights = ?weights, specific_keyword = ?specific_keyword, "Keyword IDF weights");
+
             // Calculate keyword coverage for relevance filtering
             for msg in keyword_messages {
                 if message_ids.insert(msg.message_id) {
                     let content_lower = msg.content.to_lowercase();
-                    let mut original_keyword_matches = 0;
                     
-                    // Check coverage of ORIGINAL keywords (not expanded)
-                    // Prioritize rare/specific keywords (longer = more specific)
+                    // Check coverage of ORIGINAL keywords (not expanded), weighted by IDF
                     let mut weighted_matches = 0.0;
                     let mut total_weight = 0.0;
-                    let mut has_longest_keyword = false;
-                    
-                    // Find the longest keyword (most specific)
-                    let longest_keyword = keywords.iter()
-                        .max_by_key(|k| k.len())
-                        .map(|k| k.to_lowercase());
+                    let mut has_specific_keyword = false;
+                    let mut matched_keywords = Vec::new();
                     
-                    for kw in &keywords {
-                        // Weight by length: longer keywords are more specific
-                        let weight = (kw.len() as f32).max(1.0);
+                    for (kw, &weight) in keywords.iter().zip(&weights) {
                         total_weight += weight;
                         
                         if content_lower.contains(&kw.to_lowercase()) {
                             weighted_matches += weight;
+                            if explain {
+                                matched_keywords.push(kw.clone());
+                            }
                             
-                            // Check if this is the longest keyword
-                            if Some(kw.to_lowercase()) == longest_keyword {
-                                has_longest_keyword = true;
+                            if Some(kw.to_lowercase()) == specific_keyword {
+                                has_specific_keyword = true;
                             }
                         }
                     }
@@ -341,51 +932,300 @@ pub async fn hybrid_search_messages(
                         0.0 
                     };
                     
-                    // STRICT: Must contain the longest (most specific) keyword
+                    // STRICT: Must contain the most specific keyword
                     // OR have decent BM25 score (>0.01) with good coverage (>50%)
-                    // BUT: If longest keyword is very specific (>8 chars), it MUST be present
-                    let longest_keyword_len = longest_keyword.as_ref().map(|k| k.len()).unwrap_or(0);
-                    let require_longest = longest_keyword_len > 8; // Specific terms like "editdistance" (13 chars)
+                    // BUT: If that keyword is also long (>8 chars), it MUST be present
+                    let specific_keyword_len = specific_keyword.as_ref().map(|k| k.len()).unwrap_or(0);
+                    let require_specific = specific_keyword_len > 8; // Terms like "editdistance" (13 chars)
                     
-                    if has_longest_keyword || (!require_longest && (msg.relevance_score > 0.01 && coverage >= 0.5)) {
+                    if has_specific_keyword || (!require_specific && (msg.relevance_score > 0.01 && coverage >= 0.5)) {
                         let mut boosted_msg = msg;
                         // Boost based on coverage: 20% = 1.5x, 100% = 3.0x
                         // Higher boost for better coverage: 40% = 2.0x, 100% = 4.0x
                         let boost = 2.0 + (coverage * 2.0);
+                        if explain {
+                            keyword_explanations.insert(boosted_msg.message_id, ScoreExplanation {
+                                keyword_coverage: Some(coverage),
+                                matched_keywords,
+                                bm25_rank: Some(boosted_msg.relevance_score),
+                                boost,
+                                ..Default::default()
+                            });
+                        }
                         boosted_msg.relevance_score *= boost;
-                        println!("    ✓ Message (weighted coverage: {:.0}%, boost: {:.1}x)", coverage * 100.0, boost);
-                        results.push(boosted_msg);
+                        tracing::trace!(message_id = %boosted_msg.message_id, coverage, boost, "✓ Keyword match kept");
+                        keyword_results.push(boosted_msg);
                     } else {
-                        println!("    ⚠️  Filtered out (weighted coverage: {:.0}%, score: {:.2})", coverage * 100.0, msg.relevance_score);
+                        tracing::trace!(message_id = %msg.message_id, coverage, score = msg.relevance_score, "Keyword match filtered out");
+                        rejected_ids.insert(msg.message_id);
                     }
                 }
             }
         }
     }
-    // This prevents poor-quality embeddings from polluting good keyword results
-    if keyword_count < (top_k as usize) {
-        let remaining = top_k - (keyword_count as i64);
-        if let Ok(embedding_messages) = get_similar_messages_by_embedding(client, query_embedding, remaining).await {
-            println!("  Embedding search found {} additional messages", embedding_messages.len());
-            for msg in embedding_messages {
-                if message_ids.insert(msg.message_id) {
-                    // Downweight embedding scores to prioritize keyword matches
-                    let mut adjusted_msg = msg;
-                    adjusted_msg.relevance_score *= 0.8; // 20% penalty for embedding-only matches
-                    results.push(adjusted_msg);
+    let mut results = if extras.require_both {
+        // High precision: both searches run in full and only their common messages are kept
+        let mut embedding_results = get_similar_messages_by_embedding_with_metric(client, query_embedding, top_k * 3, SimilarityMetric::Cosine, embedding_model)
+            .await
+            .unwrap_or_default();
+        tracing::debug!(result_count = embedding_results.len(), "Embedding search found messages");
+        embedding_results.retain(|m| !rejected_ids.contains(&m.message_id));
+        if explain {
+            embedding_similarities.extend(embedding_results.iter().map(|m| (m.message_id, m.relevance_score)));
+        }
+        let (keyword_matches, embedding_matches) = (keyword_results.len(), embedding_results.len());
+        let common = intersect_results(keyword_results, embedding_results, fusion);
+        tracing::debug!(keyword_matches, embedding_matches, common = common.len(), "Kept messages found by both searches");
+        common
+    } else {
+        match fusion {
+            FusionStrategy::Weighted => {
+                // Only fall back to embeddings when keywords found too little.
+                // This prevents poor-quality embeddings from polluting good keyword results
+                let mut embedding_results = Vec::new();
+                if keyword_count < (top_k as usize) {
+                    let remaining = top_k - (keyword_count as i64);
+                    if let Ok(embedding_messages) = get_similar_messages_by_embedding_with_metric(client, query_embedding, remaining, SimilarityMetric::Cosine, embedding_model).await {
+                        tracing::debug!(result_count = embedding_messages.len(), "Embedding search found additional messages");
+                        embedding_results = embedding_messages;
+                    }
+                } else {
+                    tracing::debug!("Skipping embedding search (keyword search found enough results)");
+                }
+                embedding_results.retain(|m| !rejected_ids.contains(&m.message_id));
+                if explain {
+                    embedding_similarities.extend(embedding_results.iter().map(|m| (m.message_id, m.relevance_score)));
+                }
+                weighted_fusion(keyword_results, embedding_results)
+            }
+            FusionStrategy::Rrf => {
+                // RRF needs a full ranked list from both searches
+                let mut embedding_results = get_similar_messages_by_embedding_with_metric(client, query_embedding, top_k * 3, SimilarityMetric::Cosine, embedding_model)
+                    .await
+                    .unwrap_or_default();
+                tracing::debug!(result_count = embedding_results.len(), "Embedding search found messages");
+                embedding_results.retain(|m| !rejected_ids.contains(&m.message_id));
+                if explain {
+                    embedding_similarities.extend(embedding_results.iter().map(|m| (m.message_id, m.relevance_score)));
                 }
+                keyword_results.sort_by(|a, b| score_order(a.relevance_score, b.relevance_score));
+                reciprocal_rank_fusion(&[keyword_results, embedding_results], RRF_K)
             }
         }
+    };
+    
+    // Limit to top_k
+    results.truncate(top_k as usize);
+
+    let explanations = if explain {
+        results.iter().map(|msg| {
+            let mut explanation = keyword_explanations.remove(&msg.message_id).unwrap_or_else(|| ScoreExplanation {
+                boost: match fusion {
+                    FusionStrategy::Weighted => EMBEDDING_ONLY_WEIGHT,
+                    FusionStrategy::Rrf => 1.0,
+                },
+                ..Default::default()
+            });
+            explanation.embedding_similarity = embedding_similarities.get(&msg.message_id).copied();
+            explanation.final_score = msg.relevance_score;
+            (msg.message_id, explanation)
+        }).collect()
+    } else {
+        HashMap::new()
+    };
+
+    let highlights = if extras.highlight {
+        results.iter().map(|msg| (msg.message_id, keyword_highlights(&msg.content, &keywords))).collect()
     } else {
-        println!("  Skipping embedding search (keyword search found enough results)");
+        HashMap::new()
+    };
+
+    Ok(HybridSearchResults { messages: results, explanations, highlights })
+}
+
+/// How keyword and embedding result lists are merged in hybrid search
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FusionStrategy {
+    /// Coverage-boosted keyword scores plus down-weighted embedding scores
+    #[default]
+    Weighted,
+    /// Reciprocal Rank Fusion over the two ranked lists
+    Rrf,
+}
+
+impl FusionStrategy {
+    /// Parse the `fusion` request parameter ("weighted" or "rrf")
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "weighted" => Some(FusionStrategy::Weighted),
+            "rrf" => Some(FusionStrategy::Rrf),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FusionStrategy::Weighted => "weighted",
+            FusionStrategy::Rrf => "rrf",
+        }
+    }
+}
+
+/// Retrieval backend for `POST /query/messages/search`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MessageSearchMode {
+    /// BM25 full-text search on the query's expanded keywords, unfiltered and unboosted
+    Keyword,
+    /// Cosine nearest neighbors of the query embedding
+    Embedding,
+    /// The fused search `/query/llm-context` uses
+    #[default]
+    Hybrid,
+}
+
+impl MessageSearchMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "keyword" => Some(MessageSearchMode::Keyword),
+            "embedding" => Some(MessageSearchMode::Embedding),
+            "hybrid" => Some(MessageSearchMode::Hybrid),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageSearchMode::Keyword => "keyword",
+            MessageSearchMode::Embedding => "embedding",
+            MessageSearchMode::Hybrid => "hybrid",
+        }
+    }
+
+    /// Whether the query has to be embedded first
+    pub fn needs_embedding(&self) -> bool {
+        !matches!(self, MessageSearchMode::Keyword)
+    }
+}
+
+/// Run a single retrieval backend and return its results as is, before any fusion.
+/// `query_embedding` is ignored in `Keyword` mode.
+pub async fn search_messages_with_mode(
+    client: &Client,
+    mode: MessageSearchMode,
+    query: &str,
+    query_embedding: &[f32],
+    top_k: i64,
+    embedding_model: Option<&str>,
+) -> Result<Vec<MessageWithRelevance>, Error> {
+    match mode {
+        MessageSearchMode::Keyword => {
+            let keywords = expand_query_keywords(&query_search_keywords(query));
+            search_messages_by_keywords(client, &keywords, top_k).await
+        }
+        MessageSearchMode::Embedding => {
+            get_similar_messages_by_embedding_with_metric(client, query_embedding, top_k, SimilarityMetric::Cosine, embedding_model).await
+        }
+        MessageSearchMode::Hybrid => {
+            hybrid_search_messages(client, query, query_embedding, top_k, FusionStrategy::default(), embedding_model).await
+        }
+    }
+}
+
+/// Standard RRF damping constant
+pub const RRF_K: f32 = 60.0;
+
+/// Weighted fusion multiplier for messages found only by embedding search
+pub const EMBEDDING_ONLY_WEIGHT: f32 = 0.8;
+
+/// Merge keyword and embedding results by raw score.
+/// Keyword matches keep their (boosted) score; embedding-only matches take a 20% penalty.
+pub fn weighted_fusion(
+    keyword_results: Vec<MessageWithRelevance>,
+    embedding_results: Vec<MessageWithRelevance>,
+) -> Vec<MessageWithRelevance> {
+    let mut results = keyword_results;
+    for msg in embedding_results {
+        if let Some(existing) = results.iter_mut().find(|m| m.message_id == msg.message_id) {
+            existing.source = existing.source.merge(msg.source);
+        } else {
+            // Downweight embedding scores to prioritize keyword matches
+            let mut adjusted_msg = msg;
+            adjusted_msg.relevance_score *= EMBEDDING_ONLY_WEIGHT; // 20% penalty for embedding-only matches
+            results.push(adjusted_msg);
+        }
     }
     
     // Sort by relevance score (keyword matches first, then by embedding similarity)
-    results.sort_by(|a, b| b.relevance_score.partial_cmp(&a.relevance_score).unwrap());
+    results.sort_by(|a, b| score_order(a.relevance_score, b.relevance_score));
+    results
+}
+
+/// Messages found by both keyword and embedding search, the high-precision dual of
+/// the fusions' union (`require_both`). With `Weighted`, a message scores its boosted
+/// keyword score plus its embedding similarity; with `Rrf`, the reciprocal ranks of its
+/// positions in the two lists, ranked among the common messages only.
+pub fn intersect_results(
+    keyword_results: Vec<MessageWithRelevance>,
+    embedding_results: Vec<MessageWithRelevance>,
+    fusion: FusionStrategy,
+) -> Vec<MessageWithRelevance> {
+    let keyword_ids: HashSet<Uuid> = keyword_results.iter().map(|m| m.message_id).collect();
+    let embedding_scores: HashMap<Uuid, f32> = embedding_results.iter().map(|m| (m.message_id, m.relevance_score)).collect();
+    let mut common: Vec<MessageWithRelevance> = keyword_results
+        .into_iter()
+        .filter(|m| embedding_scores.contains_key(&m.message_id))
+        .collect();
+    match fusion {
+        FusionStrategy::Weighted => {
+            for msg in &mut common {
+                msg.relevance_score += embedding_scores[&msg.message_id];
+                msg.source = msg.source.merge(RetrievalSource::Embedding);
+            }
+            common.sort_by(|a, b| score_order(a.relevance_score, b.relevance_score));
+            common
+        }
+        FusionStrategy::Rrf => {
+            common.sort_by(|a, b| score_order(a.relevance_score, b.relevance_score));
+            let embedding_common: Vec<MessageWithRelevance> = embedding_results
+                .into_iter()
+                .filter(|m| keyword_ids.contains(&m.message_id))
+                .collect();
+            reciprocal_rank_fusion(&[common, embedding_common], RRF_K)
+        }
+    }
+}
+
+/// Reciprocal Rank Fusion: each message scores `sum(1 / (k + rank))` over the
+/// ranked lists it appears in (rank is 1-based). Only positions matter, so the
+/// lists' score scales never need to be comparable.
+pub fn reciprocal_rank_fusion(
+    ranked_lists: &[Vec<MessageWithRelevance>],
+    k: f32,
+) -> Vec<MessageWithRelevance> {
+    let mut index: HashMap<Uuid, usize> = HashMap::new();
+    let mut results: Vec<MessageWithRelevance> = Vec::new();
     
-    // Limit to top_k
-    results.truncate(top_k as usize);
+    for list in ranked_lists {
+        for (rank, msg) in list.iter().enumerate() {
+            let contribution = 1.0 / (k + (rank + 1) as f32);
+            match index.get(&msg.message_id) {
+                Some(&pos) => {
+                    let existing = &mut results[pos];
+                    existing.relevance_score += contribution;
+                    existing.source = existing.source.merge(msg.source);
+                }
+                None => {
+                    index.insert(msg.message_id, results.len());
+                    let mut fused = msg.clone();
+                    fused.relevance_score = contribution;
+                    results.push(fused);
+                }
+            }
+        }
+    }
     
-    Ok(results)
+    // Stable sort keeps first-seen order for ties
+    results.sort_by(|a, b| score_order(a.relevance_score, b.relevance_score));
+    results
 }
 
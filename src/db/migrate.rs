@@ -0,0 +1,342 @@
+//! Versioned schema migrations, applied explicitly (`cargo run --bin migrate`, and once
+//! at service startup) instead of on every connection. Applied versions are recorded in
+//! `ag_catalog.schema_migrations`, so running them again is a no-op.
+//!
+//! Every statement is written to succeed on a database whose schema was created by an
+//! older release (which built it on connect and recorded nothing), so the first run
+//! against one only records the versions.
+
+use anyhow::{Context, Result};
+use tokio_postgres::Client;
+
+use crate::config::Config;
+use crate::db::vector_index::{self, VectorStorage};
+
+/// A schema change, applied once in its own transaction
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+    /// The statements; new vector columns use the configured `VECTOR_STORAGE`
+    sql: fn(VectorStorage) -> String,
+}
+
+/// Every migration, in version order. Append new ones; never edit an applied one.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, name: "edge_embeddings", sql: edge_embeddings_sql },
+    Migration { version: 2, name: "messages", sql: messages_sql },
+    Migration { version: 3, name: "knowledge_graph", sql: knowledge_graph_sql },
+    Migration { version: 4, name: "embeddings_dim", sql: embeddings_dim_sql },
+    Migration { version: 5, name: "message_turn_index", sql: message_turn_index_sql },
+    Migration { version: 6, name: "message_original_content", sql: message_original_content_sql },
+];
+
+/// Application tables every migrated database has (all in `ag_catalog`)
+pub const TABLES: &[&str] = &[
+    "schema_migrations",
+    "embeddings",
+    "sessions",
+    "ingest_metadata",
+    "edge_evidence",
+    "conversations",
+    "messages",
+    "message_embeddings",
+    "kg_nodes",
+    "kg_edges",
+    "kg_edge_embeddings",
+];
+
+/// Outcome of `run`
+#[derive(Debug, Clone, Default)]
+pub struct MigrationReport {
+    /// Versions applied by this run, in order
+    pub applied: Vec<(i32, &'static str)>,
+    /// Versions that were already recorded
+    pub already_applied: usize,
+    /// Whether the Apache AGE extension could be created and the graph set up
+    pub age_available: bool,
+}
+
+/// Apply the pending migrations, then bring the configuration-dependent parts of the
+/// schema (AGE graph, vector column types, ANN indexes) in line with `cfg`. Holds an
+/// advisory lock throughout, so concurrent runs (e.g. two service replicas starting)
+/// apply each version once.
+pub async fn run(client: &mut Client, cfg: &Config) -> Result<MigrationReport> {
+    client
+        .batch_execute(
+            "CREATE SCHEMA IF NOT EXISTS ag_catalog;
+             CREATE TABLE IF NOT EXISTS ag_catalog.schema_migrations (
+                 version INTEGER PRIMARY KEY,
+                 name TEXT NOT NULL,
+                 applied_at TIMESTAMP DEFAULT NOW()
+             );",
+        )
+        .await?;
+
+    client.execute("SELECT pg_advisory_lock(hashtext('rust_ingester.migrate'))", &[]).await?;
+    let result = apply(client, cfg).await;
+    client.execute("SELECT pg_advisory_unlock(hashtext('rust_ingester.migrate'))", &[]).await?;
+    result
+}
+
+async fn apply(client: &mut Client, cfg: &Config) -> Result<MigrationReport> {
+    let mut report = MigrationReport::default();
+
+    let applied: Vec<i32> = client
+        .query("SELECT version FROM ag_catalog.schema_migrations", &[])
+        .await?
+        .iter()
+        .map(|row| row.get(0))
+        .collect();
+
+    for migration in MIGRATIONS {
+        if applied.contains(&migration.version) {
+            report.already_applied += 1;
+            continue;
+        }
+        tracing::info!(version = migration.version, name = migration.name, "Applying migration...");
+        let tx = client.transaction().await?;
+        tx.batch_execute(&(migration.sql)(cfg.vector_storage))
+            .await
+            .with_context(|| format!("migration {} ({})", migration.version, migration.name))?;
+        tx.execute(
+            "INSERT INTO ag_catalog.schema_migrations (version, name) VALUES ($1, $2)",
+            &[&migration.version, &migration.name],
+        )
+        .await?;
+        tx.commit().await?;
+        report.applied.push((migration.version, migration.name));
+    }
+
+    report.age_available = ensure_graph_schema(client, cfg).await;
+
+    // Tables created with the other VECTOR_STORAGE are converted (and lose their ANN
+    // indexes, recreated below)
+    vector_index::ensure_vector_storage(client, cfg.vector_storage).await?;
+    client.batch_execute(&vector_index::kg_edge_index_sql(cfg.vector_storage)).await?;
+    // ANN index on message embeddings, of the type and parameters in VECTOR_INDEX
+    vector_index::ensure_message_embedding_index(client, &cfg.vector_index, cfg.vector_storage).await?;
+
+    Ok(report)
+}
+
+/// Create the AGE extension, the configured graph and the common vertex labels.
+/// AGE is optional: without it the knowledge graph features are limited, so a
+/// failure is logged rather than returned.
+async fn ensure_graph_schema(client: &Client, cfg: &Config) -> bool {
+    let age_result = client
+        .batch_execute("CREATE EXTENSION IF NOT EXISTS age;\nLOAD 'age';\nSET search_path = ag_catalog, \"$user\", public;")
+        .await;
+    if let Err(e) = age_result {
+        tracing::warn!(error = %e, "⚠️  AGE extension not available - knowledge graph features will be limited");
+        return false;
+    }
+
+    // Create the graph before its labels; the graphid is looked up by name,
+    // since it differs between databases
+    if let Err(e) = crate::db::graph::ensure_graph(client, &cfg.graph_name).await {
+        tracing::warn!(graph = %cfg.graph_name, error = %e, "⚠️  Failed to create graph");
+        return false;
+    }
+    for label in ["Node", "TestNode", "Person", "City"] {
+        let _ = crate::db::graph::ensure_vlabel(client, &cfg.graph_name, label).await;
+    }
+    true
+}
+
+/// Edge vectors hashed into LSH buckets, and the session, metadata and evidence
+/// tables around them
+fn edge_embeddings_sql(_storage: VectorStorage) -> String {
+    "CREATE EXTENSION IF NOT EXISTS vector;
+
+     CREATE TABLE IF NOT EXISTS ag_catalog.embeddings (
+         triplet_id BIGINT PRIMARY KEY,
+         vec vector,
+         lsh_bucket INTEGER,
+         session_id TEXT,
+         edge_text TEXT,
+         content_hash TEXT,
+         embedding_model TEXT,
+         lsh_buckets INTEGER[]
+     );
+     ALTER TABLE ag_catalog.embeddings ADD COLUMN IF NOT EXISTS content_hash TEXT;
+     ALTER TABLE ag_catalog.embeddings ADD COLUMN IF NOT EXISTS embedding_model TEXT;
+     DO $$
+     BEGIN
+         -- Backfill single-bucket rows once, when the array column is added
+         IF NOT EXISTS (
+             SELECT 1 FROM information_schema.columns
+             WHERE table_schema = 'ag_catalog' AND table_name = 'embeddings' AND column_name = 'lsh_buckets'
+         ) THEN
+             ALTER TABLE ag_catalog.embeddings ADD COLUMN lsh_buckets INTEGER[];
+             UPDATE ag_catalog.embeddings SET lsh_buckets = ARRAY[lsh_bucket] WHERE lsh_bucket IS NOT NULL;
+         END IF;
+     END $$;
+     DO $$
+     BEGIN
+         -- Vectors were stored as JSON text before similarity moved into pgvector
+         IF EXISTS (
+             SELECT 1 FROM information_schema.columns
+             WHERE table_schema = 'ag_catalog' AND table_name = 'embeddings'
+               AND column_name = 'vec' AND data_type = 'text'
+         ) THEN
+             ALTER TABLE ag_catalog.embeddings
+                 ALTER COLUMN vec TYPE vector USING NULLIF(vec, '[]')::vector;
+         END IF;
+     END $$;
+     CREATE INDEX IF NOT EXISTS idx_embeddings_lsh_buckets ON ag_catalog.embeddings USING GIN(lsh_buckets);
+     CREATE INDEX IF NOT EXISTS idx_embeddings_lsh_bucket ON ag_catalog.embeddings(lsh_bucket);
+     CREATE INDEX IF NOT EXISTS idx_embeddings_session ON ag_catalog.embeddings(session_id);
+
+     CREATE TABLE IF NOT EXISTS ag_catalog.sessions (
+         session_id TEXT PRIMARY KEY,
+         ingested_at TIMESTAMP DEFAULT NOW(),
+         node_count INTEGER,
+         edge_count INTEGER
+     );
+
+     -- Key/value settings the stored data depends on (e.g. the LSH bucket count)
+     CREATE TABLE IF NOT EXISTS ag_catalog.ingest_metadata (
+         key TEXT PRIMARY KEY,
+         value TEXT NOT NULL,
+         updated_at TIMESTAMP DEFAULT NOW()
+     );
+
+     CREATE TABLE IF NOT EXISTS ag_catalog.edge_evidence (
+         edge_id BIGINT,
+         session_id TEXT,
+         evidence_message_id TEXT,
+         PRIMARY KEY (edge_id, evidence_message_id)
+     );"
+        .to_string()
+}
+
+/// Conversations and messages, with full-text search
+fn messages_sql(_storage: VectorStorage) -> String {
+    "CREATE EXTENSION IF NOT EXISTS \"uuid-ossp\";
+
+     CREATE TABLE IF NOT EXISTS ag_catalog.conversations (
+         conversation_id UUID PRIMARY KEY,
+         created_at TIMESTAMP DEFAULT NOW(),
+         updated_at TIMESTAMP DEFAULT NOW(),
+         metadata JSONB DEFAULT '{}'::jsonb
+     );
+
+     CREATE TABLE IF NOT EXISTS ag_catalog.messages (
+         message_id UUID PRIMARY KEY,
+         conversation_id UUID NOT NULL REFERENCES ag_catalog.conversations(conversation_id) ON DELETE CASCADE,
+         content TEXT NOT NULL,
+         content_tsv tsvector GENERATED ALWAYS AS (to_tsvector('english', content)) STORED,
+         content_hash TEXT,
+         created_at TIMESTAMP DEFAULT NOW(),
+         deleted_at TIMESTAMP,
+         metadata JSONB DEFAULT '{}'::jsonb
+     );
+     ALTER TABLE ag_catalog.messages ADD COLUMN IF NOT EXISTS content_hash TEXT;
+     ALTER TABLE ag_catalog.messages ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP;
+
+     -- Tables created before content_tsv existed get the generated column added
+     -- (which backfills every row). Older tables with a plain tsvector column keep
+     -- the trigger and get any NULL rows backfilled.
+     DO $$
+     DECLARE
+         generated TEXT;
+     BEGIN
+         SELECT is_generated INTO generated FROM information_schema.columns
+         WHERE table_schema = 'ag_catalog' AND table_name = 'messages' AND column_name = 'content_tsv';
+         IF NOT FOUND THEN
+             ALTER TABLE ag_catalog.messages ADD COLUMN content_tsv tsvector
+                 GENERATED ALWAYS AS (to_tsvector('english', content)) STORED;
+         ELSIF generated = 'NEVER' THEN
+             CREATE OR REPLACE FUNCTION ag_catalog.messages_tsv_trigger() RETURNS trigger AS $fn$
+             BEGIN
+                 NEW.content_tsv := to_tsvector('english', NEW.content);
+                 RETURN NEW;
+             END
+             $fn$ LANGUAGE plpgsql;
+
+             DROP TRIGGER IF EXISTS tsvectorupdate ON ag_catalog.messages;
+             CREATE TRIGGER tsvectorupdate BEFORE INSERT OR UPDATE
+             ON ag_catalog.messages FOR EACH ROW EXECUTE FUNCTION ag_catalog.messages_tsv_trigger();
+
+             UPDATE ag_catalog.messages SET content_tsv = to_tsvector('english', content)
+             WHERE content_tsv IS NULL;
+         END IF;
+     END $$;
+
+     -- GIN index for full-text search (BM25-style ranking)
+     CREATE INDEX IF NOT EXISTS idx_messages_content_tsv ON ag_catalog.messages USING GIN(content_tsv);
+     CREATE INDEX IF NOT EXISTS idx_messages_conversation ON ag_catalog.messages(conversation_id);"
+        .to_string()
+}
+
+/// Message embeddings, and the knowledge graph nodes, edges and edge embeddings
+fn knowledge_graph_sql(storage: VectorStorage) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS ag_catalog.message_embeddings (
+             message_id UUID PRIMARY KEY REFERENCES ag_catalog.messages(message_id) ON DELETE CASCADE,
+             embedding {column} NOT NULL,
+             embedding_model VARCHAR(100) DEFAULT 'nomic-embed-text-v1.5',
+             created_at TIMESTAMP DEFAULT NOW()
+         );
+
+         CREATE TABLE IF NOT EXISTS ag_catalog.kg_nodes (
+             node_id VARCHAR(255),
+             conversation_id UUID REFERENCES ag_catalog.conversations(conversation_id) ON DELETE CASCADE,
+             node_type VARCHAR(100),
+             properties JSONB DEFAULT '{{}}'::jsonb,
+             created_at TIMESTAMP DEFAULT NOW(),
+             PRIMARY KEY (node_id, conversation_id)
+         );
+         ALTER TABLE ag_catalog.kg_nodes ADD COLUMN IF NOT EXISTS properties JSONB DEFAULT '{{}}'::jsonb;
+
+         CREATE TABLE IF NOT EXISTS ag_catalog.kg_edges (
+             edge_id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+             conversation_id UUID REFERENCES ag_catalog.conversations(conversation_id) ON DELETE CASCADE,
+             source_node VARCHAR(255) NOT NULL,
+             target_node VARCHAR(255) NOT NULL,
+             relation VARCHAR(255) NOT NULL,
+             evidence_message_ids UUID[] NOT NULL,
+             created_at TIMESTAMP DEFAULT NOW()
+         );
+
+         -- Edge embeddings for semantic search on edges
+         CREATE TABLE IF NOT EXISTS ag_catalog.kg_edge_embeddings (
+             edge_id UUID PRIMARY KEY REFERENCES ag_catalog.kg_edges(edge_id) ON DELETE CASCADE,
+             embedding {column} NOT NULL,
+             edge_text TEXT NOT NULL,
+             embedding_model VARCHAR(100) DEFAULT 'nomic-embed-text-v1.5',
+             created_at TIMESTAMP DEFAULT NOW()
+         );
+         ALTER TABLE ag_catalog.kg_edge_embeddings ADD COLUMN IF NOT EXISTS source_embedding {column};
+         ALTER TABLE ag_catalog.kg_edge_embeddings ADD COLUMN IF NOT EXISTS relation_embedding {column};
+         ALTER TABLE ag_catalog.kg_edge_embeddings ADD COLUMN IF NOT EXISTS target_embedding {column};
+
+         CREATE INDEX IF NOT EXISTS idx_kg_edges_conversation ON ag_catalog.kg_edges(conversation_id);
+         CREATE INDEX IF NOT EXISTS idx_kg_edges_evidence ON ag_catalog.kg_edges USING GIN(evidence_message_ids);
+         CREATE INDEX IF NOT EXISTS idx_kg_nodes_conversation ON ag_catalog.kg_nodes(conversation_id);
+         CREATE INDEX IF NOT EXISTS idx_kg_nodes_type ON ag_catalog.kg_nodes(node_type);",
+        column = storage.column_type(768)
+    )
+}
+
+/// Record each edge vector's dimension, so inserts can reject a vector whose length
+/// differs from the other rows of its model
+fn embeddings_dim_sql(_storage: VectorStorage) -> String {
+    "ALTER TABLE ag_catalog.embeddings ADD COLUMN IF NOT EXISTS dim INTEGER;
+     UPDATE ag_catalog.embeddings SET dim = vector_dims(vec) WHERE dim IS NULL AND vec IS NOT NULL;
+     CREATE INDEX IF NOT EXISTS idx_embeddings_model_dim ON ag_catalog.embeddings(embedding_model, dim);"
+        .to_string()
+}
+
+/// Order of messages ingested in one batch, which share a `created_at`
+fn message_turn_index_sql(_storage: VectorStorage) -> String {
+    "ALTER TABLE ag_catalog.messages ADD COLUMN IF NOT EXISTS turn_index INTEGER;
+     CREATE INDEX IF NOT EXISTS idx_messages_conversation_order
+         ON ag_catalog.messages(conversation_id, created_at, turn_index);"
+        .to_string()
+}
+
+/// The content of a message as ingested, when it had to be sanitized to be stored
+fn message_original_content_sql(_storage: VectorStorage) -> String {
+    "ALTER TABLE ag_catalog.messages ADD COLUMN IF NOT EXISTS original_content BYTEA;".to_string()
+}
@@ -1,6 +1,10 @@
 pub mod connect;
+pub mod migrate;
 pub mod graph;
 pub mod vector;
+pub mod vector_index;
 pub mod models;
 pub mod message_ops;
 pub mod kg_ops;
+pub mod sessions;
+pub mod export;
@@ -1,4 +1,5 @@
-use serde::{Deserialize, Serialize};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize, Serializer};
 use uuid::Uuid;
 use std::collections::HashMap;
 
@@ -12,6 +13,16 @@ pub struct TurnEmbedding {
     pub conversation_id: Uuid,
     pub actual_text: String,
     pub embedding: Vec<f32>, // 768-dim Nomic embeddings
+    /// Model that produced `embedding`; the ingest endpoint fills in `EMBED_MODEL_NAME` when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding_model: Option<String>,
+}
+
+impl TurnEmbedding {
+    /// Model name to store with this turn's embedding
+    pub fn embedding_model_or_default(&self) -> &str {
+        self.embedding_model.as_deref().unwrap_or(crate::config::DEFAULT_EMBED_MODEL)
+    }
 }
 
 // ============================================================================
@@ -40,13 +51,68 @@ pub struct KGNode {
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(try_from = "RawKGEdge")]
 pub struct KGEdge {
     pub source: String,
     pub target: String,
     pub relation: String,
+    /// May be omitted; stored as an empty array (the column is NOT NULL).
+    /// Ids that aren't UUIDs are mapped with `evidence_uuid`.
     pub evidence_message_ids: Vec<Uuid>,
 }
 
+/// `KGEdge` as it appears in the input, before evidence ids are checked
+#[derive(Deserialize)]
+struct RawKGEdge {
+    source: String,
+    target: String,
+    relation: String,
+    #[serde(default)]
+    evidence_message_ids: Vec<serde_json::Value>,
+}
+
+impl TryFrom<RawKGEdge> for KGEdge {
+    type Error = String;
+
+    fn try_from(raw: RawKGEdge) -> Result<Self, Self::Error> {
+        let evidence_message_ids = parse_evidence_ids(&raw.source, &raw.relation, &raw.target, &raw.evidence_message_ids)?
+            .iter()
+            .map(|id| evidence_uuid(id))
+            .collect();
+        Ok(KGEdge { source: raw.source, target: raw.target, relation: raw.relation, evidence_message_ids })
+    }
+}
+
+/// Namespace for UUIDv5 ids derived from non-UUID evidence ids
+pub const EVIDENCE_ID_NAMESPACE: Uuid = Uuid::from_u128(0x6f1c_2a4e_8b3d_4c7a_9e51_d2f0_7a6b_3c18);
+
+/// Evidence id as a UUID: UUID strings parse as-is, anything else maps to a
+/// deterministic UUIDv5 so the same string always gives the same id
+pub fn evidence_uuid(id: &str) -> Uuid {
+    Uuid::parse_str(id).unwrap_or_else(|_| Uuid::new_v5(&EVIDENCE_ID_NAMESPACE, id.as_bytes()))
+}
+
+/// Check an edge's raw evidence ids: strings (UUID or not) and integers are
+/// accepted; anything else is an error naming the edge and the bad entry
+pub fn parse_evidence_ids(
+    source: &str,
+    relation: &str,
+    target: &str,
+    raw: &[serde_json::Value],
+) -> Result<Vec<String>, String> {
+    raw.iter()
+        .enumerate()
+        .map(|(i, value)| match value {
+            serde_json::Value::String(s) if !s.trim().is_empty() => Ok(s.clone()),
+            serde_json::Value::Number(n) if n.is_u64() || n.is_i64() => Ok(n.to_string()),
+            other => Err(format!(
+                "edge ({})-[{}]->({}): evidence_message_ids[{}] = {} is not a usable id",
+                source, relation, target, i, other
+            )),
+        })
+        .collect()
+}
+
 // ============================================================================
 // Database Entity Models
 // ============================================================================
@@ -54,6 +120,7 @@ pub struct KGEdge {
 #[derive(Debug, Serialize, Clone)]
 pub struct Conversation {
     pub conversation_id: Uuid,
+    pub metadata: serde_json::Value,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -61,6 +128,41 @@ pub struct Message {
     pub message_id: Uuid,
     pub conversation_id: Uuid,
     pub content: String,
+    /// When the message was ingested (`messages.created_at`, database server time)
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "serialize_timestamp")]
+    pub created_at: Option<NaiveDateTime>,
+}
+
+/// `YYYY-MM-DDTHH:MM:SS[.ffffff]`, like the `TIMESTAMP` columns it comes from
+fn serialize_timestamp<S: Serializer>(timestamp: &Option<NaiveDateTime>, serializer: S) -> Result<S::Ok, S::Error> {
+    match timestamp {
+        Some(timestamp) => serializer.serialize_str(&timestamp.format("%Y-%m-%dT%H:%M:%S%.f").to_string()),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Which retrieval strategy surfaced a message
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum RetrievalSource {
+    Keyword,
+    Embedding,
+    KgEdge,
+    Multiple,
+    /// A turn next to a matched message (`context_window`), included to show the
+    /// thread around it rather than as evidence
+    Context,
+}
+
+impl RetrievalSource {
+    /// Combine the sources of a message found by more than one strategy
+    pub fn merge(self, other: RetrievalSource) -> RetrievalSource {
+        if self == other {
+            self
+        } else {
+            RetrievalSource::Multiple
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -69,21 +171,51 @@ pub struct MessageWithRelevance {
     pub conversation_id: Uuid,
     pub content: String,
     pub relevance_score: f32,
+    pub source: RetrievalSource,
 }
 
 // ============================================================================
 // LLM Context Models
 // ============================================================================
 
-#[derive(Debug, Serialize)]
+/// Why a message ranked where it did in hybrid search (returned with `explain: true`)
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct ScoreExplanation {
+    /// Length-weighted share of the query keywords found in the message (keyword matches only)
+    pub keyword_coverage: Option<f32>,
+    /// Query keywords found in the message
+    pub matched_keywords: Vec<String>,
+    /// Full-text `ts_rank` score before boosting (keyword matches only)
+    pub bm25_rank: Option<f32>,
+    /// Cosine similarity to the query embedding (embedding matches only)
+    pub embedding_similarity: Option<f32>,
+    /// Multiplier applied before fusion: the coverage boost for keyword matches,
+    /// the embedding-only weight otherwise
+    pub boost: f32,
+    /// Score after fusion, which determines the ranking
+    pub final_score: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct LLMContextMessage {
     pub role: String,
     pub content: String,
     pub message_id: Uuid,
     pub relevance_score: f32,
+    pub source: RetrievalSource,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<ScoreExplanation>,
+    /// Byte ranges `[start, end)` of `content` matching the query keywords (with `highlight: true`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlights: Option<Vec<(usize, usize)>>,
+    /// For `source: "context"` messages, the matched message they surround
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_for: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "serialize_timestamp")]
+    pub created_at: Option<NaiveDateTime>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct FormattedLLMContext {
     pub messages: Vec<LLMContextMessage>,
     pub total_tokens_estimate: usize,
@@ -95,21 +227,122 @@ pub struct FormattedLLMContext {
 // API Response Models
 // ============================================================================
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct IngestResponse {
     pub success: bool,
     pub total_processed: usize,
     pub total_inserted: usize,
+    /// Rows left untouched because they were already stored unchanged
+    pub skipped: usize,
     pub duration_ms: u128,
     pub errors: Vec<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct KGEdgeWithContext {
     pub source: String,
     pub target: String,
     pub relation: String,
     pub evidence_message_ids: Vec<Uuid>,
     pub conversation_id: Uuid,
+    /// Cosine similarity to the query embedding (None for edges without an embedding
+    /// or when not retrieved by similarity)
+    pub similarity: Option<f32>,
+    /// Resolved evidence messages, when requested with `include_evidence_content`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub evidence: Option<Vec<Message>>,
 }
 
+
+// ============================================================================
+// Apache AGE Graph Models (parsed from agtype results)
+// ============================================================================
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AgVertex {
+    pub id: i64,
+    pub label: String,
+    #[serde(default)]
+    pub properties: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AgEdge {
+    pub id: i64,
+    /// Relationship type
+    pub label: String,
+    pub start_id: i64,
+    pub end_id: i64,
+    #[serde(default)]
+    pub properties: serde_json::Value,
+}
+
+/// A node reachable from a start node, with the relationships along the shortest
+/// path found to it (in path order)
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphNeighbor {
+    pub node: AgVertex,
+    pub hops: usize,
+    pub relationships: Vec<AgEdge>,
+}
+
+/// Nodes along a path in order, and the relationships between them: `relationships[i]`
+/// joins `nodes[i]` and `nodes[i + 1]`, in either direction (see its `start_id`)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GraphPath {
+    pub nodes: Vec<AgVertex>,
+    pub relationships: Vec<AgEdge>,
+}
+
+/// An edge near a set of start nodes, named by its endpoints' pks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NearbyEdge {
+    pub id: i64,
+    pub source: String,
+    pub relation: String,
+    pub target: String,
+    /// 1 for an edge touching a start node, 2 for one beyond that, ...
+    pub hops: usize,
+}
+
+/// A relation, node type or label name and how many rows carry it
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct SchemaEntry {
+    pub name: String,
+    pub count: i64,
+}
+
+/// Relation types, node types and AGE labels that exist in the graph, each sorted by name
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphSchema {
+    pub graph: String,
+    /// Distinct `kg_edges.relation` values
+    pub relations: Vec<SchemaEntry>,
+    /// Distinct `kg_nodes.node_type` values (`unknown` when unset)
+    pub node_types: Vec<SchemaEntry>,
+    /// AGE vertex labels registered for the graph, with vertex counts
+    pub vertex_labels: Vec<SchemaEntry>,
+    /// AGE edge labels registered for the graph, with edge counts
+    pub edge_labels: Vec<SchemaEntry>,
+}
+
+/// Evidence and embedding coverage of knowledge graph edges (`GET /ingest/quality`)
+#[derive(Debug, Clone, Serialize)]
+pub struct EvidenceQualityReport {
+    /// Set when the report covers one conversation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conversation_id: Option<Uuid>,
+    pub total_edges: i64,
+    /// Edges with an empty `evidence_message_ids`
+    pub edges_without_evidence: i64,
+    /// Evidence ids naming a message that doesn't exist or was deleted
+    pub dangling_evidence_count: i64,
+    /// Edges with at least one dangling evidence id
+    pub edges_with_dangling_evidence: i64,
+    /// Number of edges per evidence count
+    pub evidence_per_edge_histogram: std::collections::BTreeMap<i32, i64>,
+    /// Edges with no `kg_edge_embeddings` row
+    pub edges_without_embedding: i64,
+    /// Edges whose stored embedding is a placeholder (embedding server was down)
+    pub edges_with_placeholder_embedding: i64,
+}
@@ -0,0 +1,55 @@
+use anyhow::Result;
+use serde::Serialize;
+use tokio_postgres::{Client, Row};
+
+/// Stored metadata for one ingested session, with the number of edge embeddings it has
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    pub session_id: String,
+    /// RFC 3339 timestamp of the last ingest of this session
+    pub ingested_at: Option<String>,
+    pub node_count: i32,
+    pub edge_count: i32,
+    /// Zero for a session with edges means its embeddings failed to store
+    pub embedding_count: i64,
+}
+
+const SESSION_SUMMARY_SELECT: &str =
+    "SELECT s.session_id, s.ingested_at, COALESCE(s.node_count, 0), COALESCE(s.edge_count, 0),
+            (SELECT COUNT(*) FROM ag_catalog.embeddings e WHERE e.session_id = s.session_id)
+     FROM ag_catalog.sessions s";
+
+fn session_summary(row: &Row) -> SessionSummary {
+    let ingested_at: Option<chrono::NaiveDateTime> = row.get(1);
+    SessionSummary {
+        session_id: row.get(0),
+        ingested_at: ingested_at.map(|t| t.and_utc().to_rfc3339()),
+        node_count: row.get(2),
+        edge_count: row.get(3),
+        embedding_count: row.get(4),
+    }
+}
+
+/// Sessions ordered by most recently ingested first
+pub async fn list_sessions(client: &Client, limit: i64, offset: i64) -> Result<Vec<SessionSummary>> {
+    let rows = client
+        .query(
+            &format!("{} ORDER BY s.ingested_at DESC NULLS LAST, s.session_id LIMIT $1 OFFSET $2", SESSION_SUMMARY_SELECT),
+            &[&limit, &offset],
+        )
+        .await?;
+    Ok(rows.iter().map(session_summary).collect())
+}
+
+/// Total number of ingested sessions
+pub async fn count_sessions(client: &Client) -> Result<i64> {
+    Ok(client.query_one("SELECT COUNT(*) FROM ag_catalog.sessions", &[]).await?.get(0))
+}
+
+/// One session's metadata, or `None` if it was never ingested
+pub async fn get_session_summary(client: &Client, session_id: &str) -> Result<Option<SessionSummary>> {
+    let row = client
+        .query_opt(&format!("{} WHERE s.session_id = $1", SESSION_SUMMARY_SELECT), &[&session_id])
+        .await?;
+    Ok(row.as_ref().map(session_summary))
+}
@@ -1,65 +1,614 @@
 use anyhow::Result;
-use tokio_postgres::Client;
+use pgvector::Vector;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use tokio_postgres::{Client, GenericClient};
 
-/// Upsert embedding vector row (storing as JSON text for now).
+use crate::etl::lsh::LshTables;
+use crate::etl::similarity::{DimensionMismatch, DimensionMismatchMode, SimilarityMetric};
+
+/// `ingest_metadata` key recording the bucket count stored `lsh_bucket`s were hashed with
+pub const LSH_BUCKETS_KEY: &str = "lsh_buckets";
+
+/// A vector whose dimension differs from the stored vectors of the same model. Rows of
+/// one model must agree so similarity never compares vectors of different lengths.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddingDimConflict {
+    pub embedding_model: String,
+    /// Dimension of the vectors already stored for the model
+    pub stored: usize,
+    /// Dimension of the rejected vector
+    pub inserted: usize,
+}
+
+impl std::fmt::Display for EmbeddingDimConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Embedding dimension mismatch for model '{}': stored vectors have {} dims, this one has {} (re-embed the stored rows or set a different EMBED_MODEL_NAME)",
+            self.embedding_model, self.stored, self.inserted
+        )
+    }
+}
+
+impl std::error::Error for EmbeddingDimConflict {}
+
+/// The `EmbeddingDimConflict` that kept a guarded write from touching a row, if any:
+/// another row of `embedding_model` has a dimension other than `dim`
+async fn dim_conflict(client: &Client, triplet_id: i64, embedding_model: &str, dim: i32) -> Result<Option<EmbeddingDimConflict>> {
+    let row = client
+        .query_opt(
+            "SELECT dim FROM ag_catalog.embeddings
+             WHERE embedding_model = $1 AND dim <> $2 AND triplet_id <> $3
+             LIMIT 1",
+            &[&embedding_model, &dim, &triplet_id],
+        )
+        .await?;
+    Ok(row.map(|row| EmbeddingDimConflict {
+        embedding_model: embedding_model.to_string(),
+        stored: row.get::<_, i32>(0) as usize,
+        inserted: dim as usize,
+    }))
+}
+
+/// Run `write` holding a Postgres advisory lock on `embedding_model`. The dimension
+/// check and the write are one statement, but two connections storing the first
+/// vectors of a model with different dimensions would each see no conflicting row;
+/// writes for one model are serialized so the second sees the first.
+async fn with_model_dim_lock<T>(client: &Client, embedding_model: &str, write: impl Future<Output = Result<T>>) -> Result<T> {
+    let key = format!("rust_ingester.embedding_dim:{}", embedding_model);
+    client.execute("SELECT pg_advisory_lock(hashtext($1))", &[&key]).await?;
+    let result = write.await;
+    client.execute("SELECT pg_advisory_unlock(hashtext($1))", &[&key]).await?;
+    result
+}
+
+/// Upsert embedding vector row (a pgvector `vector`), recording the
+/// model that produced it and the vector's dimension. `buckets` holds one bucket
+/// per LSH table (`LshTables::hash_all`); the first is also kept in `lsh_bucket`.
+/// Fails with `EmbeddingDimConflict` if the model's other rows have another dimension.
 pub async fn upsert_embedding(
     client: &Client,
     triplet_id: i64,
     vec: &[f32],
-    bucket: i32,
+    buckets: &[i32],
+    embedding_model: &str,
 ) -> Result<()> {
-    let vec_json = serde_json::to_string(vec)?;
-    client
-        .execute(
-            "INSERT INTO ag_catalog.embeddings(triplet_id, vec, lsh_bucket) VALUES($1, $2, $3)
-             ON CONFLICT (triplet_id) DO UPDATE SET vec = EXCLUDED.vec, lsh_bucket = EXCLUDED.lsh_bucket",
-            &[&triplet_id, &vec_json, &bucket],
-        )
-        .await?;
+    let dim = vec.len() as i32;
+    let vec = Vector::from(vec.to_vec());
+    let written = with_model_dim_lock(client, embedding_model, async {
+        Ok(client
+            .execute(
+                "INSERT INTO ag_catalog.embeddings(triplet_id, vec, dim, lsh_bucket, lsh_buckets, embedding_model)
+                 SELECT $1, $2, $6, $3, $4, $5
+                 WHERE NOT EXISTS (
+                     SELECT 1 FROM ag_catalog.embeddings
+                     WHERE embedding_model = $5 AND dim <> $6 AND triplet_id <> $1
+                 )
+                 ON CONFLICT (triplet_id) DO UPDATE SET
+                    vec = EXCLUDED.vec,
+                    dim = EXCLUDED.dim,
+                    lsh_bucket = EXCLUDED.lsh_bucket,
+                    lsh_buckets = EXCLUDED.lsh_buckets,
+                    embedding_model = EXCLUDED.embedding_model",
+                &[&triplet_id, &vec, &buckets.first().copied(), &buckets, &embedding_model, &dim],
+            )
+            .await?)
+    })
+    .await?;
+    if written == 0 {
+        if let Some(conflict) = dim_conflict(client, triplet_id, embedding_model, dim).await? {
+            return Err(conflict.into());
+        }
+    }
     Ok(())
 }
 
-/// Upsert embedding with session tracking
+/// Upsert embedding with session tracking, the edge's content hash and the
+/// model that produced it (buckets and the dimension check as in `upsert_embedding`)
+#[allow(clippy::too_many_arguments)]
 pub async fn upsert_embedding_with_session(
     client: &Client,
     triplet_id: i64,
     vec: &[f32],
-    bucket: i32,
+    buckets: &[i32],
     session_id: &str,
     edge_text: &str,
+    content_hash: &str,
+    embedding_model: &str,
 ) -> Result<()> {
-    let vec_json = serde_json::to_string(vec)?;
-    client
-        .execute(
-            "INSERT INTO ag_catalog.embeddings(triplet_id, vec, lsh_bucket, session_id, edge_text) 
-             VALUES($1, $2, $3, $4, $5)
-             ON CONFLICT (triplet_id) DO UPDATE SET 
-                vec = EXCLUDED.vec, 
-                lsh_bucket = EXCLUDED.lsh_bucket,
-                session_id = EXCLUDED.session_id,
-                edge_text = EXCLUDED.edge_text",
-            &[&triplet_id, &vec_json, &bucket, &session_id, &edge_text],
+    let dim = vec.len() as i32;
+    let vec = Vector::from(vec.to_vec());
+    let written = with_model_dim_lock(client, embedding_model, async {
+        Ok(client
+            .execute(
+                "INSERT INTO ag_catalog.embeddings(triplet_id, vec, dim, lsh_bucket, lsh_buckets, session_id, edge_text, content_hash, embedding_model) 
+                 SELECT $1, $2, $9, $3, $4, $5, $6, $7, $8
+                 WHERE NOT EXISTS (
+                     SELECT 1 FROM ag_catalog.embeddings
+                     WHERE embedding_model = $8 AND dim <> $9 AND triplet_id <> $1
+                 )
+                 ON CONFLICT (triplet_id) DO UPDATE SET 
+                    vec = EXCLUDED.vec, 
+                    dim = EXCLUDED.dim,
+                    lsh_bucket = EXCLUDED.lsh_bucket,
+                    lsh_buckets = EXCLUDED.lsh_buckets,
+                    session_id = EXCLUDED.session_id,
+                    edge_text = EXCLUDED.edge_text,
+                    content_hash = EXCLUDED.content_hash,
+                    embedding_model = EXCLUDED.embedding_model",
+                &[&triplet_id, &vec, &buckets.first().copied(), &buckets, &session_id, &edge_text, &content_hash, &embedding_model, &dim],
+            )
+            .await?)
+    })
+    .await?;
+    if written == 0 {
+        if let Some(conflict) = dim_conflict(client, triplet_id, embedding_model, dim).await? {
+            return Err(conflict.into());
+        }
+    }
+    Ok(())
+}
+
+/// Session edge embeddings that are placeholders (or have no vector), as
+/// `(triplet_id, edge_text)`, optionally limited to one session. Rows without
+/// `edge_text` (single triplet ingests) can't be re-embedded and are left out.
+pub async fn get_placeholder_embeddings(client: &Client, session_id: Option<&str>) -> Result<Vec<(i64, String)>> {
+    let rows = client
+        .query(
+            "SELECT triplet_id, edge_text FROM ag_catalog.embeddings
+             WHERE (vec IS NULL OR embedding_model = $1)
+               AND edge_text IS NOT NULL
+               AND ($2::text IS NULL OR session_id = $2)
+             ORDER BY triplet_id",
+            &[&crate::etl::embed::PLACEHOLDER_MODEL, &session_id],
         )
         .await?;
+    Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+}
+
+/// Replace a stored edge's vector, LSH buckets and model, keeping its session,
+/// text and content hash (with the dimension check of `upsert_embedding`)
+pub async fn update_embedding_vector(
+    client: &Client,
+    triplet_id: i64,
+    vec: &[f32],
+    buckets: &[i32],
+    embedding_model: &str,
+) -> Result<()> {
+    let dim = vec.len() as i32;
+    let vec = Vector::from(vec.to_vec());
+    let written = with_model_dim_lock(client, embedding_model, async {
+        Ok(client
+            .execute(
+                "UPDATE ag_catalog.embeddings
+                 SET vec = $2, dim = $6, lsh_bucket = $3, lsh_buckets = $4, embedding_model = $5
+                 WHERE triplet_id = $1 AND NOT EXISTS (
+                     SELECT 1 FROM ag_catalog.embeddings
+                     WHERE embedding_model = $5 AND dim <> $6 AND triplet_id <> $1
+                 )",
+                &[&triplet_id, &vec, &buckets.first().copied(), &buckets, &embedding_model, &dim],
+            )
+            .await?)
+    })
+    .await?;
+    if written == 0 {
+        if let Some(conflict) = dim_conflict(client, triplet_id, embedding_model, dim).await? {
+            return Err(conflict.into());
+        }
+    }
     Ok(())
 }
 
-/// Store evidence for an edge
+/// Stored edge embedding considered by a similarity query
+#[derive(Debug, Clone)]
+pub struct EmbeddingCandidate {
+    pub triplet_id: i64,
+    pub vec: Vec<f32>,
+    pub session_id: Option<String>,
+    pub edge_text: Option<String>,
+}
+
+/// Candidates for a similarity query and how they were found
+#[derive(Debug, Clone)]
+pub struct LshCandidates {
+    pub candidates: Vec<EmbeddingCandidate>,
+    /// The query's buckets were empty in every table, so the candidates come
+    /// from a capped scan of the whole table rather than the LSH index
+    pub degraded: bool,
+}
+
+/// Embeddings sharing a bucket with the query (`buckets` from `LshTables::hash_all`)
+/// in any LSH table, optionally restricted to one embedding model. When none do,
+/// falls back to scanning up to `fallback_limit` rows of the whole table and
+/// marks the result as degraded.
+pub async fn fetch_lsh_candidates(
+    client: &Client,
+    buckets: &[i32],
+    embedding_model: Option<&str>,
+    fallback_limit: usize,
+) -> Result<LshCandidates> {
+    let rows = client
+        .query(
+            "SELECT triplet_id, vec, session_id, edge_text FROM ag_catalog.embeddings
+             WHERE lsh_buckets && $1 AND ($2::text IS NULL OR embedding_model = $2)",
+            &[&buckets, &embedding_model],
+        )
+        .await?;
+    tracing::debug!(candidate_count = rows.len(), buckets = ?buckets, "LSH candidates found");
+
+    let degraded = rows.is_empty();
+    let rows = if degraded {
+        tracing::warn!(
+            buckets = ?buckets,
+            fallback_limit,
+            "⚠️  LSH buckets are empty, scanning the whole embeddings table (degraded)"
+        );
+        let all_rows = client
+            .query(
+                "SELECT triplet_id, vec, session_id, edge_text FROM ag_catalog.embeddings
+                 WHERE $1::text IS NULL OR embedding_model = $1 LIMIT $2",
+                &[&embedding_model, &(fallback_limit as i64)],
+            )
+            .await?;
+        tracing::debug!(candidate_count = all_rows.len(), "Fallback scan candidates");
+
+        // Show bucket distribution (an extra query, so only when it will be logged;
+        // `GET /debug/lsh-distribution` reports it in full)
+        if tracing::enabled!(tracing::Level::DEBUG) {
+            let bucket_counts = get_lsh_bucket_counts(client).await?;
+            for (b, count) in bucket_counts.iter().take(10) {
+                tracing::debug!(bucket = b, count, "Bucket distribution");
+            }
+            if bucket_counts.len() > 10 {
+                tracing::debug!(more_buckets = bucket_counts.len() - 10, "Bucket distribution truncated");
+            }
+        }
+
+        all_rows
+    } else {
+        rows
+    };
+
+    let candidates = rows
+        .iter()
+        .map(|row| EmbeddingCandidate {
+            triplet_id: row.get(0),
+            vec: row.get::<_, Vector>(1).to_vec(),
+            session_id: row.get(2),
+            edge_text: row.get(3),
+        })
+        .collect();
+
+    Ok(LshCandidates { candidates, degraded })
+}
+
+/// Number of stored embeddings in each non-empty single-table bucket (`lsh_bucket`)
+pub async fn get_lsh_bucket_counts(client: &Client) -> Result<BTreeMap<i32, i64>> {
+    let rows = client
+        .query(
+            "SELECT lsh_bucket, COUNT(*) FROM ag_catalog.embeddings
+             WHERE lsh_bucket IS NOT NULL GROUP BY lsh_bucket",
+            &[],
+        )
+        .await?;
+    Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+}
+
+/// A stored edge embedding ranked by `nearest_lsh_embeddings`
+#[derive(Debug, Clone)]
+pub struct NearestEmbedding {
+    pub triplet_id: i64,
+    /// Distance under the query's metric (smaller = closer)
+    pub distance: f32,
+    pub session_id: Option<String>,
+    pub edge_text: Option<String>,
+}
+
+/// Closest embeddings to a query and how the candidates were found
+#[derive(Debug, Clone)]
+pub struct NearestEmbeddings {
+    /// Closest first; ties by triplet id
+    pub neighbors: Vec<NearestEmbedding>,
+    /// As in `LshCandidates`: no row shared a bucket with the query, so the
+    /// candidates were the first `fallback_limit` rows of the table
+    pub degraded: bool,
+}
+
+/// The `limit` embeddings closest to `query_vec` under `metric`, among those sharing an
+/// LSH bucket with it (falling back like `fetch_lsh_candidates`). Distances, ordering
+/// and the limit are computed by pgvector, so only the returned rows leave the database.
+/// Candidates of another dimension are skipped with a warning or fail the query,
+/// depending on `mode`.
+#[allow(clippy::too_many_arguments)]
+pub async fn nearest_lsh_embeddings(
+    client: &Client,
+    query_vec: &[f32],
+    buckets: &[i32],
+    metric: SimilarityMetric,
+    mode: DimensionMismatchMode,
+    embedding_model: Option<&str>,
+    fallback_limit: usize,
+    limit: usize,
+) -> Result<NearestEmbeddings> {
+    let query = Vector::from(query_vec.to_vec());
+    let limit = limit as i64;
+
+    let (mut neighbors, mut mismatched) =
+        ranked_candidates(client, &query, metric, embedding_model, CandidateSet::Buckets(buckets), limit).await?;
+    // Nothing of any dimension shares a bucket with the query
+    let degraded = neighbors.is_empty() && mismatched.is_empty();
+    if degraded {
+        tracing::warn!(
+            buckets = ?buckets,
+            fallback_limit,
+            "⚠️  LSH buckets are empty, scanning the whole embeddings table (degraded)"
+        );
+        let fallback = CandidateSet::Fallback(fallback_limit as i64);
+        (neighbors, mismatched) = ranked_candidates(client, &query, metric, embedding_model, fallback, limit).await?;
+    }
+
+    if let Some(&(found, _)) = mismatched.first() {
+        if mode == DimensionMismatchMode::Error {
+            return Err(DimensionMismatch { expected: query_vec.len(), found }.into());
+        }
+        tracing::warn!(
+            skipped = mismatched.iter().map(|(_, count)| count).sum::<i64>(),
+            query_dim = query_vec.len(),
+            "Skipped stored embeddings with a different dimension than the query"
+        );
+    }
+    tracing::debug!(result_count = neighbors.len(), degraded, "Nearest embeddings found");
+
+    Ok(NearestEmbeddings { neighbors, degraded })
+}
+
+/// Rows a similarity query ranks
+enum CandidateSet<'a> {
+    /// Rows sharing any of these LSH buckets
+    Buckets(&'a [i32]),
+    /// The first this many rows of the table (the degraded fallback)
+    Fallback(i64),
+}
+
+/// Rank one candidate set in SQL: the `limit` closest rows of the query's dimension,
+/// plus `(dimension, count)` for candidates of any other dimension, most common first
+async fn ranked_candidates(
+    client: &Client,
+    query: &Vector,
+    metric: SimilarityMetric,
+    embedding_model: Option<&str>,
+    candidates: CandidateSet<'_>,
+    limit: i64,
+) -> Result<(Vec<NearestEmbedding>, Vec<(usize, i64)>)> {
+    let (source, selector): (&str, &(dyn tokio_postgres::types::ToSql + Sync)) = match &candidates {
+        CandidateSet::Buckets(buckets) => (
+            "SELECT * FROM ag_catalog.embeddings
+             WHERE lsh_buckets && $3 AND ($2::text IS NULL OR embedding_model = $2)",
+            buckets,
+        ),
+        CandidateSet::Fallback(rows) => (
+            "SELECT * FROM ag_catalog.embeddings
+             WHERE $2::text IS NULL OR embedding_model = $2 LIMIT $3",
+            rows,
+        ),
+    };
+
+    let rows = client
+        .query(
+            &format!(
+                "SELECT c.triplet_id, (c.vec {op} $1::vector)::real AS distance, c.session_id, c.edge_text
+                 FROM ({source}) c
+                 WHERE vector_dims(c.vec) = vector_dims($1::vector)
+                 ORDER BY distance, c.triplet_id
+                 LIMIT $4",
+                op = metric.pg_operator(),
+                source = source
+            ),
+            &[query, &embedding_model, selector, &limit],
+        )
+        .await?;
+    let neighbors = rows
+        .iter()
+        .map(|row| NearestEmbedding {
+            triplet_id: row.get(0),
+            distance: row.get(1),
+            session_id: row.get(2),
+            edge_text: row.get(3),
+        })
+        .collect();
+
+    let mismatched = client
+        .query(
+            &format!(
+                "SELECT vector_dims(c.vec), COUNT(*) FROM ({source}) c
+                 WHERE vector_dims(c.vec) <> vector_dims($1::vector)
+                 GROUP BY 1 ORDER BY 2 DESC, 1",
+                source = source
+            ),
+            &[query, &embedding_model, selector],
+        )
+        .await?
+        .iter()
+        .map(|row| (row.get::<_, i32>(0) as usize, row.get::<_, i64>(1)))
+        .collect();
+
+    Ok((neighbors, mismatched))
+}
+
+/// Tables holding vectors along with an `embedding_model` column
+pub const EMBEDDING_TABLES: &[&str] = &["embeddings", "message_embeddings", "kg_edge_embeddings"];
+
+/// Distinct embedding models stored in `ag_catalog.<table>` (NULL = not recorded).
+/// `table` must be one of `EMBEDDING_TABLES`.
+pub async fn embedding_models(client: &Client, table: &str) -> Result<Vec<Option<String>>> {
+    anyhow::ensure!(EMBEDDING_TABLES.contains(&table), "Unknown embedding table: {}", table);
+    let rows = client
+        .query(
+            &format!("SELECT DISTINCT embedding_model::text FROM ag_catalog.{} ORDER BY 1", table),
+            &[],
+        )
+        .await?;
+    Ok(rows.iter().map(|r| r.get(0)).collect())
+}
+
+/// Log a warning for each of `tables` holding vectors from more than one model,
+/// since similarities between them are meaningless. Returns the mixed tables.
+pub async fn warn_on_mixed_embedding_models(client: &Client, tables: &[&str]) -> Vec<String> {
+    let mut mixed = Vec::new();
+    for table in tables {
+        match embedding_models(client, table).await {
+            Ok(models) if models.len() > 1 => {
+                let names: Vec<&str> = models.iter().map(|m| m.as_deref().unwrap_or("unknown")).collect();
+                tracing::warn!(
+                    "⚠️  ag_catalog.{} contains vectors from {} embedding models ({}); pass embedding_model to compare only one",
+                    table, names.len(), names.join(", ")
+                );
+                mixed.push(table.to_string());
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("⚠️  Failed to check embedding models in {}: {}", table, e),
+        }
+    }
+    mixed
+}
+
+/// Content hashes of the edges already stored for a session, keyed by edge id
+pub async fn get_session_edge_hashes(
+    client: &Client,
+    session_id: &str,
+) -> Result<HashMap<i64, String>> {
+    let rows = client
+        .query(
+            "SELECT triplet_id, content_hash FROM ag_catalog.embeddings
+             WHERE session_id = $1 AND content_hash IS NOT NULL",
+            &[&session_id],
+        )
+        .await?;
+    Ok(rows.iter().map(|r| (r.get(0), r.get(1))).collect())
+}
+
+/// Store evidence for an edge, all ids in one statement (ids already stored, or
+/// repeated, are skipped)
 pub async fn store_edge_evidence(
     client: &Client,
     edge_id: i64,
     session_id: &str,
     evidence_ids: &[String],
 ) -> Result<()> {
-    for evidence_id in evidence_ids {
-        client
-            .execute(
-                "INSERT INTO ag_catalog.edge_evidence(edge_id, session_id, evidence_message_id) 
-                 VALUES($1, $2, $3)
-                 ON CONFLICT DO NOTHING",
-                &[&edge_id, &session_id, &evidence_id],
-            )
-            .await?;
+    if evidence_ids.is_empty() {
+        return Ok(());
     }
+    client
+        .execute(
+            "INSERT INTO ag_catalog.edge_evidence(edge_id, session_id, evidence_message_id)
+             SELECT u.edge_id, $3, u.evidence_id
+             FROM UNNEST($1::bigint[], $2::text[]) AS u(edge_id, evidence_id)
+             ON CONFLICT DO NOTHING",
+            &[&vec![edge_id; evidence_ids.len()], &evidence_ids, &session_id],
+        )
+        .await?;
+    Ok(())
+}
+
+/// Read a value from the `ingest_metadata` table
+pub async fn get_metadata(client: &impl GenericClient, key: &str) -> Result<Option<String>> {
+    let row = client
+        .query_opt("SELECT value FROM ag_catalog.ingest_metadata WHERE key = $1", &[&key])
+        .await?;
+    Ok(row.map(|r| r.get(0)))
+}
+
+/// Write a value to the `ingest_metadata` table
+pub async fn set_metadata(client: &impl GenericClient, key: &str, value: &str) -> Result<()> {
+    client
+        .execute(
+            "INSERT INTO ag_catalog.ingest_metadata(key, value) VALUES($1, $2)
+             ON CONFLICT (key) DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()",
+            &[&key, &value],
+        )
+        .await?;
     Ok(())
 }
+
+/// Declared dimension of a `vector(n)` column in `ag_catalog`, or `None` for an
+/// unconstrained `vector` column
+pub async fn vector_column_dim(client: &Client, table: &str, column: &str) -> Result<Option<usize>> {
+    let row = client
+        .query_one(
+            "SELECT a.atttypmod FROM pg_attribute a
+             WHERE a.attrelid = ('ag_catalog.' || $1)::regclass AND a.attname = $2 AND NOT a.attisdropped",
+            &[&table, &column],
+        )
+        .await?;
+    // pgvector stores the dimension as the type modifier; -1 means none was given
+    let typmod: i32 = row.get(0);
+    Ok((typmod > 0).then_some(typmod as usize))
+}
+
+/// Compare the configured bucket count with the one the stored embeddings were
+/// hashed with. Records the configured value on first use. Returns the stored
+/// value when it differs, in which case bucket lookups will miss existing data
+/// until `reindex` is run.
+pub async fn check_lsh_buckets(client: &Client, configured: usize) -> Result<Option<usize>> {
+    match get_metadata(client, LSH_BUCKETS_KEY).await? {
+        Some(stored) => {
+            let stored: usize = stored.parse()?;
+            Ok((stored != configured).then_some(stored))
+        }
+        None => {
+            set_metadata(client, LSH_BUCKETS_KEY, &configured.to_string()).await?;
+            Ok(None)
+        }
+    }
+}
+
+/// LSH buckets (one per table) for each `(triplet_id, vector)` pair with the
+/// given bucket and table counts
+pub fn compute_lsh_buckets(rows: &[(i64, Vec<f32>)], buckets: usize, tables: usize) -> Vec<(i64, Vec<i32>)> {
+    // Projections depend on the dimension, so build one hasher per dimension seen
+    let mut hashers: HashMap<usize, LshTables> = HashMap::new();
+    rows.iter()
+        .map(|(id, vec)| {
+            let lsh = hashers
+                .entry(vec.len())
+                .or_insert_with(|| LshTables::new(vec.len(), buckets, tables));
+            (*id, lsh.hash_all(vec))
+        })
+        .collect()
+}
+
+/// Re-hash every stored embedding into `buckets` buckets in each of `tables`
+/// LSH tables and record the new bucket count, all in one transaction.
+/// Returns the number of rows updated.
+pub async fn reindex_lsh_buckets(client: &mut Client, buckets: usize, tables: usize) -> Result<usize> {
+    let tx = client.transaction().await?;
+    
+    let rows = tx
+        .query("SELECT triplet_id, vec FROM ag_catalog.embeddings WHERE vec IS NOT NULL FOR UPDATE", &[])
+        .await?;
+    let mut vectors = Vec::with_capacity(rows.len());
+    for row in &rows {
+        vectors.push((row.get::<_, i64>(0), row.get::<_, Vector>(1).to_vec()));
+    }
+    
+    // Every row has exactly `tables` buckets, so they travel as one flat array
+    // sliced per row by ordinality
+    let tables = tables.max(1);
+    let (ids, new_buckets): (Vec<i64>, Vec<Vec<i32>>) = compute_lsh_buckets(&vectors, buckets, tables).into_iter().unzip();
+    let flat: Vec<i32> = new_buckets.into_iter().flatten().collect();
+    let updated = tx
+        .execute(
+            "UPDATE ag_catalog.embeddings e
+             SET lsh_buckets = ($2::int[])[((u.ord - 1) * $3 + 1)::int : (u.ord * $3)::int],
+                 lsh_bucket = ($2::int[])[((u.ord - 1) * $3 + 1)::int]
+             FROM UNNEST($1::bigint[]) WITH ORDINALITY AS u(id, ord)
+             WHERE e.triplet_id = u.id",
+            &[&ids, &flat, &(tables as i32)],
+        )
+        .await?;
+    set_metadata(&tx, LSH_BUCKETS_KEY, &buckets.to_string()).await?;
+    
+    tx.commit().await?;
+    Ok(updated as usize)
+}
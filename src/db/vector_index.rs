@@ -0,0 +1,213 @@
+//! Approximate nearest neighbor index on `message_embeddings`, chosen by `VECTOR_INDEX`,
+//! and the element type of the embedding columns, chosen by `VECTOR_STORAGE`
+
+use anyhow::Result;
+use tokio_postgres::Client;
+
+/// ivfflat list count when `IVFFLAT_LISTS` is unset
+pub const DEFAULT_IVFFLAT_LISTS: u32 = 100;
+
+/// HNSW links per node when `HNSW_M` is unset (pgvector's default)
+pub const DEFAULT_HNSW_M: u32 = 16;
+
+/// HNSW build-time candidate list size when `HNSW_EF_CONSTRUCTION` is unset (pgvector's default)
+pub const DEFAULT_HNSW_EF_CONSTRUCTION: u32 = 64;
+
+const IVFFLAT_INDEX_NAME: &str = "idx_message_embeddings_ivfflat";
+const HNSW_INDEX_NAME: &str = "idx_message_embeddings_hnsw";
+const KG_EDGE_INDEX_NAME: &str = "idx_kg_edge_embeddings_ivfflat";
+
+/// Per-field edge vectors stored next to `kg_edge_embeddings.embedding` when
+/// `MULTI_FIELD_EMBED` is on, in source, relation, target order
+pub const KG_EDGE_FIELD_COLUMNS: [&str; 3] = ["source_embedding", "relation_embedding", "target_embedding"];
+
+/// Tables whose vector columns follow `VECTOR_STORAGE`, with the ANN indexes on them
+const STORAGE_TABLES: &[(&str, &[&str], &[&str])] = &[
+    ("message_embeddings", &["embedding"], &[IVFFLAT_INDEX_NAME, HNSW_INDEX_NAME]),
+    (
+        "kg_edge_embeddings",
+        &["embedding", KG_EDGE_FIELD_COLUMNS[0], KG_EDGE_FIELD_COLUMNS[1], KG_EDGE_FIELD_COLUMNS[2]],
+        &[KG_EDGE_INDEX_NAME],
+    ),
+];
+
+/// How `message_embeddings` and `kg_edge_embeddings` store their vectors. Rust code
+/// always binds and reads f32 `vector`s; Postgres converts to and from the column type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VectorStorage {
+    /// pgvector `vector`, 4 bytes per dimension
+    #[default]
+    F32,
+    /// pgvector `halfvec`, 2 bytes per dimension: half the storage and index size,
+    /// with cosine rankings all but unchanged
+    F16,
+}
+
+impl VectorStorage {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "f32" | "vector" => Some(VectorStorage::F32),
+            "f16" | "halfvec" => Some(VectorStorage::F16),
+            _ => None,
+        }
+    }
+
+    /// The `VECTOR_STORAGE` name: `f32` or `f16`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VectorStorage::F32 => "f32",
+            VectorStorage::F16 => "f16",
+        }
+    }
+
+    /// pgvector type of the column
+    pub fn pg_type(&self) -> &'static str {
+        match self {
+            VectorStorage::F32 => "vector",
+            VectorStorage::F16 => "halfvec",
+        }
+    }
+
+    /// Column type with its dimension, e.g. `halfvec(768)`
+    pub fn column_type(&self, dim: usize) -> String {
+        format!("{}({})", self.pg_type(), dim)
+    }
+
+    /// Operator class of a cosine ANN index on the column
+    pub fn cosine_ops(&self) -> &'static str {
+        match self {
+            VectorStorage::F32 => "vector_cosine_ops",
+            VectorStorage::F16 => "halfvec_cosine_ops",
+        }
+    }
+
+    /// SQL for the `vector` query parameter `param` (e.g. `$1`) as the column's type,
+    /// so distance operators compare like types and can use the column's index
+    pub fn query_param(&self, param: &str) -> String {
+        match self {
+            VectorStorage::F32 => format!("{}::vector", param),
+            VectorStorage::F16 => format!("{}::vector::halfvec", param),
+        }
+    }
+}
+
+/// Index type and build parameters for `message_embeddings.embedding`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VectorIndex {
+    /// Clusters vectors into `lists` partitions, chosen from the rows present at build
+    /// time. A common starting point is rows / 1000 (sqrt(rows) past a million rows).
+    IvfFlat { lists: u32 },
+    /// Graph index: better recall than ivfflat and builds well on an empty table, but
+    /// slower to build and larger
+    Hnsw { m: u32, ef_construction: u32 },
+}
+
+impl Default for VectorIndex {
+    fn default() -> Self {
+        VectorIndex::IvfFlat { lists: DEFAULT_IVFFLAT_LISTS }
+    }
+}
+
+impl VectorIndex {
+    /// The `VECTOR_INDEX` name: `ivfflat` or `hnsw`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VectorIndex::IvfFlat { .. } => "ivfflat",
+            VectorIndex::Hnsw { .. } => "hnsw",
+        }
+    }
+
+    pub fn index_name(&self) -> &'static str {
+        match self {
+            VectorIndex::IvfFlat { .. } => IVFFLAT_INDEX_NAME,
+            VectorIndex::Hnsw { .. } => HNSW_INDEX_NAME,
+        }
+    }
+
+    /// `CREATE INDEX` statement for this index type and its parameters, on a column
+    /// stored as `storage`
+    pub fn create_sql(&self, storage: VectorStorage) -> String {
+        let with = match self {
+            VectorIndex::IvfFlat { lists } => format!("lists = {}", lists),
+            VectorIndex::Hnsw { m, ef_construction } => format!("m = {}, ef_construction = {}", m, ef_construction),
+        };
+        format!(
+            "CREATE INDEX IF NOT EXISTS {} ON ag_catalog.message_embeddings USING {} (embedding {}) WITH ({})",
+            self.index_name(),
+            self.as_str(),
+            storage.cosine_ops(),
+            with
+        )
+    }
+}
+
+/// Create the configured index if it's missing, and drop the index of the other type
+/// so a switch of `VECTOR_INDEX` doesn't leave both to maintain. An existing index of
+/// the configured type is kept as is; use `rebuild_message_embedding_index` to apply
+/// new parameters.
+pub async fn ensure_message_embedding_index(client: &Client, index: &VectorIndex, storage: VectorStorage) -> Result<()> {
+    let other = match index {
+        VectorIndex::IvfFlat { .. } => HNSW_INDEX_NAME,
+        VectorIndex::Hnsw { .. } => IVFFLAT_INDEX_NAME,
+    };
+    client
+        .batch_execute(&format!("DROP INDEX IF EXISTS ag_catalog.{}; {};", other, index.create_sql(storage)))
+        .await?;
+    Ok(())
+}
+
+/// Drop and recreate the index with the configured type and parameters. Run this after
+/// a bulk load: ivfflat picks its list centers from the rows present when it's built,
+/// so an index created on an empty or small table gives poor recall later.
+pub async fn rebuild_message_embedding_index(client: &Client, index: &VectorIndex, storage: VectorStorage) -> Result<()> {
+    client
+        .batch_execute(&format!(
+            "DROP INDEX IF EXISTS ag_catalog.{}; DROP INDEX IF EXISTS ag_catalog.{}; {};",
+            IVFFLAT_INDEX_NAME,
+            HNSW_INDEX_NAME,
+            index.create_sql(storage)
+        ))
+        .await?;
+    Ok(())
+}
+
+/// `CREATE INDEX` statement for the cosine ivfflat index on `kg_edge_embeddings`
+pub fn kg_edge_index_sql(storage: VectorStorage) -> String {
+    format!(
+        "CREATE INDEX IF NOT EXISTS {} ON ag_catalog.kg_edge_embeddings USING ivfflat (embedding {}) WITH (lists = 50)",
+        KG_EDGE_INDEX_NAME,
+        storage.cosine_ops()
+    )
+}
+
+/// Convert the embedding columns whose type doesn't match `storage`, after a change of
+/// `VECTOR_STORAGE`. Each conversion rewrites its table and drops the ANN indexes on it
+/// (their operator class is type-specific); the caller recreates them afterwards.
+pub async fn ensure_vector_storage(client: &Client, storage: VectorStorage) -> Result<()> {
+    for (table, columns, indexes) in STORAGE_TABLES {
+        for column in *columns {
+            let row = client
+                .query_opt(
+                    "SELECT t.typname, a.atttypmod FROM pg_attribute a JOIN pg_type t ON t.oid = a.atttypid
+                     WHERE a.attrelid = ('ag_catalog.' || $1)::regclass AND a.attname = $2 AND NOT a.attisdropped",
+                    &[table, column],
+                )
+                .await?;
+            let Some(row) = row else { continue };
+            let (current, dim): (String, i32) = (row.get(0), row.get(1));
+            if current == storage.pg_type() {
+                continue;
+            }
+            let column_type = if dim > 0 { storage.column_type(dim as usize) } else { storage.pg_type().to_string() };
+            tracing::info!(table = %table, column = %column, from = %current, to = %column_type, "Converting embedding column (rewrites the table)...");
+            let drops: String = indexes.iter().map(|i| format!("DROP INDEX IF EXISTS ag_catalog.{}; ", i)).collect();
+            client
+                .batch_execute(&format!(
+                    "{}ALTER TABLE ag_catalog.{} ALTER COLUMN {} TYPE {} USING {}::{};",
+                    drops, table, column, column_type, column, column_type
+                ))
+                .await?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,21 @@
+use sha2::{Digest, Sha256};
+
+/// Hex SHA-256 of arbitrary content
+pub fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+/// Hash identifying an edge's content (`source|relation|target`), used to
+/// detect unchanged edges on re-ingest
+pub fn edge_content_hash(source: &str, relation: &str, target: &str) -> String {
+    content_hash(&format!("{}|{}|{}", source, relation, target))
+}
+
+/// Id of a session edge in `embeddings` and `edge_evidence`: the first 8 bytes of
+/// SHA-256 over `session_id|source|relation|target`, as a non-negative i64. It depends
+/// only on the edge's content, so re-ingesting a session with its edges reordered (or
+/// with a different Rust toolchain) keeps every id.
+pub fn session_edge_id(session_id: &str, source: &str, relation: &str, target: &str) -> i64 {
+    let digest = Sha256::digest(format!("{}|{}|{}|{}", session_id, source, relation, target).as_bytes());
+    i64::from_be_bytes(digest[..8].try_into().expect("sha256 is 32 bytes")) & i64::MAX
+}
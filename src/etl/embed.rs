@@ -1,133 +1,544 @@
 use anyhow::Result;
 use serde_json::json;
-use std::time::Instant;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+use crate::config::Config;
+
+/// Where an embedding came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbeddingProvider {
+    /// llama.cpp HTTP server
+    Http,
+    /// Constant placeholder vector (server unset or unreachable)
+    Placeholder,
+}
+
+impl EmbeddingProvider {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmbeddingProvider::Http => "llama_cpp_http",
+            EmbeddingProvider::Placeholder => "placeholder",
+        }
+    }
+
+    /// Model name to record with a vector from this provider. Placeholder vectors
+    /// get their own name so they are never mixed with real ones.
+    pub fn model_name<'a>(&self, configured: &'a str) -> &'a str {
+        match self {
+            EmbeddingProvider::Http => configured,
+            EmbeddingProvider::Placeholder => PLACEHOLDER_MODEL,
+        }
+    }
+}
+
+/// `embedding_model` recorded for placeholder vectors
+pub const PLACEHOLDER_MODEL: &str = "placeholder";
 
 /// Generate embedding for text using llama.cpp HTTP server
 /// Falls back to placeholder if server is not configured
-pub async fn embed_text(text: &str) -> Result<Vec<f32>> {
+pub async fn embed_text(cfg: &Config, text: &str) -> Result<Vec<f32>> {
+    embed_text_with_provider(cfg, text).await.map(|(embedding, _)| embedding)
+}
+
+/// Same as `embed_text`, also reporting which provider produced the vector
+pub async fn embed_text_with_provider(cfg: &Config, text: &str) -> Result<(Vec<f32>, EmbeddingProvider)> {
+    if let Some(server_url) = cfg.embed_server_url.as_deref() {
+        match embed_via_server(cfg, server_url, text).await {
+            Ok(embedding) => return Ok((embedding, EmbeddingProvider::Http)),
+            // A full queue is reported to the caller; a placeholder would hide the overload
+            Err(e) if is_overloaded(&e) => return Err(e),
+            Err(e) => {
+                tracing::warn!(server_url = %server_url, error = ?e, "❌ HTTP embedding failed, falling back to placeholder embeddings");
+            }
+        }
+    } else {
+        tracing::warn!("⚠️  EMBED_SERVER_URL not set (add EMBED_SERVER_URL=http://localhost:8080 to .env)");
+    }
+    Ok(placeholder_with_warning(cfg, text))
+}
+
+/// Like `embed_text_with_provider`, but a failing embedding server is returned as an
+/// error instead of being replaced by a placeholder, so the caller decides what to do.
+/// Without `EMBED_SERVER_URL` the placeholder is still used.
+pub async fn embed_text_strict(cfg: &Config, text: &str) -> Result<(Vec<f32>, EmbeddingProvider)> {
+    match cfg.embed_server_url.as_deref() {
+        Some(server_url) => Ok((embed_via_server(cfg, server_url, text).await?, EmbeddingProvider::Http)),
+        None => Ok(placeholder_with_warning(cfg, text)),
+    }
+}
+
+fn placeholder_with_warning(cfg: &Config, text: &str) -> (Vec<f32>, EmbeddingProvider) {
+    tracing::warn!(kind = cfg.placeholder_embeddings.as_str(), "⚠️  Using placeholder embeddings");
+    crate::telemetry::record_embed_placeholder();
+    (placeholder_for(cfg, text), EmbeddingProvider::Placeholder)
+}
+
+/// Embed `text` with the server at `server_url`, shortening it per `EMBED_OVERFLOW`
+async fn embed_via_server(cfg: &Config, server_url: &str, text: &str) -> Result<Vec<f32>> {
     let start = Instant::now();
-    let cfg = crate::config::Config::from_env();
-    
-    eprintln!("🔍 embed_text called with text length: {} chars", text.len());
-    eprintln!("   First 50 chars: {}", &text.chars().take(50).collect::<String>());
     
-    // Try to use HTTP server first
-    if let Some(server_url) = cfg.embed_server_url {
-        eprintln!("🌐 Attempting HTTP embedding via: {}", server_url);
-        match embed_via_http(&server_url, text).await {
-            Ok(embedding) => {
-                let duration = start.elapsed();
-                eprintln!("✅ HTTP embedding successful:");
-                eprintln!("   Dimension: {}", embedding.len());
-                eprintln!("   Duration: {:?}", duration);
-                eprintln!("   First 5 values: {:?}", &embedding[..5.min(embedding.len())]);
-                return Ok(embedding);
+    tracing::trace!(
+        text_len = text.len(),
+        preview = %text.chars().take(50).collect::<String>(),
+        "embed_text called"
+    );
+    tracing::trace!(server_url = %server_url, "Attempting HTTP embedding");
+    let _permit = embed_limiter(cfg).acquire().await?;
+    let pieces = split_for_embedding(text, cfg.max_embed_chars, cfg.embed_overflow);
+    if pieces.len() > 1 || pieces[0].len() < text.len() {
+        tracing::info!(
+            text_chars = text.chars().count(),
+            max_embed_chars = cfg.max_embed_chars,
+            mode = cfg.embed_overflow.as_str(),
+            pieces = pieces.len(),
+            "✂️  Text longer than MAX_EMBED_CHARS; embedding a shortened input"
+        );
+    }
+    let timeout = Duration::from_secs(cfg.embed_timeout_secs);
+    let mut vectors = Vec::with_capacity(pieces.len());
+    for piece in &pieces {
+        let call_start = Instant::now();
+        let piece_result = embed_via_http(server_url, piece, timeout).await;
+        crate::telemetry::record_embed_request(call_start.elapsed(), piece_result.is_ok());
+        vectors.push(piece_result?);
+    }
+    let embedding = if vectors.len() == 1 {
+        vectors.into_iter().next().expect("one vector")
+    } else {
+        let weights: Vec<usize> = pieces.iter().map(|p| p.chars().count()).collect();
+        mean_pool(&vectors, &weights)
+    };
+    tracing::debug!(
+        dim = embedding.len(),
+        duration_ms = start.elapsed().as_millis() as u64,
+        "HTTP embedding successful"
+    );
+    tracing::trace!(first_values = ?&embedding[..5.min(embedding.len())], "Embedding preview");
+    Ok(embedding)
+}
+
+/// What is embedded when a text is longer than `MAX_EMBED_CHARS`, chosen by `EMBED_OVERFLOW`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmbedOverflow {
+    /// Embed only the first `MAX_EMBED_CHARS` characters
+    #[default]
+    Truncate,
+    /// Embed consecutive chunks of at most `MAX_EMBED_CHARS` characters and store
+    /// their mean, weighted by chunk length
+    Chunk,
+}
+
+impl EmbedOverflow {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "truncate" => Some(EmbedOverflow::Truncate),
+            "chunk" | "pool" => Some(EmbedOverflow::Chunk),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmbedOverflow::Truncate => "truncate",
+            EmbedOverflow::Chunk => "chunk",
+        }
+    }
+}
+
+/// The inputs sent to the embedding server for `text`: the text itself when it has
+/// at most `max_chars` characters (or `max_chars` is 0), otherwise its first
+/// `max_chars` characters (`Truncate`) or all of it in pieces of at most `max_chars`
+/// (`Chunk`). Cuts fall on the last whitespace in the second half of a piece, so
+/// words aren't split unless a piece has no whitespace to break at.
+pub fn split_for_embedding(text: &str, max_chars: usize, overflow: EmbedOverflow) -> Vec<&str> {
+    if max_chars == 0 || text.chars().count() <= max_chars {
+        return vec![text];
+    }
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let Some((limit, _)) = rest.char_indices().nth(max_chars) else {
+            pieces.push(rest.trim_end());
+            break;
+        };
+        let half = rest.char_indices().nth(max_chars / 2).map_or(0, |(i, _)| i);
+        let cut = rest[..limit]
+            .char_indices()
+            .rev()
+            .find(|&(i, c)| i >= half && i > 0 && c.is_whitespace())
+            .map_or(limit, |(i, _)| i);
+        let piece = rest[..cut].trim_end();
+        if !piece.is_empty() {
+            pieces.push(piece);
+        }
+        if overflow == EmbedOverflow::Truncate {
+            break;
+        }
+        rest = rest[cut..].trim_start();
+    }
+    // Only whitespace: nothing worth splitting
+    if pieces.is_empty() {
+        return vec![text];
+    }
+    pieces
+}
+
+/// Mean of `vectors` weighted by `weights`, scaled to unit length
+pub fn mean_pool(vectors: &[Vec<f32>], weights: &[usize]) -> Vec<f32> {
+    let dim = vectors.first().map_or(0, Vec::len);
+    let mut pooled = vec![0.0f32; dim];
+    for (v, &w) in vectors.iter().zip(weights) {
+        for (p, x) in pooled.iter_mut().zip(v) {
+            *p += x * w as f32;
+        }
+    }
+    let norm = crate::etl::similarity::l2_norm(&pooled);
+    if norm > 0.0 {
+        pooled.iter_mut().for_each(|x| *x /= norm);
+    }
+    pooled
+}
+
+/// What the service does with the startup embedding probe, chosen by `EMBED_WARMUP`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmbedWarmup {
+    /// No probe (offline and test environments)
+    Off,
+    /// Probe, and log a warning for each problem found
+    #[default]
+    Warn,
+    /// Probe, and refuse to start if anything is wrong
+    Strict,
+}
+
+impl EmbedWarmup {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "off" | "false" | "0" => Some(EmbedWarmup::Off),
+            "warn" => Some(EmbedWarmup::Warn),
+            "strict" => Some(EmbedWarmup::Strict),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmbedWarmup::Off => "off",
+            EmbedWarmup::Warn => "warn",
+            EmbedWarmup::Strict => "strict",
+        }
+    }
+}
+
+/// Text embedded by the startup probe
+pub const WARMUP_PROBE_TEXT: &str = "warmup";
+
+/// Outcome of the startup embedding probe
+#[derive(Debug, Clone)]
+pub struct WarmupReport {
+    /// Length of the vector the probe got back
+    pub dim: usize,
+    pub provider: EmbeddingProvider,
+    pub duration_ms: u64,
+    /// Everything that would make later embeddings unusable; empty when all is well
+    pub problems: Vec<String>,
+}
+
+/// Embed a probe string, so the first request doesn't pay for loading the model, and
+/// check the vector's length against `EMBED_DIM` and `column_dim`, the dimension of
+/// the `message_embeddings.embedding` column (when known)
+pub async fn warmup(cfg: &Config, column_dim: Option<usize>) -> Result<WarmupReport> {
+    let start = Instant::now();
+    let (embedding, provider) = embed_text_with_provider(cfg, WARMUP_PROBE_TEXT).await?;
+    let dim = embedding.len();
+
+    let mut problems = Vec::new();
+    if provider == EmbeddingProvider::Placeholder {
+        problems.push(match cfg.embed_server_url.as_deref() {
+            Some(url) => format!("embedding server at {} is unreachable; placeholder vectors would be stored", url),
+            None => "EMBED_SERVER_URL is not set; placeholder vectors would be stored".to_string(),
+        });
+    } else {
+        if dim != cfg.embed_dim {
+            problems.push(format!("embedding server returned {} dimensions but EMBED_DIM is {}", dim, cfg.embed_dim));
+        }
+        if let Some(column_dim) = column_dim.filter(|&d| d != dim) {
+            problems.push(format!(
+                "embedding server returned {} dimensions but message_embeddings.embedding is vector({})",
+                dim, column_dim
+            ));
+        }
+    }
+
+    Ok(WarmupReport { dim, provider, duration_ms: start.elapsed().as_millis() as u64, problems })
+}
+
+/// Caps how many embedding server calls run at once, so a burst of queries and
+/// ingests can't overwhelm a single llama.cpp instance. Callers beyond the cap
+/// wait their turn; once `max_queued` are waiting, further callers are turned away.
+pub struct EmbedLimiter {
+    permits: Semaphore,
+    max_queued: usize,
+    queued: AtomicUsize,
+}
+
+impl EmbedLimiter {
+    pub fn new(max_concurrent: usize, max_queued: usize) -> Self {
+        EmbedLimiter { permits: Semaphore::new(max_concurrent), max_queued, queued: AtomicUsize::new(0) }
+    }
+
+    /// Wait for a free slot, or fail with `EmbedError::Overloaded` if the queue is full.
+    /// The slot is released when the permit is dropped.
+    pub async fn acquire(&self) -> Result<SemaphorePermit<'_>, EmbedError> {
+        if let Ok(permit) = self.permits.try_acquire() {
+            return Ok(permit);
+        }
+        let queued = self.queued.fetch_add(1, Ordering::SeqCst);
+        let _slot = QueueSlot(&self.queued);
+        if queued >= self.max_queued {
+            return Err(EmbedError::Overloaded { queued });
+        }
+        Ok(self.permits.acquire().await.expect("embedding semaphore is never closed"))
+    }
+
+    /// Calls waiting for a slot
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+}
+
+/// Leaves the queue when dropped, including when the waiting caller is cancelled
+struct QueueSlot<'a>(&'a AtomicUsize);
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// The process-wide limiter, sized from `MAX_CONCURRENT_EMBEDS` and `EMBED_QUEUE_LIMIT`
+/// by the first config that embeds
+fn embed_limiter(cfg: &Config) -> &'static EmbedLimiter {
+    static LIMITER: OnceLock<EmbedLimiter> = OnceLock::new();
+    LIMITER.get_or_init(|| EmbedLimiter::new(cfg.max_concurrent_embeds, cfg.embed_queue_limit))
+}
+
+/// Whether `e` is an embedding call turned away because the queue was full
+pub fn is_overloaded(e: &anyhow::Error) -> bool {
+    matches!(e.downcast_ref::<EmbedError>(), Some(EmbedError::Overloaded { .. }))
+}
+
+/// Timeout for the readiness probe's embedding server ping
+pub const HEALTH_PING_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Why a call to the embedding server failed
+#[derive(Debug)]
+pub enum EmbedError {
+    /// No response within the configured timeout
+    Timeout { endpoint: String, after: Duration },
+    /// Connection or transport failure
+    Request(String),
+    /// The server answered with a non-success status
+    Status { status: u16, body: String },
+    /// The response body wasn't an embedding
+    InvalidResponse(String),
+    /// Too many calls were already waiting for the embedding server
+    Overloaded { queued: usize },
+}
+
+impl EmbedError {
+    fn from_reqwest(e: reqwest::Error, endpoint: &str, timeout: Duration) -> Self {
+        if e.is_timeout() {
+            EmbedError::Timeout { endpoint: endpoint.to_string(), after: timeout }
+        } else {
+            EmbedError::Request(e.to_string())
+        }
+    }
+}
+
+impl std::fmt::Display for EmbedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EmbedError::Timeout { endpoint, after } => {
+                write!(f, "Embedding server {} did not respond within {:?}", endpoint, after)
             }
-            Err(e) => {
-                eprintln!("❌ HTTP embedding failed: {}", e);
-                eprintln!("   Error details: {:?}", e);
-                eprintln!("   Falling back to placeholder embeddings");
+            EmbedError::Request(e) => write!(f, "Embedding request failed: {}", e),
+            EmbedError::Status { status, body } => write!(f, "Embedding server returned {}: {}", status, body),
+            EmbedError::InvalidResponse(e) => write!(f, "Invalid embedding response: {}", e),
+            EmbedError::Overloaded { queued } => {
+                write!(f, "Embedding server is busy ({} requests already waiting); retry later", queued)
             }
         }
+    }
+}
+
+impl std::error::Error for EmbedError {}
+
+/// Idle keep-alive connections kept open to the embedding server
+const POOL_MAX_IDLE_PER_HOST: usize = 32;
+
+/// HTTP client shared by every embedding call, built on first use so batch
+/// ingest reuses pooled keep-alive connections instead of reconnecting per edge.
+/// Timeouts are set per request so one client serves both embeds and health pings.
+pub fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .pool_max_idle_per_host(POOL_MAX_IDLE_PER_HOST)
+            .pool_idle_timeout(Duration::from_secs(90))
+            .tcp_keepalive(Duration::from_secs(60))
+            .build()
+            .expect("failed to build embedding HTTP client")
+    })
+}
+
+/// Check the embedding server's `/health` endpoint, giving up after `timeout`
+pub async fn ping_embed_server(server_url: &str, timeout: Duration) -> Result<(), EmbedError> {
+    let endpoint = format!("{}/health", server_url);
+    let response = http_client()
+        .get(&endpoint)
+        .timeout(timeout)
+        .send()
+        .await
+        .map_err(|e| EmbedError::from_reqwest(e, &endpoint, timeout))?;
+    if response.status().is_success() {
+        Ok(())
     } else {
-        eprintln!("⚠️  EMBED_SERVER_URL not set in .env");
-        eprintln!("   Add: EMBED_SERVER_URL=http://localhost:8080");
+        Err(EmbedError::Status { status: response.status().as_u16(), body: response.text().await.unwrap_or_default() })
     }
-    
-    // Fallback to placeholder
-    eprintln!("⚠️  Using placeholder embeddings (all 0.1)");
-    Ok(vec![0.1f32; 768])
 }
 
-async fn embed_via_http(server_url: &str, text: &str) -> Result<Vec<f32>> {
+/// Placeholder vector used when no embedding server is available
+pub fn placeholder_embedding() -> Vec<f32> {
+    vec![0.1f32; 768]
+}
+
+/// Which placeholder vector is stored when no embedding server is available, chosen
+/// by `PLACEHOLDER_EMBEDDINGS`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PlaceholderKind {
+    /// `placeholder_embedding()` for every text
+    #[default]
+    Constant,
+    /// `text_placeholder_embedding()`: distinct, reproducible vectors per text, so
+    /// ranking and LSH bucketing can be exercised without an embedding server
+    Hashed,
+}
+
+impl PlaceholderKind {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "constant" => Some(PlaceholderKind::Constant),
+            "hashed" => Some(PlaceholderKind::Hashed),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PlaceholderKind::Constant => "constant",
+            PlaceholderKind::Hashed => "hashed",
+        }
+    }
+}
+
+/// Unit-length `dim`-dimensional vector seeded from a hash of `text`: the same text
+/// always gets the same vector, different texts get unrelated ones. Carries no
+/// meaning, so similar texts are no closer than any others.
+pub fn text_placeholder_embedding(text: &str, dim: usize) -> Vec<f32> {
+    use rand::{Rng, SeedableRng};
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::digest(text.as_bytes());
+    let seed = u64::from_le_bytes(digest[..8].try_into().expect("sha256 is 32 bytes"));
+    let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+    let mut v: Vec<f32> = (0..dim).map(|_| rng.gen_range(-1.0f32..1.0)).collect();
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+    v
+}
+
+/// The placeholder vector for `text` under `PLACEHOLDER_EMBEDDINGS`
+pub fn placeholder_for(cfg: &Config, text: &str) -> Vec<f32> {
+    match cfg.placeholder_embeddings {
+        PlaceholderKind::Constant => placeholder_embedding(),
+        PlaceholderKind::Hashed => text_placeholder_embedding(text, cfg.embed_dim),
+    }
+}
+
+/// Request one embedding from the llama.cpp server, giving up after `timeout`
+pub async fn embed_via_http(server_url: &str, text: &str, timeout: Duration) -> Result<Vec<f32>, EmbedError> {
     let start = Instant::now();
     
-    eprintln!("   → Building HTTP client...");
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .map_err(|e| {
-            eprintln!("   ❌ Failed to build HTTP client: {}", e);
-            e
-        })?;
-    
     let endpoint = format!("{}/embedding", server_url);
-    eprintln!("   → Sending POST request to: {}", endpoint);
-    
     let payload = json!({ "content": text });
-    eprintln!("   → Payload: {}", serde_json::to_string(&payload).unwrap_or_default());
+    tracing::trace!(endpoint = %endpoint, payload = %payload, "Sending embedding request");
     
-    let response = client
+    let response = http_client()
         .post(&endpoint)
         .json(&payload)
+        .timeout(timeout)
         .send()
         .await
         .map_err(|e| {
-            eprintln!("   ❌ HTTP request failed: {}", e);
-            eprintln!("      Is the llama.cpp server running on {}?", server_url);
-            e
+            tracing::error!(error = %e, server_url, "HTTP request failed (is the llama.cpp server running?)");
+            EmbedError::from_reqwest(e, &endpoint, timeout)
         })?;
     
     let status = response.status();
-    eprintln!("   → Response status: {}", status);
+    tracing::trace!(status = %status, "Embedding response received");
     
     if !status.is_success() {
-        let body = response.text().await?;
-        eprintln!("   ❌ Server returned error body: {}", body);
-        return Err(anyhow::anyhow!(
-            "Embedding server returned {}: {}", 
-            status, 
-            body
-        ));
+        let body = response.text().await.unwrap_or_default();
+        tracing::error!(status = %status, body = %body, "Embedding server returned an error");
+        return Err(EmbedError::Status { status: status.as_u16(), body });
     }
     
-    eprintln!("   → Parsing JSON response...");
     let result: serde_json::Value = response.json().await.map_err(|e| {
-        eprintln!("   ❌ Failed to parse JSON: {}", e);
-        e
+        tracing::error!(error = %e, "Failed to parse embedding JSON");
+        EmbedError::from_reqwest(e, &endpoint, timeout)
     })?;
     
     // llama.cpp server returns: [{"index": 0, "embedding": [[...values...]]}]
     // We need to extract the first item's embedding array
-    eprintln!("   → Extracting embedding from response...");
-    
     let embedding = if let Some(arr) = result.as_array() {
         // Response is an array, get first item
-        eprintln!("   → Response is array with {} items", arr.len());
-        arr.get(0)
+        tracing::trace!(items = arr.len(), "Response is an array");
+        arr.first()
             .and_then(|item| item["embedding"].as_array())
-            .and_then(|emb_arr| emb_arr.get(0))
+            .and_then(|emb_arr| emb_arr.first())
             .and_then(|inner| inner.as_array())
             .ok_or_else(|| {
-                eprintln!("   ❌ Unexpected array structure");
-                eprintln!("      Response: {}", serde_json::to_string(&result).unwrap_or_default());
-                anyhow::anyhow!("Unexpected array structure in response")
+                tracing::error!(response = %result, "Unexpected array structure in embedding response");
+                EmbedError::InvalidResponse("unexpected array structure".to_string())
             })?
     } else if let Some(obj) = result.as_object() {
         // Response is an object, try direct embedding field
-        eprintln!("   → Response is object with keys: {:?}", obj.keys().collect::<Vec<_>>());
+        tracing::trace!(keys = ?obj.keys().collect::<Vec<_>>(), "Response is an object");
         result["embedding"]
             .as_array()
             .ok_or_else(|| {
-                eprintln!("   ❌ No 'embedding' field in response");
-                eprintln!("      Response: {}", serde_json::to_string(&result).unwrap_or_default());
-                anyhow::anyhow!("No 'embedding' field in response")
+                tracing::error!(response = %result, "No 'embedding' field in embedding response");
+                EmbedError::InvalidResponse("no 'embedding' field".to_string())
             })?
     } else {
-        eprintln!("   ❌ Response is neither array nor object");
-        return Err(anyhow::anyhow!("Invalid response format"));
+        tracing::error!(response = %result, "Embedding response is neither array nor object");
+        return Err(EmbedError::InvalidResponse("neither an array nor an object".to_string()));
     };
     
     let embedding: Vec<f32> = embedding
         .iter()
-        .map(|v| v.as_f64().ok_or_else(|| anyhow::anyhow!("Invalid embedding value")))
-        .collect::<Result<Vec<f64>>>()?
+        .map(|v| v.as_f64().ok_or_else(|| EmbedError::InvalidResponse(format!("invalid embedding value {}", v))))
+        .collect::<Result<Vec<f64>, _>>()?
         .into_iter()
         .map(|f| f as f32)
         .collect();
     
-    let duration = start.elapsed();
-    eprintln!("   ✅ Embedding extracted successfully in {:?}", duration);
+    tracing::trace!(duration_ms = start.elapsed().as_millis() as u64, "Embedding extracted");
     
     Ok(embedding)
 }
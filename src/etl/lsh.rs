@@ -6,16 +6,34 @@ pub struct Lsh {
     buckets: usize,
 }
 
+/// Seed of the first hash table (the single-table LSH stored in `lsh_bucket`)
+const BASE_SEED: u64 = 42;
+
 impl Lsh {
     pub fn new(dim: usize, buckets: usize) -> Self {
+        Self::with_seed(dim, buckets, BASE_SEED)
+    }
+
+    /// Hasher with its own random projections, for independent hash tables
+    pub fn with_seed(dim: usize, buckets: usize, seed: u64) -> Self {
         use rand::{Rng, SeedableRng};
-        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
-        let projections = Array2::from_shape_fn((buckets as usize, dim), |_| rng.gen::<f32>());
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        let projections = Array2::from_shape_fn((buckets, dim), |_| rng.gen::<f32>());
         Self { projections, buckets }
     }
 
+    /// Bucket for `v`. The vector is L2-normalized first, so only its direction
+    /// matters: `hash(v) == hash(k * v)` for any `k > 0`, matching the cosine
+    /// metric used to rank candidates. Stored vectors keep their magnitude (the
+    /// dot and euclidean metrics need it); ingest and query both hash through here.
     pub fn hash(&self, v: &[f32]) -> usize {
-        let v = Array1::from_vec(v.to_vec());
+        // Norm in f64 so large components don't overflow to infinity
+        let norm = v.iter().map(|&x| (x as f64).powi(2)).sum::<f64>().sqrt();
+        let v = if norm > 0.0 && norm.is_finite() {
+            Array1::from_iter(v.iter().map(|&x| (x as f64 / norm) as f32))
+        } else {
+            Array1::from_vec(v.to_vec())
+        };
         let mut bits = 0usize;
         for (i, row) in self.projections.outer_iter().enumerate() {
             let dot = row.dot(&v);
@@ -26,3 +44,46 @@ impl Lsh {
         bits % self.buckets
     }
 }
+
+/// `L` independent hash tables. A vector is a candidate match when it shares a
+/// bucket with the query in any table.
+pub struct LshTables {
+    tables: Vec<Lsh>,
+    buckets: usize,
+}
+
+impl LshTables {
+    /// Table 0 uses the same projections as `Lsh::new`, so single-table buckets stay valid
+    pub fn new(dim: usize, buckets: usize, tables: usize) -> Self {
+        let tables = (0..tables.max(1) as u64)
+            .map(|i| Lsh::with_seed(dim, buckets, BASE_SEED + i))
+            .collect();
+        Self { tables, buckets }
+    }
+
+    /// Bucket in every table, offset by `table_idx * buckets` so buckets from
+    /// different tables never compare equal in an array overlap (`&&`) query.
+    /// The first entry is the plain single-table bucket.
+    pub fn hash_all(&self, v: &[f32]) -> Vec<i32> {
+        self.tables
+            .iter()
+            .enumerate()
+            .map(|(i, lsh)| (i * self.buckets + lsh.hash(v)) as i32)
+            .collect()
+    }
+}
+
+/// Gini coefficient of bucket occupancy: 0.0 when every bucket holds the same number
+/// of embeddings, approaching 1.0 as they collapse into a single bucket. Empty buckets
+/// must be included as zeros. 0.0 for no embeddings at all.
+pub fn gini_coefficient(counts: &[i64]) -> f64 {
+    let total: i64 = counts.iter().sum();
+    if counts.is_empty() || total == 0 {
+        return 0.0;
+    }
+    let mut sorted = counts.to_vec();
+    sorted.sort_unstable();
+    let n = sorted.len() as f64;
+    let weighted: f64 = sorted.iter().enumerate().map(|(i, c)| (i + 1) as f64 * *c as f64).sum();
+    2.0 * weighted / (n * total as f64) - (n + 1.0) / n
+}
@@ -1,3 +1,9 @@
 pub mod parser;
 pub mod embed;
 pub mod lsh;
+pub mod similarity;
+pub mod content_hash;
+pub mod sanitize;
+pub mod roles;
+pub mod stop_words;
+pub mod payload;
@@ -57,12 +57,143 @@ impl Default for ParsedTriplet {
 }
 
 /// Dummy parser: assume JSON line already matches ParsedTriplet.
-/// Replace with real extractor for NRN strings/artifacts.
+/// Use `extract_triplets` for free-text statements.
 pub fn parse_line(line: &str) -> anyhow::Result<ParsedTriplet> {
     let t: ParsedTriplet = serde_json::from_str(line)?;
     Ok(t)
 }
 
+// ============================================================================
+// Free-text Triplet Extraction
+// ============================================================================
+
+#[derive(Debug, PartialEq)]
+enum TripletToken {
+    Word(String),
+    Arrow { relation: String, reversed: bool },
+    Invalid,
+}
+
+/// Extract subject–relation–object triplets from free text.
+///
+/// Supported syntaxes (statements are separated by newlines or `;`):
+/// - plain: `alice AUTHORED_BY email_123` (relation must be UPPER_SNAKE_CASE,
+///   entities must not be)
+/// - arrow: `alice -[AUTHORED_BY]-> email_123` or `email_123 <-[AUTHORED_BY]- alice`
+///
+/// Chains such as `a -[R]-> b -[S]-> c` yield one triplet per hop.
+/// Ids are deterministic hashes of `subject|relation|object`.
+pub fn extract_triplets(text: &str) -> Vec<ParsedTriplet> {
+    let mut triplets = Vec::new();
+
+    for statement in text.split(['\n', ';']) {
+        let tokens = tokenize_statement(statement);
+        let mut i = 1;
+        while i + 1 < tokens.len() {
+            let found = match (&tokens[i - 1], &tokens[i], &tokens[i + 1]) {
+                (TripletToken::Word(a), TripletToken::Arrow { relation, reversed }, TripletToken::Word(b)) => {
+                    if *reversed {
+                        Some((b, relation, a))
+                    } else {
+                        Some((a, relation, b))
+                    }
+                }
+                (TripletToken::Word(a), TripletToken::Word(rel), TripletToken::Word(b))
+                    if is_relation_token(rel) && !is_relation_token(a) && !is_relation_token(b) =>
+                {
+                    Some((a, rel, b))
+                }
+                _ => None,
+            };
+
+            match found {
+                Some((subject, relation, object)) => {
+                    triplets.push(ParsedTriplet {
+                        id: triplet_id(subject, relation, object),
+                        subject: ParsedNode::from(subject.as_str()),
+                        relationship: relation.clone(),
+                        object: ParsedNode::from(object.as_str()),
+                        edge_props: Value::Null,
+                    });
+                    // The object may start the next triplet in a chain
+                    i += 2;
+                }
+                None => i += 1,
+            }
+        }
+    }
+
+    triplets
+}
+
+/// Split a statement into entity words and arrow relations
+fn tokenize_statement(statement: &str) -> Vec<TripletToken> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut rest = statement;
+
+    fn flush(word: &mut String, tokens: &mut Vec<TripletToken>) {
+        let trimmed = word.trim_matches(|c: char| !(c.is_alphanumeric() || c == '_'));
+        if !trimmed.is_empty() {
+            tokens.push(TripletToken::Word(trimmed.to_string()));
+        }
+        word.clear();
+    }
+
+    while let Some(c) = rest.chars().next() {
+        let (open, close, reversed) = if rest.starts_with("<-[") {
+            ("<-[", "]-", true)
+        } else if rest.starts_with("-[") {
+            ("-[", "]->", false)
+        } else {
+            if c.is_whitespace() {
+                flush(&mut word, &mut tokens);
+            } else {
+                word.push(c);
+            }
+            rest = &rest[c.len_utf8()..];
+            continue;
+        };
+
+        flush(&mut word, &mut tokens);
+        let body = &rest[open.len()..];
+        match body.find(close) {
+            Some(end) if !body[..end].trim().is_empty() => {
+                tokens.push(TripletToken::Arrow {
+                    relation: body[..end].trim().to_string(),
+                    reversed,
+                });
+                rest = &body[end + close.len()..];
+            }
+            _ => {
+                // Unterminated or empty arrow: nothing after it can form a triplet
+                tokens.push(TripletToken::Invalid);
+                break;
+            }
+        }
+    }
+    flush(&mut word, &mut tokens);
+
+    tokens
+}
+
+/// Relation tokens look like `AUTHORED_BY`: uppercase letters, digits and underscores
+fn is_relation_token(s: &str) -> bool {
+    s.len() >= 2
+        && s.chars().any(|c| c.is_ascii_uppercase())
+        && s.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Deterministic, positive triplet id (FNV-1a, stable across Rust versions)
+fn triplet_id(subject: &str, relation: &str, object: &str) -> i64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in format!("{}|{}|{}", subject, relation, object).bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    (hash & i64::MAX as u64) as i64
+}
+
 // ============================================================================
 // New Models for ok.json Format
 // ============================================================================
@@ -75,8 +206,10 @@ pub struct KnowledgeNode {
     pub node_type: String,
 }
 
-/// Edge from ok.json format
+/// Edge from ok.json format. Evidence ids follow the same rules as
+/// `db::models::KGEdge` but are kept verbatim, since they are stored as text.
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(try_from = "RawKnowledgeEdge")]
 pub struct KnowledgeEdge {
     pub source: String,
     pub relation: String,
@@ -84,6 +217,25 @@ pub struct KnowledgeEdge {
     pub evidence_message_ids: Vec<String>,
 }
 
+#[derive(Deserialize)]
+struct RawKnowledgeEdge {
+    source: String,
+    relation: String,
+    target: String,
+    #[serde(default)]
+    evidence_message_ids: Vec<Value>,
+}
+
+impl TryFrom<RawKnowledgeEdge> for KnowledgeEdge {
+    type Error = String;
+
+    fn try_from(raw: RawKnowledgeEdge) -> Result<Self, Self::Error> {
+        let evidence_message_ids =
+            crate::db::models::parse_evidence_ids(&raw.source, &raw.relation, &raw.target, &raw.evidence_message_ids)?;
+        Ok(KnowledgeEdge { source: raw.source, relation: raw.relation, target: raw.target, evidence_message_ids })
+    }
+}
+
 /// Session graph containing nodes and edges
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SessionGraph {
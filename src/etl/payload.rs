@@ -0,0 +1,108 @@
+//! JSON ingest payloads parsed with the failing record and field pinpointed, so a
+//! malformed `turn_embeddings.json` or KG file says where it's wrong instead of only
+//! "failed to deserialize"
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_path_to_error::{Path, Segment};
+use std::fmt;
+
+/// Where in a JSON payload deserialization failed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PayloadLocation {
+    /// Path to the offending value, e.g. `turns[3].embedding` (`.` for the document root)
+    pub path: String,
+    /// Index of the innermost array element containing the error (the record)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record: Option<usize>,
+    /// Name of the offending field, including one that's missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    /// 1-based line and column reported by the JSON parser
+    pub line: usize,
+    pub column: usize,
+    /// Byte offset of `line`/`column` in the payload
+    pub offset: usize,
+}
+
+/// A payload that isn't valid JSON or doesn't have the expected shape
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayloadError {
+    pub location: PayloadLocation,
+    /// The parser's message without its position, e.g. `invalid type: string "x", expected a sequence`
+    pub message: String,
+}
+
+impl fmt::Display for PayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let loc = &self.location;
+        if let Some(record) = loc.record {
+            write!(f, "record {}: ", record)?;
+        }
+        if let Some(field) = &loc.field {
+            write!(f, "field `{}`: ", field)?;
+        }
+        write!(
+            f,
+            "{} (at {}, line {} column {}, byte {})",
+            self.message, loc.path, loc.line, loc.column, loc.offset
+        )
+    }
+}
+
+impl std::error::Error for PayloadError {}
+
+/// Deserialize `text` as `T`, reporting the record, field and position of the first
+/// value that doesn't fit
+pub fn parse_payload<T: DeserializeOwned>(text: &str) -> Result<T, PayloadError> {
+    let mut de = serde_json::Deserializer::from_str(text);
+    let value = serde_path_to_error::deserialize(&mut de)
+        .map_err(|e| payload_error(text, Some(e.path()), e.inner()))?;
+    // Trailing characters after the document are an error, as in `serde_json::from_str`
+    de.end().map_err(|e| payload_error(text, None, &e))?;
+    Ok(value)
+}
+
+fn payload_error(text: &str, path: Option<&Path>, err: &serde_json::Error) -> PayloadError {
+    let segments: Vec<&Segment> = path.map(|p| p.iter().collect()).unwrap_or_default();
+    let record = segments.iter().rev().find_map(|s| match s {
+        Segment::Seq { index } => Some(*index),
+        _ => None,
+    });
+    let message = strip_position(&err.to_string());
+    // A missing field is reported on its parent, so the name is only in the message
+    let field = missing_field(&message).or_else(|| match segments.last() {
+        Some(Segment::Map { key }) => Some(key.clone()),
+        _ => None,
+    });
+    PayloadError {
+        location: PayloadLocation {
+            path: path.map_or_else(|| ".".to_string(), Path::to_string),
+            record,
+            field,
+            line: err.line(),
+            column: err.column(),
+            offset: byte_offset(text, err.line(), err.column()),
+        },
+        message,
+    }
+}
+
+/// `serde_json` appends ` at line L column C`; the location carries those separately
+fn strip_position(message: &str) -> String {
+    match message.rfind(" at line ") {
+        Some(pos) => message[..pos].to_string(),
+        None => message.to_string(),
+    }
+}
+
+fn missing_field(message: &str) -> Option<String> {
+    let rest = message.strip_prefix("missing field `")?;
+    Some(rest[..rest.find('`')?].to_string())
+}
+
+/// Byte offset of a 1-based `line`/`column` (serde_json counts columns in bytes)
+pub fn byte_offset(text: &str, line: usize, column: usize) -> usize {
+    let line_start: usize = text.split_inclusive('\n').take(line.saturating_sub(1)).map(str::len).sum();
+    (line_start + column.saturating_sub(1)).min(text.len())
+}
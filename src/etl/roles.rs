@@ -0,0 +1,77 @@
+/// Role prefixes recognized when `ROLE_PREFIXES` is unset (`prefix=role` pairs)
+pub const DEFAULT_ROLE_PREFIXES: &str = "user=user,assistant=assistant,system=system,tool=tool,function=tool";
+
+/// Separator between a role prefix and the message when `ROLE_SEPARATORS` is unset
+pub const DEFAULT_ROLE_SEPARATOR: &str = ":";
+
+/// Role given to messages without a recognized prefix when `DEFAULT_ROLE` is unset
+pub const DEFAULT_ROLE: &str = "user";
+
+/// How stored message content marks its speaker, e.g. `user: hi`, `USER> hi` or `AI: hi`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoleScheme {
+    /// Lowercased prefix and the canonical role it stands for
+    pub prefixes: Vec<(String, String)>,
+    /// Strings that may end a prefix; the earliest one in the content that follows a known prefix wins
+    pub separators: Vec<String>,
+    /// Role for content without a recognized prefix
+    pub default_role: String,
+}
+
+impl Default for RoleScheme {
+    fn default() -> Self {
+        RoleScheme {
+            prefixes: parse_role_prefixes(DEFAULT_ROLE_PREFIXES).expect("default role prefixes are valid"),
+            separators: vec![DEFAULT_ROLE_SEPARATOR.to_string()],
+            default_role: DEFAULT_ROLE.to_string(),
+        }
+    }
+}
+
+impl RoleScheme {
+    /// Split `content` into its role and the message after the prefix, e.g.
+    /// `"Human: hello"` -> `("user", "hello")` with `human=user` configured.
+    /// Content without a known prefix is returned whole with `default_role`.
+    pub fn parse<'a>(&self, content: &'a str) -> (String, &'a str) {
+        let found = self
+            .separators
+            .iter()
+            .filter_map(|sep| content.find(sep.as_str()).map(|pos| (pos, sep.len())))
+            .filter_map(|(pos, sep_len)| {
+                let prefix = content[..pos].trim().to_lowercase();
+                self.prefixes
+                    .iter()
+                    .find(|(p, _)| *p == prefix)
+                    .map(|(_, role)| (pos, role.clone(), content[pos + sep_len..].trim()))
+            })
+            .min_by_key(|(pos, _, _)| *pos);
+
+        match found {
+            Some((_, role, rest)) => (role, rest),
+            None => (self.default_role.clone(), content),
+        }
+    }
+}
+
+/// Parse `ROLE_PREFIXES`: comma-separated `prefix=role` pairs such as
+/// `human=user,ai=assistant`. Both sides are lowercased. `None` if a pair is malformed.
+pub fn parse_role_prefixes(s: &str) -> Option<Vec<(String, String)>> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (prefix, role) = pair.split_once('=')?;
+            let (prefix, role) = (prefix.trim().to_lowercase(), role.trim().to_lowercase());
+            (!prefix.is_empty() && !role.is_empty()).then_some((prefix, role))
+        })
+        .collect()
+}
+
+/// Parse `ROLE_SEPARATORS`: comma-separated strings such as `:,>`
+pub fn parse_role_separators(s: &str) -> Vec<String> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|sep| !sep.is_empty())
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,27 @@
+use std::borrow::Cow;
+
+/// Make message content safe to store and index. Postgres `TEXT` can't hold NUL, and
+/// stray control characters end up as garbage tokens in `to_tsvector`, so:
+/// NUL bytes are removed, `\r\n` and lone `\r` become `\n`, and every other control
+/// character except `\n` and `\t` becomes a space. Everything else, including runs of
+/// whitespace (code blocks), is kept. Clean content is returned without copying.
+pub fn sanitize_content(content: &str) -> Cow<'_, str> {
+    if !content.chars().any(|c| c.is_control() && c != '\n' && c != '\t') {
+        return Cow::Borrowed(content);
+    }
+    let mut sanitized = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\0' => {}
+            '\r' => {
+                chars.next_if_eq(&'\n');
+                sanitized.push('\n');
+            }
+            '\n' | '\t' => sanitized.push(c),
+            c if c.is_control() => sanitized.push(' '),
+            c => sanitized.push(c),
+        }
+    }
+    Cow::Owned(sanitized)
+}
@@ -0,0 +1,241 @@
+//! Vector similarity helpers shared by the retrieval paths.
+
+use std::cmp::Ordering;
+
+/// Cosine similarity computed in a single pass over both slices.
+/// Returns 0.0 when either vector has zero norm.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let mut dot = 0.0f32;
+    let mut norm_a = 0.0f32;
+    let mut norm_b = 0.0f32;
+    for (x, y) in a.iter().zip(b.iter()) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a.sqrt() * norm_b.sqrt())
+    }
+}
+
+/// L2 norm of a vector
+pub fn l2_norm(v: &[f32]) -> f32 {
+    v.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+/// Query vector with its norm precomputed, for scoring against many vectors in
+/// process (`retrieve::score_candidates`) without recomputing the query norm per
+/// row. Stored edge vectors are ranked by pgvector in SQL, so their norms are never
+/// recomputed here and aren't cached.
+pub struct CosineQuery<'a> {
+    vec: &'a [f32],
+    norm: f32,
+}
+
+impl<'a> CosineQuery<'a> {
+    pub fn new(vec: &'a [f32]) -> Self {
+        Self { vec, norm: l2_norm(vec) }
+    }
+
+    /// Cosine similarity between the query and a stored vector
+    pub fn similarity(&self, other: &[f32]) -> f32 {
+        let mut dot = 0.0f32;
+        let mut norm_other = 0.0f32;
+        for (x, y) in self.vec.iter().zip(other.iter()) {
+            dot += x * y;
+            norm_other += y * y;
+        }
+        if self.norm == 0.0 || norm_other == 0.0 {
+            0.0
+        } else {
+            dot / (self.norm * norm_other.sqrt())
+        }
+    }
+}
+
+/// Dot product of two vectors
+pub fn dot_product(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
+/// Euclidean (L2) distance between two vectors
+pub fn euclidean_distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b.iter()).map(|(x, y)| (x - y) * (x - y)).sum::<f32>().sqrt()
+}
+
+/// Metric used to rank stored vectors against a query.
+/// `similarity` is higher-is-closer and `distance` is smaller-is-closer for every metric.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SimilarityMetric {
+    /// Cosine similarity; distance is `1 - cos`
+    #[default]
+    Cosine,
+    /// Inner product; distance is the negated inner product (as pgvector's `<#>`)
+    Dot,
+    /// L2 distance; similarity is `1 / (1 + d)`
+    Euclidean,
+}
+
+impl SimilarityMetric {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "cosine" => Some(SimilarityMetric::Cosine),
+            "dot" | "inner_product" => Some(SimilarityMetric::Dot),
+            "euclidean" | "l2" => Some(SimilarityMetric::Euclidean),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SimilarityMetric::Cosine => "cosine",
+            SimilarityMetric::Dot => "dot",
+            SimilarityMetric::Euclidean => "euclidean",
+        }
+    }
+
+    /// Distance between two vectors (smaller = closer)
+    pub fn distance(&self, a: &[f32], b: &[f32]) -> f32 {
+        match self {
+            SimilarityMetric::Cosine => 1.0 - cosine_similarity(a, b),
+            SimilarityMetric::Dot => -dot_product(a, b),
+            SimilarityMetric::Euclidean => euclidean_distance(a, b),
+        }
+    }
+
+    /// Similarity derived from a distance of this metric (higher = closer)
+    pub fn similarity_from_distance(&self, distance: f32) -> f32 {
+        match self {
+            SimilarityMetric::Cosine => 1.0 - distance,
+            SimilarityMetric::Dot => -distance,
+            SimilarityMetric::Euclidean => 1.0 / (1.0 + distance),
+        }
+    }
+
+    /// Distance of this metric that `similarity_from_distance` maps to `similarity`
+    pub fn distance_from_similarity(&self, similarity: f32) -> f32 {
+        match self {
+            SimilarityMetric::Cosine => 1.0 - similarity,
+            SimilarityMetric::Dot => -similarity,
+            SimilarityMetric::Euclidean => 1.0 / similarity - 1.0,
+        }
+    }
+
+    /// pgvector distance operator for this metric
+    pub fn pg_operator(&self) -> &'static str {
+        match self {
+            SimilarityMetric::Cosine => "<=>",
+            SimilarityMetric::Dot => "<#>",
+            SimilarityMetric::Euclidean => "<->",
+        }
+    }
+
+    /// SQL expression for the similarity between `column` and the query parameter `param`,
+    /// matching `similarity_from_distance`
+    pub fn pg_similarity_sql(&self, column: &str, param: &str) -> String {
+        let op = self.pg_operator();
+        match self {
+            SimilarityMetric::Cosine => format!("1 - ({column} {op} {param})"),
+            SimilarityMetric::Dot => format!("-({column} {op} {param})"),
+            SimilarityMetric::Euclidean => format!("1 / (1 + ({column} {op} {param}))"),
+        }
+    }
+}
+
+/// A stored vector whose length differs from the query vector's. Scoring it would
+/// zip the two slices and silently ignore the tail of the longer one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DimensionMismatch {
+    pub expected: usize,
+    pub found: usize,
+}
+
+impl std::fmt::Display for DimensionMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Embedding dimension mismatch: query has {} dims, stored vector has {} (re-embed with the current model)",
+            self.expected, self.found
+        )
+    }
+}
+
+impl std::error::Error for DimensionMismatch {}
+
+/// What retrieval does with a stored vector whose dimension differs from the query's
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DimensionMismatchMode {
+    /// Leave the row out of the results and log a warning
+    #[default]
+    Skip,
+    /// Fail the query with `DimensionMismatch`
+    Error,
+}
+
+impl DimensionMismatchMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "skip" => Some(DimensionMismatchMode::Skip),
+            "error" => Some(DimensionMismatchMode::Error),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DimensionMismatchMode::Skip => "skip",
+            DimensionMismatchMode::Error => "error",
+        }
+    }
+}
+
+/// Query vector scored with a configurable metric. Cosine keeps the precomputed
+/// query norm from `CosineQuery`.
+pub struct MetricQuery<'a> {
+    metric: SimilarityMetric,
+    cosine: CosineQuery<'a>,
+}
+
+impl<'a> MetricQuery<'a> {
+    pub fn new(vec: &'a [f32], metric: SimilarityMetric) -> Self {
+        Self { metric, cosine: CosineQuery::new(vec) }
+    }
+
+    /// Distance between the query and a stored vector (smaller = closer)
+    pub fn distance(&self, other: &[f32]) -> f32 {
+        match self.metric {
+            SimilarityMetric::Cosine => 1.0 - self.cosine.similarity(other),
+            metric => metric.distance(self.cosine.vec, other),
+        }
+    }
+
+    /// `distance`, after checking `other` has the query's dimension. Under
+    /// `DimensionMismatchMode::Skip` a mismatched vector yields `Ok(None)`.
+    pub fn checked_distance(&self, other: &[f32], mode: DimensionMismatchMode) -> Result<Option<f32>, DimensionMismatch> {
+        if other.len() == self.cosine.vec.len() {
+            return Ok(Some(self.distance(other)));
+        }
+        match mode {
+            DimensionMismatchMode::Skip => Ok(None),
+            DimensionMismatchMode::Error => Err(DimensionMismatch { expected: self.cosine.vec.len(), found: other.len() }),
+        }
+    }
+}
+
+/// Ascending order for distances (closest first) with NaN sorted last, so a
+/// degenerate stored vector ranks worst instead of panicking the sort
+pub fn distance_order(a: f32, b: f32) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (false, false) => a.total_cmp(&b),
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+    }
+}
+
+/// Descending order for scores (best first) with NaN sorted last
+pub fn score_order(a: f32, b: f32) -> Ordering {
+    distance_order(-a, -b)
+}
@@ -0,0 +1,45 @@
+//! Stop words dropped from queries before keyword search
+
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Stop words used when `STOP_WORDS_FILE` is unset
+pub const DEFAULT_STOP_WORDS: &[&str] = &[
+    // Common English stop words
+    "the", "and", "for", "with", "from", "this", "that", "what", "how",
+    "are", "was", "were", "been", "being", "have", "has", "had", "does",
+    "did", "will", "would", "could", "should", "may", "might", "must",
+    "can", "about", "into", "through", "during", "before", "after",
+    "above", "below", "between", "under", "again", "further", "then",
+    "once", "here", "there", "when", "where", "why", "all", "any",
+    "both", "each", "few", "more", "most", "other", "some", "such",
+    "only", "own", "same", "than", "too", "very", "just", "but",
+    // Conversational filler words
+    "hey", "hello", "hi", "please", "thanks", "thank", "you", "your",
+    "want", "need", "help", "tell", "show", "give", "get", "make",
+    "called", "named", "like", "know", "think", "see", "look",
+    // Question words
+    "who", "whom", "which", "whose",
+    // Common verbs that add little meaning
+    "doing", "done", "going", "gone", "come", "came",
+];
+
+/// The built-in list as a set
+pub fn default_stop_words() -> HashSet<String> {
+    DEFAULT_STOP_WORDS.iter().map(|w| w.to_string()).collect()
+}
+
+/// Parse a stop-word list: one word per line, lowercased. Blank lines and lines
+/// starting with `#` are skipped.
+pub fn parse_stop_words(text: &str) -> HashSet<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Read a stop-word file (`STOP_WORDS_FILE`) in the `parse_stop_words` format
+pub fn load_stop_words(path: &Path) -> std::io::Result<HashSet<String>> {
+    Ok(parse_stop_words(&std::fs::read_to_string(path)?))
+}
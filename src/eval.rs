@@ -0,0 +1,100 @@
+//! Retrieval quality metrics for a labeled query set (used by `bin/eval`)
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::etl::parser::ParsedTriplet;
+
+/// Labeled evaluation set: optional triplets to ingest first, then the queries
+#[derive(Debug, Clone, Deserialize)]
+pub struct EvalSet {
+    /// Ingested (upserted) before querying, so a fixture is self-contained
+    #[serde(default)]
+    pub triplets: Vec<ParsedTriplet>,
+    pub queries: Vec<EvalCase>,
+}
+
+/// One query and the triplet ids a correct retrieval should return
+#[derive(Debug, Clone, Deserialize)]
+pub struct EvalCase {
+    pub query: String,
+    pub expected: Vec<i64>,
+}
+
+/// What retrieval returned for one case
+#[derive(Debug, Clone)]
+pub struct QueryOutcome {
+    /// Triplet ids, closest first
+    pub retrieved: Vec<i64>,
+    /// The LSH buckets were empty and the results came from the fallback scan
+    pub degraded: bool,
+}
+
+/// Metrics for a single query at cutoff `k`
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryScore {
+    pub query: String,
+    pub recall: f64,
+    pub precision: f64,
+    pub reciprocal_rank: f64,
+    pub degraded: bool,
+}
+
+/// Metrics averaged over every query
+#[derive(Debug, Clone, Serialize)]
+pub struct EvalReport {
+    pub k: usize,
+    pub queries: usize,
+    pub recall_at_k: f64,
+    pub precision_at_k: f64,
+    pub mrr: f64,
+    /// Fraction of queries answered by the LSH full-scan fallback
+    pub fallback_rate: f64,
+    pub per_query: Vec<QueryScore>,
+}
+
+/// Score one query: recall and precision over the top `k`, and the reciprocal
+/// rank of the first expected id (0 when none is in the top `k`)
+pub fn score_query(case: &EvalCase, outcome: &QueryOutcome, k: usize) -> QueryScore {
+    let expected: HashSet<i64> = case.expected.iter().copied().collect();
+    let top_k = &outcome.retrieved[..k.min(outcome.retrieved.len())];
+    let hits = top_k.iter().filter(|id| expected.contains(id)).count();
+    let reciprocal_rank = top_k
+        .iter()
+        .position(|id| expected.contains(id))
+        .map(|pos| 1.0 / (pos + 1) as f64)
+        .unwrap_or(0.0);
+
+    QueryScore {
+        query: case.query.clone(),
+        recall: if expected.is_empty() { 0.0 } else { hits as f64 / expected.len() as f64 },
+        precision: if k == 0 { 0.0 } else { hits as f64 / k as f64 },
+        reciprocal_rank,
+        degraded: outcome.degraded,
+    }
+}
+
+impl EvalReport {
+    /// Average per-query scores; `cases` and `outcomes` are paired by position
+    pub fn new(cases: &[EvalCase], outcomes: &[QueryOutcome], k: usize) -> Self {
+        let per_query: Vec<QueryScore> = cases
+            .iter()
+            .zip(outcomes)
+            .map(|(case, outcome)| score_query(case, outcome, k))
+            .collect();
+        let n = per_query.len();
+        let mean = |f: fn(&QueryScore) -> f64| {
+            if n == 0 { 0.0 } else { per_query.iter().map(f).sum::<f64>() / n as f64 }
+        };
+
+        EvalReport {
+            k,
+            queries: n,
+            recall_at_k: mean(|s| s.recall),
+            precision_at_k: mean(|s| s.precision),
+            mrr: mean(|s| s.reciprocal_rank),
+            fallback_rate: mean(|s| if s.degraded { 1.0 } else { 0.0 }),
+            per_query,
+        }
+    }
+}
@@ -1,17 +1,21 @@
 use rand::seq::SliceRandom;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use crate::db;
-use crate::{config::Config, etl::{embed, lsh::Lsh, parser::{ParsedTriplet, SessionGraph, KnowledgeGraphData}}};
-use std::collections::HashMap;
+use crate::benchmark::{IngestStage, StageTimings};
+use crate::{config::Config, telemetry, etl::{content_hash::{edge_content_hash, session_edge_id}, embed, lsh::LshTables, parser::{ParsedTriplet, SessionGraph, KnowledgeGraphData}, payload::parse_payload}};
+use futures::stream::{self, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
 
 /// Quickly seed 100 sample nodes (label Person) and 200 random edges between them.
-pub async fn seed_sample_graph() -> Result<()> {
+pub async fn seed_sample_graph(cfg: &Config) -> Result<()> {
     let client = db::connect::get_client().await?;
     let mut ids = Vec::new();
     // insert nodes
     for i in 0..100 {
         let node_props = serde_json::json!({"name": format!("Person{i}")});
-        let id = db::graph::upsert_node(&client, "Person", &format!("person_{i}"), &node_props).await?;
+        let id = db::graph::upsert_node(&client, &cfg.graph_name, "Person", &format!("person_{i}"), &node_props).await?;
         ids.push(id);
     }
     // random edges
@@ -21,43 +25,105 @@ pub async fn seed_sample_graph() -> Result<()> {
         let b = *ids.choose(&mut rng).unwrap();
         if a == b {continue;}
         let edge_props = serde_json::Value::Null;
-        db::graph::upsert_edge(&client, "KNOWS", a, b, &edge_props).await?;
+        db::graph::upsert_edge(&client, &cfg.graph_name, "KNOWS", a, b, &edge_props).await?;
     }
     Ok(())
 }
 
-pub async fn ingest_triplet(t: ParsedTriplet) -> Result<()> {
-    let cfg = Config::from_env();
+/// Upsert a triplet's nodes and edge and store its embedding. Returns the AGE id of the edge.
+pub async fn ingest_triplet(cfg: &Config, t: ParsedTriplet) -> Result<i64> {
     let client = db::connect::get_client().await?;
 
     // upsert subject and object nodes
-    let subject_id = db::graph::upsert_node(&client, &t.subject.label, &t.subject.pk, &t.subject.props).await?;
-    let object_id = db::graph::upsert_node(&client, &t.object.label, &t.object.pk, &t.object.props).await?;
+    let subject_id = db::graph::upsert_node(&client, &cfg.graph_name, &t.subject.label, &t.subject.pk, &t.subject.props).await?;
+    let object_id = db::graph::upsert_node(&client, &cfg.graph_name, &t.object.label, &t.object.pk, &t.object.props).await?;
 
     // upsert edge between them
-    db::graph::upsert_edge(&client, &t.relationship, subject_id, object_id, &t.edge_props).await?;
+    let graph_edge_id = db::graph::upsert_edge(&client, &cfg.graph_name, &t.relationship, subject_id, object_id, &t.edge_props).await?;
+    tracing::trace!(triplet_id = t.id, graph_edge_id, "Upserted triplet edge");
 
     // Compute embedding and store
     let text = format!("{} {} {}", t.subject.pk, t.relationship, t.object.pk);
-    let vec_f32 = embed::embed_text(&text).await?;
-    let lsh = Lsh::new(vec_f32.len(), cfg.lsh_buckets);
-    let bucket = lsh.hash(&vec_f32) as i32;
-    db::vector::upsert_embedding(&client, t.id, &vec_f32, bucket).await?;
+    let (vec_f32, provider) = embed::embed_text_with_provider(cfg, &text).await?;
+    let buckets = LshTables::new(vec_f32.len(), cfg.lsh_buckets, cfg.lsh_tables).hash_all(&vec_f32);
+    let model = provider.model_name(&cfg.embed_model_name);
+    db::vector::upsert_embedding(&client, t.id, &vec_f32, &buckets, model).await?;
 
-    Ok(())
+    telemetry::record_ingested("nodes", 2);
+    telemetry::record_ingested("edges", 1);
+    telemetry::record_ingested("embeddings", 1);
+    Ok(graph_edge_id)
 }
 
 // ============================================================================
 // New Functions for ok.json Batch Ingestion
 // ============================================================================
 
+/// What to do when generating an edge embedding fails during session ingest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbedErrorMode {
+    /// Abort the whole session (previous behavior)
+    #[default]
+    Abort,
+    /// Record the failure in the session errors and continue without an embedding
+    Skip,
+    /// Store the placeholder embedding and continue
+    Placeholder,
+}
+
+/// What to do when a session edge references a node that isn't in the session's `nodes`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MissingNodeMode {
+    /// Abort the session with "Source node not found" / "Target node not found"
+    #[default]
+    Strict,
+    /// Use the node with that pk already in the graph (e.g. from an earlier session),
+    /// or create one labeled `MISSING_NODE_LABEL`
+    Lenient,
+}
+
+impl MissingNodeMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "strict" => Some(MissingNodeMode::Strict),
+            "lenient" => Some(MissingNodeMode::Lenient),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MissingNodeMode::Strict => "strict",
+            MissingNodeMode::Lenient => "lenient",
+        }
+    }
+}
+
+/// Label of the nodes `MissingNodeMode::Lenient` creates for pks not found anywhere
+pub const MISSING_NODE_LABEL: &str = "Entity";
+
+/// Options controlling a session ingest
+#[derive(Debug, Clone, Default)]
+pub struct SessionIngestOptions {
+    pub on_embed_error: EmbedErrorMode,
+    pub on_missing_node: MissingNodeMode,
+    /// Record how long each pipeline stage takes (see `bin/benchmark`)
+    pub timings: Option<StageTimings>,
+}
+
 #[derive(Debug, Clone)]
 pub struct SessionIngestStats {
     pub session_id: String,
     pub nodes_created: usize,
     pub edges_created: usize,
     pub embeddings_created: usize,
+    pub edges_skipped: usize,
+    /// Edges whose content hash matched the stored one (not re-embedded)
+    pub skipped_unchanged: usize,
     pub duration_ms: u64,
+    pub errors: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -66,141 +132,337 @@ pub struct BatchIngestStats {
     pub total_nodes: usize,
     pub total_edges: usize,
     pub total_embeddings: usize,
+    pub total_edges_skipped: usize,
+    pub skipped_unchanged: usize,
     pub duration_ms: u64,
     pub errors: Vec<String>,
 }
 
+/// A new or changed session edge that needs an embedding
+struct PendingEmbedding {
+    idx: usize,
+    text: String,
+}
+
+/// Embed each of `texts` with at most `concurrency` requests in flight. Results are
+/// yielded as they complete, tagged with their index in `texts`. A failing embedding
+/// server yields placeholders, as `embed::embed_text_with_provider` does.
+pub fn embed_concurrently<'a>(
+    cfg: &'a Config,
+    texts: &'a [String],
+    concurrency: usize,
+) -> impl Stream<Item = (usize, Result<(Vec<f32>, embed::EmbeddingProvider)>)> + 'a {
+    stream::iter(texts.iter().enumerate())
+        .map(move |(i, text)| async move { (i, embed::embed_text_with_provider(cfg, text).await) })
+        .buffer_unordered(concurrency.max(1))
+}
+
+/// Like `embed_concurrently`, but a failing embedding server yields the error
+/// (`embed::embed_text_strict`), so session ingest can apply `EmbedErrorMode`.
+/// Each request's duration is recorded as `IngestStage::Embedding`.
+pub fn embed_concurrently_timed<'a>(
+    cfg: &'a Config,
+    texts: &'a [String],
+    concurrency: usize,
+    timings: Option<&'a StageTimings>,
+) -> impl Stream<Item = (usize, Result<(Vec<f32>, embed::EmbeddingProvider)>)> + 'a {
+    stream::iter(texts.iter().enumerate())
+        .map(move |(i, text)| async move {
+            tracing::debug!(edge_text = %text, "Generating edge embedding");
+            let started = std::time::Instant::now();
+            let result = embed::embed_text_strict(cfg, text).await;
+            if let Some(timings) = timings {
+                timings.record(IngestStage::Embedding, started.elapsed());
+            }
+            (i, result)
+        })
+        .buffer_unordered(concurrency.max(1))
+}
+
 /// Ingest a single session graph
 pub async fn ingest_session_graph(
+    cfg: &Config,
     session_id: &str,
     graph: &SessionGraph,
+    opts: &SessionIngestOptions,
 ) -> Result<SessionIngestStats> {
     let start = std::time::Instant::now();
-    let cfg = Config::from_env();
     let client = db::connect::get_client().await?;
     
     let mut node_map: HashMap<String, i64> = HashMap::new();
     let mut nodes_created = 0;
     let mut edges_created = 0;
     let mut embeddings_created = 0;
+    let mut edges_skipped = 0;
+    let mut skipped_unchanged = 0;
+    let mut errors = Vec::new();
+    let timings = opts.timings.as_ref();
+    let record = |stage: IngestStage, started: std::time::Instant| {
+        if let Some(timings) = timings {
+            timings.record(stage, started.elapsed());
+        }
+    };
     
-    // Step 1: Create all nodes
-    for node in &graph.nodes {
-        let parsed_node = node.to_parsed_node();
-        let node_id = db::graph::upsert_node(
-            &client,
-            &parsed_node.label,
-            &parsed_node.pk,
-            &parsed_node.props,
-        ).await?;
-        node_map.insert(node.id.clone(), node_id);
-        nodes_created += 1;
+    // Content hashes from a previous ingest of this session
+    let existing_hashes = db::vector::get_session_edge_hashes(&client, session_id).await?;
+    
+    // Without lenient lookup an edge may only reference the session's own nodes
+    if opts.on_missing_node == MissingNodeMode::Strict {
+        let declared: HashSet<&str> = graph.nodes.iter().map(|n| n.id.as_str()).collect();
+        for edge in &graph.edges {
+            if !declared.contains(edge.source.as_str()) {
+                anyhow::bail!("Source node not found: {}", edge.source);
+            }
+            if !declared.contains(edge.target.as_str()) {
+                anyhow::bail!("Target node not found: {}", edge.target);
+            }
+        }
     }
     
-    // Step 2: Create all edges with evidence tracking
-    for (idx, edge) in graph.edges.iter().enumerate() {
-        let source_id = node_map.get(&edge.source)
-            .ok_or_else(|| anyhow::anyhow!("Source node not found: {}", edge.source))?;
-        let target_id = node_map.get(&edge.target)
-            .ok_or_else(|| anyhow::anyhow!("Target node not found: {}", edge.target))?;
-        
-        let edge_props = edge.to_edge_props();
-        db::graph::upsert_edge(&client, &edge.relation, *source_id, *target_id, &edge_props).await?;
-        edges_created += 1;
+    // Step 1: Embed the new and changed edges, up to `embed_concurrency` requests at a time,
+    // before anything is written: an embedding failure under `Abort` leaves the session
+    // as it was. Results arrive out of order; each carries its edge.
+    let pending: Vec<PendingEmbedding> = graph
+        .edges
+        .iter()
+        .enumerate()
+        .filter(|(_, edge)| {
+            let edge_id = session_edge_id(session_id, &edge.source, &edge.relation, &edge.target);
+            existing_hashes.get(&edge_id) != Some(&edge_content_hash(&edge.source, &edge.relation, &edge.target))
+        })
+        .map(|(idx, edge)| PendingEmbedding { idx, text: format!("{} {} {}", edge.source, edge.relation, edge.target) })
+        .collect();
+    let texts: Vec<String> = pending.iter().map(|p| p.text.clone()).collect();
+    let mut results = embed_concurrently_timed(cfg, &texts, cfg.embed_concurrency, timings);
+    let mut embedded: HashMap<usize, (Vec<f32>, embed::EmbeddingProvider)> = HashMap::with_capacity(pending.len());
+    let mut failures: Vec<(usize, String)> = Vec::new();
+    while let Some((i, result)) = results.next().await {
+        let PendingEmbedding { idx, text: edge_text } = &pending[i];
+        match result {
+            Ok(v) => {
+                embedded.insert(*idx, v);
+            }
+            Err(e) => {
+                tracing::warn!(session_id, edge = idx + 1, error = %e, "❌ Failed to generate embedding");
+                match opts.on_embed_error {
+                    EmbedErrorMode::Abort => return Err(e),
+                    EmbedErrorMode::Skip => {
+                        failures.push((*idx, format!("Embedding for edge {} ({}): {}", idx + 1, edge_text, e)));
+                        edges_skipped += 1;
+                    }
+                    EmbedErrorMode::Placeholder => {
+                        failures.push((*idx, format!("Placeholder embedding used for edge {} ({}): {}", idx + 1, edge_text, e)));
+                        embedded.insert(*idx, (embed::placeholder_for(cfg, edge_text), embed::EmbeddingProvider::Placeholder));
+                    }
+                }
+            }
+        }
+    }
+    // Report failures in edge order regardless of completion order
+    failures.sort_by_key(|(idx, _)| *idx);
+    errors.extend(failures.into_iter().map(|(_, message)| message));
+    
+    // Steps 2-4 run in one transaction on this session's connection, so a session that
+    // fails partway leaves nothing behind. The graph's write lock is held until commit,
+    // so sessions write one at a time while their embedding (step 1) overlaps.
+    client.batch_execute("BEGIN").await?;
+    let written: Result<()> = async {
+        db::graph::lock_graph_for_transaction(&client, &cfg.graph_name).await?;
+
+        // Step 2: Create all nodes in one batch
+        let started = std::time::Instant::now();
+        let parsed_nodes: Vec<_> = graph.nodes.iter().map(|n| n.to_parsed_node()).collect();
+        let batch: Vec<(&str, &str, &serde_json::Value)> = parsed_nodes
+            .iter()
+            .map(|n| (n.label.as_str(), n.pk.as_str(), &n.props))
+            .collect();
+        node_map.extend(db::graph::upsert_nodes_batch(&client, &cfg.graph_name, &batch).await?);
+        nodes_created += parsed_nodes.len();
+        record(IngestStage::NodeUpsert, started);
+    
+        // Edges may reference nodes declared in an earlier session
+        if opts.on_missing_node == MissingNodeMode::Lenient {
+            for pk in graph.edges.iter().flat_map(|e| [&e.source, &e.target]) {
+                if node_map.contains_key(pk) {
+                    continue;
+                }
+                let id = match db::graph::get_node_by_pk(&client, &cfg.graph_name, pk).await? {
+                    Some(node) => node.id,
+                    None => {
+                        tracing::debug!(session_id, pk = %pk, label = MISSING_NODE_LABEL, "Creating node referenced by an edge");
+                        nodes_created += 1;
+                        db::graph::upsert_node(&client, &cfg.graph_name, MISSING_NODE_LABEL, pk, &serde_json::Value::Null).await?
+                    }
+                };
+                node_map.insert(pk.clone(), id);
+            }
+        }
+    
+        // Step 3: Create the edges with evidence tracking and store their embeddings
+        for (idx, edge) in graph.edges.iter().enumerate() {
+            let source_id = node_map.get(&edge.source)
+                .ok_or_else(|| anyhow::anyhow!("Source node not found: {}", edge.source))?;
+            let target_id = node_map.get(&edge.target)
+                .ok_or_else(|| anyhow::anyhow!("Target node not found: {}", edge.target))?;
         
-        // Generate unique edge ID for this session
-        // Use a hash of session_id + idx to create a unique i64
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
+            // Stable id from the edge's content, independent of its position in the session
+            let edge_id = session_edge_id(session_id, &edge.source, &edge.relation, &edge.target);
         
-        let mut hasher = DefaultHasher::new();
-        session_id.hash(&mut hasher);
-        idx.hash(&mut hasher);
-        let edge_id = hasher.finish() as i64;
+            // Unchanged edge: keep the stored edge and embedding, only refresh evidence
+            let hash = edge_content_hash(&edge.source, &edge.relation, &edge.target);
+            if existing_hashes.get(&edge_id) == Some(&hash) {
+                db::vector::store_edge_evidence(&client, edge_id, session_id, &edge.evidence_message_ids).await?;
+                skipped_unchanged += 1;
+                continue;
+            }
         
-        // Store evidence
-        db::vector::store_edge_evidence(&client, edge_id, session_id, &edge.evidence_message_ids).await?;
+            // Skipped after a failed embedding: nothing is written for the edge
+            let Some((vec_f32, provider)) = embedded.remove(&idx) else {
+                continue;
+            };
         
-        // Generate embedding for the edge
-        let edge_text = format!("{} {} {}", edge.source, edge.relation, edge.target);
-        eprintln!("   Generating embedding for edge {}/{}: {}", idx + 1, graph.edges.len(), edge_text);
+            let started = std::time::Instant::now();
+            let edge_props = edge.to_edge_props();
+            let graph_edge_id = db::graph::upsert_edge(&client, &cfg.graph_name, &edge.relation, *source_id, *target_id, &edge_props).await?;
+            tracing::trace!(session_id, edge_id, graph_edge_id, "Upserted session edge");
+            edges_created += 1;
         
-        let vec_f32 = match embed::embed_text(&edge_text).await {
-            Ok(v) => v,
-            Err(e) => {
-                eprintln!("   ❌ Failed to generate embedding: {}", e);
-                return Err(e);
-            }
-        };
+            // Store evidence
+            db::vector::store_edge_evidence(&client, edge_id, session_id, &edge.evidence_message_ids).await?;
+            record(IngestStage::EdgeUpsert, started);
         
-        let lsh = Lsh::new(vec_f32.len(), cfg.lsh_buckets);
-        let bucket = lsh.hash(&vec_f32) as i32;
+            let started = std::time::Instant::now();
+            let buckets = LshTables::new(vec_f32.len(), cfg.lsh_buckets, cfg.lsh_tables).hash_all(&vec_f32);
+            record(IngestStage::LshHash, started);
         
-        match db::vector::upsert_embedding_with_session(
-            &client,
-            edge_id,
-            &vec_f32,
-            bucket,
-            session_id,
-            &edge_text,
-        ).await {
-            Ok(_) => {
-                eprintln!("   ✅ Stored embedding for edge {}", idx + 1);
-                embeddings_created += 1;
+            let started = std::time::Instant::now();
+            let edge_text = format!("{} {} {}", edge.source, edge.relation, edge.target);
+            match db::vector::upsert_embedding_with_session(
+                &client,
+                edge_id,
+                &vec_f32,
+                &buckets,
+                session_id,
+                &edge_text,
+                &hash,
+                provider.model_name(&cfg.embed_model_name),
+            ).await {
+                Ok(_) => {
+                    record(IngestStage::VectorInsert, started);
+                    tracing::trace!(session_id, edge = idx + 1, "Stored edge embedding");
+                    embeddings_created += 1;
+                }
+                Err(e) => {
+                    tracing::error!(session_id, edge = idx + 1, error = %e, "❌ Failed to store embedding");
+                    return Err(e);
+                }
             }
-            Err(e) => {
-                eprintln!("   ❌ Failed to store embedding: {}", e);
-                return Err(e);
+        }
+    
+        // Step 4: Update session metadata
+        client.execute(
+            "INSERT INTO ag_catalog.sessions(session_id, node_count, edge_count) VALUES($1, $2, $3)
+             ON CONFLICT (session_id) DO UPDATE SET 
+                node_count = EXCLUDED.node_count,
+                edge_count = EXCLUDED.edge_count,
+                ingested_at = NOW()",
+            &[&session_id, &(nodes_created as i32), &((edges_created + skipped_unchanged) as i32)],
+        ).await?;
+    
+        Ok(())
+    }
+    .await;
+    match written {
+        Ok(()) => client.batch_execute("COMMIT").await?,
+        Err(e) => {
+            if let Err(rollback) = client.batch_execute("ROLLBACK").await {
+                tracing::warn!(session_id, error = %rollback, "Could not roll back the failed session");
             }
+            return Err(e);
         }
     }
     
-    // Step 3: Update session metadata
-    client.execute(
-        "INSERT INTO ag_catalog.sessions(session_id, node_count, edge_count) VALUES($1, $2, $3)
-         ON CONFLICT (session_id) DO UPDATE SET 
-            node_count = EXCLUDED.node_count,
-            edge_count = EXCLUDED.edge_count,
-            ingested_at = NOW()",
-        &[&session_id, &(nodes_created as i32), &(edges_created as i32)],
-    ).await?;
-    
     let duration_ms = start.elapsed().as_millis() as u64;
     
+    telemetry::record_ingested("nodes", nodes_created);
+    telemetry::record_ingested("edges", edges_created);
+    telemetry::record_ingested("embeddings", embeddings_created);
+    
     Ok(SessionIngestStats {
         session_id: session_id.to_string(),
         nodes_created,
         edges_created,
         embeddings_created,
+        edges_skipped,
+        skipped_unchanged,
         duration_ms,
+        errors,
     })
 }
 
 /// Ingest entire knowledge graph data (ok.json format)
 pub async fn ingest_knowledge_graph_data(
+    cfg: &Config,
+    data: &KnowledgeGraphData,
+    opts: &SessionIngestOptions,
+) -> Result<BatchIngestStats> {
+    ingest_knowledge_graph_data_with_progress(cfg, data, opts, |_| {}).await
+}
+
+/// Same as `ingest_knowledge_graph_data`, calling `on_progress` with the number
+/// of sessions finished after each one
+pub async fn ingest_knowledge_graph_data_with_progress(
+    cfg: &Config,
     data: &KnowledgeGraphData,
+    opts: &SessionIngestOptions,
+    on_progress: impl Fn(usize) + Send + Sync,
 ) -> Result<BatchIngestStats> {
     let start = std::time::Instant::now();
     let mut total_nodes = 0;
     let mut total_edges = 0;
     let mut total_embeddings = 0;
+    let mut total_edges_skipped = 0;
+    let mut skipped_unchanged = 0;
+    let mut sessions_done = 0;
     let mut errors = Vec::new();
     
-    for (session_id, graph) in data {
-        match ingest_session_graph(session_id, graph).await {
+    // Each session ingests on its own new connection, writing in one transaction that
+    // holds the graph's advisory lock, so embedding is what overlaps.
+    // Results are taken in input order, so totals and errors read the same whatever
+    // SESSION_CONCURRENCY is
+    let sessions: Vec<_> = data
+        .iter()
+        .map(|(session_id, graph)| async move {
+            (session_id, ingest_session_graph(cfg, session_id, graph, opts).await)
+        })
+        .collect();
+    let mut results = stream::iter(sessions).buffered(cfg.session_concurrency.max(1));
+    
+    while let Some((session_id, result)) = results.next().await {
+        match result {
             Ok(stats) => {
                 total_nodes += stats.nodes_created;
                 total_edges += stats.edges_created;
                 total_embeddings += stats.embeddings_created;
-                println!("✓ Ingested session {}: {} nodes, {} edges", 
-                    session_id, stats.nodes_created, stats.edges_created);
+                total_edges_skipped += stats.edges_skipped;
+                skipped_unchanged += stats.skipped_unchanged;
+                errors.extend(stats.errors.into_iter().map(|e| format!("Session {}: {}", session_id, e)));
+                tracing::info!(
+                    session_id = %session_id,
+                    nodes = stats.nodes_created,
+                    edges = stats.edges_created,
+                    duration_ms = stats.duration_ms,
+                    "✓ Ingested session"
+                );
             }
             Err(e) => {
                 let error_msg = format!("Failed to ingest session {}: {:?}", session_id, e);
-                eprintln!("✗ {}", error_msg);
+                tracing::error!("✗ {}", error_msg);
                 errors.push(error_msg);
             }
         }
+        sessions_done += 1;
+        on_progress(sessions_done);
     }
     
     let duration_ms = start.elapsed().as_millis() as u64;
@@ -210,14 +472,127 @@ pub async fn ingest_knowledge_graph_data(
         total_nodes,
         total_edges,
         total_embeddings,
+        total_edges_skipped,
+        skipped_unchanged,
         duration_ms,
         errors,
     })
 }
 
 /// Load and ingest from a JSON file
-pub async fn ingest_from_file(file_path: &str) -> Result<BatchIngestStats> {
+pub async fn ingest_from_file(cfg: &Config, file_path: &str) -> Result<BatchIngestStats> {
     let content = tokio::fs::read_to_string(file_path).await?;
-    let data: KnowledgeGraphData = serde_json::from_str(&content)?;
-    ingest_knowledge_graph_data(&data).await
+    // Errors name the record, field and byte offset that failed to parse
+    let data: KnowledgeGraphData = parse_payload(&content).with_context(|| format!("parsing {}", file_path))?;
+    let opts = SessionIngestOptions {
+        on_missing_node: cfg.missing_node_mode,
+        ..Default::default()
+    };
+    ingest_knowledge_graph_data(cfg, &data, &opts).await
+}
+
+/// Which stored edge embeddings `reembed_missing` regenerates. A session limits it to
+/// session edges (`embeddings`), a conversation to KG edges (`kg_edge_embeddings`);
+/// with neither, both tables are scanned in full.
+#[derive(Debug, Clone, Default)]
+pub struct ReembedScope {
+    pub session_id: Option<String>,
+    pub conversation_id: Option<Uuid>,
+}
+
+/// Outcome of re-embedding one table
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ReembedCounts {
+    /// Edges without a real embedding
+    pub found: usize,
+    /// Edges that now have one
+    pub reembedded: usize,
+    /// Edges the embedding server still couldn't embed, left unchanged
+    pub still_placeholder: usize,
+    /// Edges whose embedding request or update failed
+    pub failed: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReembedStats {
+    pub embeddings: ReembedCounts,
+    pub kg_edge_embeddings: ReembedCounts,
+    pub duration_ms: u64,
+    pub errors: Vec<String>,
+}
+
+/// Regenerate edge embeddings that are placeholders or missing, e.g. after an ingest
+/// ran while the embedding server was down, updating the stored rows in place
+pub async fn reembed_missing(cfg: &Config, scope: &ReembedScope) -> Result<ReembedStats> {
+    let start = std::time::Instant::now();
+    let client = db::connect::get_client().await?;
+    let mut stats = ReembedStats::default();
+
+    if scope.session_id.is_some() || scope.conversation_id.is_none() {
+        let pending = db::vector::get_placeholder_embeddings(&client, scope.session_id.as_deref()).await?;
+        let texts: Vec<String> = pending.iter().map(|(_, text)| text.clone()).collect();
+        stats.embeddings.found = pending.len();
+        let mut embeddings = embed_concurrently(cfg, &texts, cfg.embed_concurrency);
+        while let Some((i, result)) = embeddings.next().await {
+            let (triplet_id, text) = &pending[i];
+            let stored = match result {
+                Ok((_, embed::EmbeddingProvider::Placeholder)) => {
+                    stats.embeddings.still_placeholder += 1;
+                    continue;
+                }
+                Ok((vec_f32, provider)) => {
+                    let buckets = LshTables::new(vec_f32.len(), cfg.lsh_buckets, cfg.lsh_tables).hash_all(&vec_f32);
+                    db::vector::update_embedding_vector(&client, *triplet_id, &vec_f32, &buckets, provider.model_name(&cfg.embed_model_name)).await
+                }
+                Err(e) => Err(e),
+            };
+            match stored {
+                Ok(()) => stats.embeddings.reembedded += 1,
+                Err(e) => {
+                    stats.embeddings.failed += 1;
+                    stats.errors.push(format!("Edge {} ({}): {}", triplet_id, text, e));
+                }
+            }
+        }
+    }
+
+    if scope.conversation_id.is_some() || scope.session_id.is_none() {
+        let pending = db::kg_ops::get_edges_needing_embedding(&client, scope.conversation_id).await?;
+        let texts: Vec<String> = pending.iter().map(|(_, text)| text.clone()).collect();
+        stats.kg_edge_embeddings.found = pending.len();
+        let mut embeddings = embed_concurrently(cfg, &texts, cfg.embed_concurrency);
+        while let Some((i, result)) = embeddings.next().await {
+            let (edge_id, text) = &pending[i];
+            let stored = match result {
+                Ok((_, embed::EmbeddingProvider::Placeholder)) => {
+                    stats.kg_edge_embeddings.still_placeholder += 1;
+                    continue;
+                }
+                Ok((vec_f32, provider)) => {
+                    db::kg_ops::insert_kg_edge_embedding(&client, *edge_id, &vec_f32, text, provider.model_name(&cfg.embed_model_name))
+                        .await
+                        .map_err(anyhow::Error::from)
+                }
+                Err(e) => Err(e),
+            };
+            match stored {
+                Ok(()) => stats.kg_edge_embeddings.reembedded += 1,
+                Err(e) => {
+                    stats.kg_edge_embeddings.failed += 1;
+                    stats.errors.push(format!("KG edge {} ({}): {}", edge_id, text, e));
+                }
+            }
+        }
+    }
+
+    stats.duration_ms = start.elapsed().as_millis() as u64;
+    telemetry::record_ingested("embeddings", stats.embeddings.reembedded + stats.kg_edge_embeddings.reembedded);
+    if stats.embeddings.still_placeholder + stats.kg_edge_embeddings.still_placeholder > 0 {
+        tracing::warn!(
+            session_edges = stats.embeddings.still_placeholder,
+            kg_edges = stats.kg_edge_embeddings.still_placeholder,
+            "Embedding server unavailable; some edges still have placeholder embeddings"
+        );
+    }
+    Ok(stats)
 }
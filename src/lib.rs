@@ -4,8 +4,11 @@ pub mod config;
 pub mod db;
 pub mod etl;
 
+pub mod benchmark;
+pub mod eval;
 pub mod ingest;
 pub mod retrieve;
+pub mod telemetry;
 
 pub mod api;
 
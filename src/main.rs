@@ -1,6 +1,6 @@
 use anyhow::Result;
 use rust_ingester::etl::parser::ParsedTriplet;
-use rust_ingester::{ingest::ingest_triplet, retrieve::query_similar};
+use rust_ingester::{config::Config, ingest::ingest_triplet, retrieve::query_similar};
 
 /// Main entry point for the Rust Ingestor CLI.
 ///
@@ -15,6 +15,8 @@ use rust_ingester::{ingest::ingest_triplet, retrieve::query_similar};
 ///
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cfg = Config::global();
+
     // Example triplet ----------------------------------------------
     let t = ParsedTriplet {
         id: 1,
@@ -25,10 +27,10 @@ async fn main() -> Result<()> {
     };
 
     // Ingest it
-    ingest_triplet(t).await?;
+    ingest_triplet(cfg, t).await?;
 
     // Query similar
-    let results = query_similar("alice email", 5).await?;
+    let results = query_similar(cfg, "alice email", 5).await?;
     for (triplet_id, dist) in results {
         println!("id {triplet_id}  -> distance {dist}");
     }
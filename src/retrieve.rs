@@ -1,80 +1,100 @@
 use anyhow::Result;
 
-use crate::{config::Config, db, etl::{embed, lsh::Lsh}};
+use crate::{config::Config, db, telemetry, etl::{embed, lsh::LshTables}};
+use crate::db::vector::EmbeddingCandidate;
+use crate::etl::similarity::{distance_order, DimensionMismatch, DimensionMismatchMode, MetricQuery, SimilarityMetric};
 
-/// Simple cosine similarity calculation for demonstration
-fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
-    let dot_product: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
-    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
-    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
-    if norm_a == 0.0 || norm_b == 0.0 {
-        0.0
-    } else {
-        dot_product / (norm_a * norm_b)
-    }
+/// Nearest stored triplets to a query, with whether the lookup was degraded
+#[derive(Debug, Clone)]
+pub struct SimilarResults {
+    /// `(triplet_id, distance)`, closest first
+    pub results: Vec<(i64, f32)>,
+    /// The LSH buckets were empty, so `results` come from a capped full-table
+    /// scan (`FALLBACK_SCAN_LIMIT` rows) and may miss closer matches
+    pub degraded: bool,
+}
+
+pub async fn query_similar(cfg: &Config, text: &str, k: i64) -> Result<Vec<(i64, f32)>> {
+    query_similar_with_status(cfg, text, k).await.map(|r| r.results)
 }
 
-pub async fn query_similar(text: &str, k: i64) -> Result<Vec<(i64, f32)>> {
-    let cfg = Config::from_env();
+/// Same as `query_similar`, also reporting whether the LSH lookup fell back to a scan
+pub async fn query_similar_with_status(cfg: &Config, text: &str, k: i64) -> Result<SimilarResults> {
+    let _timer = telemetry::time_query("retrieve");
     let client = db::connect::get_client().await?;
 
-    let query_vec = embed::embed_text(text).await?;
-    let lsh = Lsh::new(query_vec.len(), cfg.lsh_buckets);
-    let bucket = lsh.hash(&query_vec) as i32;
+    let query_vec = embed::embed_text(cfg, text).await?;
+    let lsh = LshTables::new(query_vec.len(), cfg.lsh_buckets, cfg.lsh_tables);
+    let buckets = lsh.hash_all(&query_vec);
 
-    eprintln!("🔍 Query similarity search:");
-    eprintln!("   Query text: {}", text);
-    eprintln!("   Query bucket: {}", bucket);
-    eprintln!("   LSH buckets config: {}", cfg.lsh_buckets);
+    tracing::debug!(
+        query = text,
+        buckets = ?buckets,
+        lsh_buckets = cfg.lsh_buckets,
+        lsh_tables = cfg.lsh_tables,
+        metric = cfg.similarity_metric.as_str(),
+        "🔍 Query similarity search"
+    );
 
-    // Get all vectors in the same LSH bucket
-    let sql = "SELECT triplet_id, vec FROM embeddings WHERE lsh_bucket = $1";
-    let rows = client.query(sql, &[&bucket]).await?;
+    // Rank the vectors sharing a bucket with the query in any LSH table, in SQL
+    let db::vector::NearestEmbeddings { neighbors, degraded } = db::vector::nearest_lsh_embeddings(
+        &client,
+        &query_vec,
+        &buckets,
+        cfg.similarity_metric,
+        cfg.on_dimension_mismatch,
+        None,
+        cfg.fallback_scan_limit,
+        k.max(0) as usize,
+    ).await?;
+    if degraded {
+        telemetry::record_lsh_fallback("retrieve");
+    }
     
-    eprintln!("   Found {} embeddings in bucket {}", rows.len(), bucket);
+    let results: Vec<(i64, f32)> = neighbors.into_iter().map(|n| (n.triplet_id, n.distance)).collect();
     
-    // If no results in the specific bucket, fall back to searching all embeddings
-    let rows = if rows.is_empty() {
-        eprintln!("   ⚠️  Bucket {} is empty, searching ALL embeddings as fallback", bucket);
-        let sql_all = "SELECT triplet_id, vec, lsh_bucket FROM embeddings LIMIT 1000";
-        let all_rows = client.query(sql_all, &[]).await?;
-        eprintln!("   Found {} total embeddings in database", all_rows.len());
-        
-        // Show bucket distribution
-        let bucket_count_sql = "SELECT lsh_bucket, COUNT(*) FROM embeddings GROUP BY lsh_bucket ORDER BY lsh_bucket";
-        let bucket_rows = client.query(bucket_count_sql, &[]).await?;
-        eprintln!("   Bucket distribution:");
-        for br in bucket_rows {
-            let b: i32 = br.get(0);
-            let count: i64 = br.get(1);
-            eprintln!("      Bucket {}: {} embeddings", b, count);
-        }
-        
-        all_rows
-    } else {
-        rows
-    };
+    tracing::debug!(
+        result_count = results.len(),
+        top_triplet_id = results.first().map(|r| r.0),
+        top_distance = results.first().map(|r| r.1),
+        degraded,
+        "Returning similarity results"
+    );
     
-    let mut results = Vec::new();
-    for row in rows {
-        let triplet_id: i64 = row.get(0);
-        let vec_json: String = row.get(1);
-        let stored_vec: Vec<f32> = serde_json::from_str(&vec_json)?;
-        
-        // Calculate similarity (convert to distance: 1 - similarity)
-        let similarity = cosine_similarity(&query_vec, &stored_vec);
-        let distance = 1.0 - similarity;
-        results.push((triplet_id, distance));
+    telemetry::record_query_results("retrieve", results.len());
+    Ok(SimilarResults { results, degraded })
+}
+
+/// `(triplet_id, distance)` for each candidate under `metric` (smaller = closer), unsorted.
+/// Candidates whose dimension differs from the query's are skipped with a warning,
+/// or fail the whole query, depending on `mode`.
+pub fn score_candidates(
+    query_vec: &[f32],
+    candidates: &[EmbeddingCandidate],
+    metric: SimilarityMetric,
+    mode: DimensionMismatchMode,
+) -> Result<Vec<(i64, f32)>, DimensionMismatch> {
+    let query = MetricQuery::new(query_vec, metric);
+    let mut results = Vec::with_capacity(candidates.len());
+    let mut skipped = 0usize;
+    for candidate in candidates {
+        match query.checked_distance(&candidate.vec, mode)? {
+            Some(distance) => results.push((candidate.triplet_id, distance)),
+            None => skipped += 1,
+        }
     }
-    
-    // Sort by distance (ascending) and take top k
-    results.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
-    results.truncate(k as usize);
-    
-    eprintln!("   Returning {} results", results.len());
-    if !results.is_empty() {
-        eprintln!("   Top result: triplet_id={}, distance={:.4}", results[0].0, results[0].1);
+    if skipped > 0 {
+        tracing::warn!(
+            skipped,
+            query_dim = query_vec.len(),
+            "Skipped stored embeddings with a different dimension than the query"
+        );
     }
-    
     Ok(results)
 }
+
+/// Sort `(triplet_id, distance)` pairs closest first, NaN distances last and
+/// ties by triplet id, so the order is deterministic
+pub fn sort_by_distance(results: &mut [(i64, f32)]) {
+    results.sort_by(|a, b| distance_order(a.1, b.1).then(a.0.cmp(&b.0)));
+}
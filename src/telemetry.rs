@@ -0,0 +1,99 @@
+//! Prometheus metrics for ingest and query throughput/latency, served on `GET /metrics`
+
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+
+/// Rows ingested, labelled by `kind` (nodes, edges, messages, embeddings)
+pub const INGESTED_TOTAL: &str = "rust_ingester_ingested_total";
+/// Embedding server calls, labelled by `outcome` (success, error)
+pub const EMBED_REQUESTS_TOTAL: &str = "rust_ingester_embed_requests_total";
+/// Embedding server call latency
+pub const EMBED_DURATION_SECONDS: &str = "rust_ingester_embed_request_duration_seconds";
+/// Embeddings that fell back to the placeholder vector
+pub const EMBED_PLACEHOLDER_TOTAL: &str = "rust_ingester_embed_placeholder_total";
+/// Query latency, labelled by `endpoint`
+pub const QUERY_DURATION_SECONDS: &str = "rust_ingester_query_duration_seconds";
+/// Results returned per query, labelled by `endpoint`
+pub const QUERY_RESULTS: &str = "rust_ingester_query_results";
+/// LSH lookups that found no candidates and scanned all embeddings, labelled by `endpoint`
+pub const LSH_FALLBACK_TOTAL: &str = "rust_ingester_lsh_fallback_total";
+
+/// `/query/llm-context` response cache lookups, labelled by `outcome` (hit, miss)
+pub const QUERY_CACHE_TOTAL: &str = "rust_ingester_query_cache_total";
+
+/// Latency buckets (seconds) for the `_seconds` histograms
+const LATENCY_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+/// Buckets for the per-query result count histogram
+const RESULT_COUNT_BUCKETS: &[f64] = &[0.0, 1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 1000.0];
+
+static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+/// Install the global Prometheus recorder (once per process) and return its handle.
+/// Metrics recorded before this is called are dropped.
+pub fn install() -> PrometheusHandle {
+    HANDLE
+        .get_or_init(|| {
+            PrometheusBuilder::new()
+                .set_buckets_for_metric(Matcher::Suffix("_seconds".to_string()), LATENCY_BUCKETS)
+                .and_then(|b| b.set_buckets_for_metric(Matcher::Full(QUERY_RESULTS.to_string()), RESULT_COUNT_BUCKETS))
+                .expect("histogram buckets are non-empty")
+                .install_recorder()
+                .expect("failed to install Prometheus recorder")
+        })
+        .clone()
+}
+
+/// Count ingested rows of one kind
+pub fn record_ingested(kind: &'static str, count: usize) {
+    if count > 0 {
+        metrics::counter!(INGESTED_TOTAL, "kind" => kind).increment(count as u64);
+    }
+}
+
+/// Record one embedding server call
+pub fn record_embed_request(elapsed: Duration, success: bool) {
+    let outcome = if success { "success" } else { "error" };
+    metrics::counter!(EMBED_REQUESTS_TOTAL, "outcome" => outcome).increment(1);
+    metrics::histogram!(EMBED_DURATION_SECONDS).record(elapsed.as_secs_f64());
+}
+
+/// Record an embedding served by the placeholder vector
+pub fn record_embed_placeholder() {
+    metrics::counter!(EMBED_PLACEHOLDER_TOTAL).increment(1);
+}
+
+/// Record an LSH lookup that fell back to a full scan
+pub fn record_lsh_fallback(endpoint: &'static str) {
+    metrics::counter!(LSH_FALLBACK_TOTAL, "endpoint" => endpoint).increment(1);
+}
+
+/// Record a query cache lookup
+pub fn record_query_cache(hit: bool) {
+    let outcome = if hit { "hit" } else { "miss" };
+    metrics::counter!(QUERY_CACHE_TOTAL, "outcome" => outcome).increment(1);
+}
+
+/// Record how many results a query returned
+pub fn record_query_results(endpoint: &'static str, count: usize) {
+    metrics::histogram!(QUERY_RESULTS, "endpoint" => endpoint).record(count as f64);
+}
+
+/// Records the query latency for `endpoint` when dropped, so every return path is timed
+pub struct QueryTimer {
+    endpoint: &'static str,
+    start: Instant,
+}
+
+/// Start timing a query
+pub fn time_query(endpoint: &'static str) -> QueryTimer {
+    QueryTimer { endpoint, start: Instant::now() }
+}
+
+impl Drop for QueryTimer {
+    fn drop(&mut self) {
+        metrics::histogram!(QUERY_DURATION_SECONDS, "endpoint" => self.endpoint)
+            .record(self.start.elapsed().as_secs_f64());
+    }
+}